@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+
+ALTER TABLE commits
+    DROP CONSTRAINT ak_repository_commit_hash;
@@ -0,0 +1,11 @@
+-- Your SQL goes here
+
+DELETE
+FROM commits c
+    USING commits d
+WHERE c.repository_id = d.repository_id
+  AND c.hash = d.hash
+  AND c.id > d.id;
+
+ALTER TABLE commits
+    ADD CONSTRAINT ak_repository_commit_hash UNIQUE (repository_id, hash);
@@ -0,0 +1,21 @@
+-- Your SQL goes here
+
+CREATE TABLE ingest_jobs
+(
+    id            SERIAL PRIMARY KEY,
+    sync_client   INTEGER     NOT NULL REFERENCES sync_clients (id) ON UPDATE CASCADE ON DELETE CASCADE,
+    kind          TEXT        NOT NULL,
+    status        TEXT        NOT NULL DEFAULT 'queued',
+    payload       TEXT        NULL,
+    commits_total INTEGER     NOT NULL DEFAULT 0,
+    inserted      INTEGER     NOT NULL DEFAULT 0,
+    updated       INTEGER     NOT NULL DEFAULT 0,
+    skipped       INTEGER     NOT NULL DEFAULT 0,
+    repository_id INTEGER     NULL REFERENCES repositories (id) ON UPDATE CASCADE ON DELETE SET NULL,
+    errors        TEXT        NULL,
+    added_at      TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    started_at    TIMESTAMPTZ NULL,
+    finished_at   TIMESTAMPTZ NULL
+);
+
+CREATE INDEX idx_ingest_jobs_status ON ingest_jobs (status, id);
@@ -0,0 +1,9 @@
+-- This file should undo anything in `up.sql`
+
+DROP INDEX idx_commits_note_digest;
+
+ALTER TABLE commits
+    DROP CONSTRAINT fk_commits_superseded_by,
+    DROP COLUMN is_superseded,
+    DROP COLUMN superseded_by,
+    DROP COLUMN note_digest;
@@ -0,0 +1,13 @@
+-- Your SQL goes here
+
+ALTER TABLE commits
+    ADD COLUMN is_superseded BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN superseded_by INTEGER NULL,
+    ADD COLUMN note_digest   TEXT    NULL,
+    ADD CONSTRAINT fk_commits_superseded_by FOREIGN KEY (superseded_by) REFERENCES commits (id) ON DELETE SET NULL;
+
+-- note_digest is computed by the API from a commit's files, so it is not backfilled here. Existing
+-- commits get theirs from the stored files and timeline on the next sync of their repository
+-- (commit::db::backfill_note_digests), before superseded commits are looked for.
+
+CREATE INDEX idx_commits_note_digest ON commits (repository_id, note_digest);
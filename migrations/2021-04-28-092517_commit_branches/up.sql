@@ -0,0 +1,17 @@
+-- Your SQL goes here
+
+CREATE TABLE commit_branches
+(
+    commit   INTEGER                  NOT NULL,
+    branch   TEXT                     NOT NULL,
+    added_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    CONSTRAINT pk_commit_branches PRIMARY KEY (commit, branch),
+    CONSTRAINT fk_commit_branches_commit FOREIGN KEY (commit) REFERENCES commits (id) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_commit_branches_branch ON commit_branches (branch);
+
+INSERT INTO commit_branches (commit, branch)
+SELECT id, branch
+FROM commits
+WHERE branch <> '';
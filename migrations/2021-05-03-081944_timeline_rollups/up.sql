@@ -0,0 +1,32 @@
+-- Your SQL goes here
+
+CREATE TABLE timeline_rollups
+(
+    repository_id INTEGER NOT NULL,
+    commit        INTEGER NOT NULL,
+    directory     TEXT    NOT NULL,
+    is_app        BOOLEAN NOT NULL,
+    hour          BIGINT  NOT NULL,
+    time          BIGINT  NOT NULL,
+    lines_added   BIGINT  NOT NULL,
+    lines_deleted BIGINT  NOT NULL,
+    CONSTRAINT pk_timeline_rollups PRIMARY KEY (commit, directory, is_app, hour),
+    CONSTRAINT fk_timeline_rollups_repository FOREIGN KEY (repository_id) REFERENCES repositories (id) ON DELETE CASCADE,
+    CONSTRAINT fk_timeline_rollups_commit FOREIGN KEY (commit) REFERENCES commits (id) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_timeline_rollups_repository_hour ON timeline_rollups (repository_id, hour);
+
+INSERT INTO timeline_rollups (repository_id, commit, directory, is_app, hour, time, lines_added, lines_deleted)
+SELECT commits.repository_id,
+       commits.id,
+       split_part(regexp_replace(files.path, '^(\./)+', ''), '/', 1),
+       files.path LIKE '%.app',
+       timeline.timestamp - timeline.timestamp % 3600,
+       sum(timeline.time),
+       sum(files.lines_added),
+       sum(files.lines_deleted)
+FROM timeline
+    INNER JOIN files ON timeline.file = files.id
+    INNER JOIN commits ON files.commit = commits.id
+GROUP BY 1, 2, 3, 4, 5;
@@ -0,0 +1,42 @@
+-- Your SQL goes here
+
+CREATE TABLE language_mappings
+(
+    id        SERIAL PRIMARY KEY,
+    extension TEXT NOT NULL,
+    language  TEXT NOT NULL,
+    CONSTRAINT uq_language_mappings_extension UNIQUE (extension)
+);
+
+INSERT INTO language_mappings (extension, language)
+VALUES ('rs', 'Rust'),
+       ('java', 'Java'),
+       ('kt', 'Kotlin'),
+       ('scala', 'Scala'),
+       ('py', 'Python'),
+       ('js', 'JavaScript'),
+       ('jsx', 'JavaScript'),
+       ('ts', 'TypeScript'),
+       ('tsx', 'TypeScript'),
+       ('c', 'C'),
+       ('h', 'C'),
+       ('cpp', 'C++'),
+       ('hpp', 'C++'),
+       ('cs', 'C#'),
+       ('go', 'Go'),
+       ('rb', 'Ruby'),
+       ('php', 'PHP'),
+       ('swift', 'Swift'),
+       ('hs', 'Haskell'),
+       ('sql', 'SQL'),
+       ('sh', 'Shell'),
+       ('html', 'HTML'),
+       ('css', 'CSS'),
+       ('scss', 'CSS'),
+       ('vue', 'Vue'),
+       ('md', 'Markdown'),
+       ('json', 'JSON'),
+       ('xml', 'XML'),
+       ('yml', 'YAML'),
+       ('yaml', 'YAML'),
+       ('toml', 'TOML');
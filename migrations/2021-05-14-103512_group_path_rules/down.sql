@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+
+DROP TABLE group_path_rules;
+
+ALTER TABLE groups DROP COLUMN include_apps;
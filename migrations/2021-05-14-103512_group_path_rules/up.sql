@@ -0,0 +1,15 @@
+-- Your SQL goes here
+
+ALTER TABLE groups ADD COLUMN include_apps BOOLEAN NOT NULL DEFAULT TRUE;
+
+CREATE TABLE group_path_rules
+(
+    id        SERIAL PRIMARY KEY,
+    "group"   INTEGER NOT NULL REFERENCES groups ON DELETE CASCADE,
+    pattern   TEXT    NOT NULL,
+    regex     TEXT    NOT NULL,
+    include   BOOLEAN NOT NULL,
+    CONSTRAINT uq_group_path_rules_pattern UNIQUE ("group", pattern, include)
+);
+
+CREATE INDEX idx_group_path_rules_group ON group_path_rules ("group");
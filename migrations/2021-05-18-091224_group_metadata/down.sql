@@ -0,0 +1,10 @@
+-- This file should undo anything in `up.sql`
+
+DROP INDEX idx_groups_tags;
+
+ALTER TABLE groups
+    DROP COLUMN display_name,
+    DROP COLUMN description,
+    DROP COLUMN tags,
+    DROP COLUMN period_start,
+    DROP COLUMN period_end;
@@ -0,0 +1,10 @@
+-- Your SQL goes here
+
+ALTER TABLE groups
+    ADD COLUMN display_name TEXT                     NULL,
+    ADD COLUMN description  TEXT                     NULL,
+    ADD COLUMN tags         TEXT[]                   NOT NULL DEFAULT '{}',
+    ADD COLUMN period_start TIMESTAMP WITH TIME ZONE NULL,
+    ADD COLUMN period_end   TIMESTAMP WITH TIME ZONE NULL;
+
+CREATE INDEX idx_groups_tags ON groups USING GIN (tags);
@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+
+ALTER TABLE ingest_jobs
+    DROP COLUMN commits_processed,
+    DROP COLUMN attempts,
+    DROP COLUMN heartbeat_at;
@@ -0,0 +1,6 @@
+-- Your SQL goes here
+
+ALTER TABLE ingest_jobs
+    ADD COLUMN commits_processed INTEGER     NOT NULL DEFAULT 0,
+    ADD COLUMN attempts          INTEGER     NOT NULL DEFAULT 0,
+    ADD COLUMN heartbeat_at      TIMESTAMPTZ NULL;
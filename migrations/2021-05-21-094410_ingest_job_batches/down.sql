@@ -0,0 +1,10 @@
+-- This file should undo anything in `up.sql`
+
+UPDATE ingest_jobs
+SET payload = jsonb_set(payload::JSONB, '{commits}', COALESCE((
+    SELECT jsonb_agg(c.value ORDER BY b.seq, c.idx)
+    FROM ingest_job_batches b, jsonb_array_elements(b.payload::JSONB) WITH ORDINALITY c(value, idx)
+    WHERE b.job = ingest_jobs.id), '[]'::JSONB))::TEXT
+WHERE payload IS NOT NULL;
+
+DROP TABLE IF EXISTS ingest_job_batches;
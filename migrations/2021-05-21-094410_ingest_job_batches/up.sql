@@ -0,0 +1,20 @@
+-- Your SQL goes here
+
+CREATE TABLE ingest_job_batches
+(
+    job     INTEGER NOT NULL REFERENCES ingest_jobs (id) ON UPDATE CASCADE ON DELETE CASCADE,
+    seq     INTEGER NOT NULL,
+    payload TEXT    NOT NULL,
+    PRIMARY KEY (job, seq)
+);
+
+-- Pending jobs keep their commits as a single batch, the job payload only describes the repository
+INSERT INTO ingest_job_batches (job, seq, payload)
+SELECT id, 0, (payload::JSONB -> 'commits')::TEXT
+FROM ingest_jobs
+WHERE payload IS NOT NULL
+  AND payload::JSONB ? 'commits';
+
+UPDATE ingest_jobs
+SET payload = (payload::JSONB - 'commits')::TEXT
+WHERE payload IS NOT NULL;
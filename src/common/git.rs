@@ -6,6 +6,7 @@ use regex::Regex;
 lazy_static! {
     static ref PATH_FROM_URL_REGEX: Regex =
         Regex::new(r#"(git@|https://)([a-zA-Z0-9.]+)[:/]([a-zA-Z0-9-_/.]+)/([a-zA-Z0-9-._]+)\.git"#).unwrap();
+    static ref GIT_USER_NAME_EMAIL_REGEX: Regex = Regex::new("^(.*)\\s+<(.*)>$").unwrap();
 }
 
 #[derive(Serialize, Clone, JsonSchema)]
@@ -24,6 +25,12 @@ pub fn generate_credentials_from_clone_url(url: &str) -> Option<RepoCredentials>
     });
 }
 
+/// Splits a git author string `name <email>` into name and email.
+pub fn parse_author(author: &str) -> Option<(String, String)> {
+    let caps = GIT_USER_NAME_EMAIL_REGEX.captures(author)?;
+    Some((caps.get(1)?.as_str().to_string(), caps.get(2)?.as_str().to_string()))
+}
+
 pub fn generate_group_name(provider: &str, user: &str, repo: &str) -> String {
     format!("{}-{}-{}", provider, user.replace("/", "-"), repo)
 }
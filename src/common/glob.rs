@@ -0,0 +1,47 @@
+const MAX_GLOB_LENGTH: usize = 256;
+
+/// Converts a gitignore style glob to a POSIX regex matched against paths relative to the
+/// repository root. Globs without an inner slash match at any depth, a leading slash anchors
+/// them to the root and a trailing slash only matches directories. `*` and `?` stay within a
+/// path segment, `**` also crosses them. A glob naming a directory matches everything in it.
+pub fn glob_to_regex(glob: &str) -> Result<String, &'static str> {
+    let glob = glob.trim();
+    if glob.len() > MAX_GLOB_LENGTH {
+        return Err("Pattern is too long!");
+    }
+    let directory = glob.ends_with('/');
+    let glob = glob.trim_end_matches('/');
+    let anchored = glob.contains('/');
+    let glob = glob.trim_start_matches('/');
+    if glob.is_empty() {
+        return Err("Pattern can't be empty!");
+    }
+
+    let mut regex = String::from(if anchored { "^" } else { "(^|/)" });
+    let chars: Vec<char> = glob.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '*' if chars.get(i + 1) == Some(&'*') => {
+                if chars.get(i + 2) == Some(&'/') {
+                    regex.push_str("(.*/)?");
+                    i += 3;
+                } else {
+                    regex.push_str(".*");
+                    i += 2;
+                }
+                continue;
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            c if "\\.+()|[]{}^$".contains(c) => {
+                regex.push('\\');
+                regex.push(c);
+            }
+            c => regex.push(c),
+        }
+        i += 1;
+    }
+    regex.push_str(if directory { "/" } else { "($|/)" });
+    Ok(regex)
+}
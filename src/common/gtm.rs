@@ -0,0 +1,229 @@
+use std::collections::{BTreeMap, HashMap};
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+lazy_static! {
+    static ref NOTE_HEADER_REGEX: Regex = Regex::new(r"^\[ver:(\d+),total:(\d+)\]$").unwrap();
+}
+
+/// Time data of a single file as stored in a gtm note.
+#[derive(Debug, PartialEq)]
+pub struct NoteFile {
+    pub path: String,
+    pub time_total: i64,
+    /// Seconds spent on the file, keyed by the epoch of the hour they were spent in.
+    pub timeline: BTreeMap<i64, i64>,
+    pub status: String,
+}
+
+/// Parses a `git notes --ref=gtm-data` note.
+///
+/// A note consists of one or more blocks, each starting with a `[ver:N,total:T]` header
+/// followed by a line per file: `path:time,epoch:time,...,status`. Notes of amended or
+/// squashed commits contain several blocks, times of the same file are summed up.
+pub fn parse_note(note: &str) -> Result<Vec<NoteFile>, &'static str> {
+    let mut files: Vec<NoteFile> = vec![];
+    let mut block_total: Option<i64> = None;
+    let mut block_sum = 0;
+
+    for line in note.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        if let Some(caps) = NOTE_HEADER_REGEX.captures(line) {
+            check_block_total(block_total, block_sum)?;
+            if &caps[1] != "1" && &caps[1] != "2" {
+                return Err("unsupported note version");
+            }
+            block_total = Some(caps[2].parse().map_err(|_| "invalid note total")?);
+            block_sum = 0;
+            continue;
+        }
+        if block_total.is_none() {
+            return Err("missing note header");
+        }
+
+        let file = parse_note_line(line)?;
+        block_sum += file.time_total;
+        match files.iter_mut().find(|f| f.path == file.path) {
+            Some(existing) => {
+                existing.time_total += file.time_total;
+                for (epoch, time) in file.timeline {
+                    *existing.timeline.entry(epoch).or_insert(0) += time;
+                }
+                existing.status = file.status;
+            }
+            None => files.push(file),
+        }
+    }
+    check_block_total(block_total, block_sum)?;
+
+    Ok(files)
+}
+
+fn check_block_total(total: Option<i64>, sum: i64) -> Result<(), &'static str> {
+    match total {
+        Some(total) if total != sum => Err("note total does not match file times"),
+        _ => Ok(()),
+    }
+}
+
+fn parse_note_line(line: &str) -> Result<NoteFile, &'static str> {
+    let mut fields: Vec<&str> = line.split(',').collect();
+    // Version 1 notes have no status field, files in them are always modified.
+    let status = match fields.last() {
+        Some(last) if fields.len() > 1 && !last.contains(':') => fields.pop().unwrap().to_string(),
+        _ => "m".to_string(),
+    };
+
+    let (path, time_total) = split_pair(fields[0]).ok_or("invalid file entry")?;
+    if path.is_empty() {
+        return Err("invalid file entry");
+    }
+    let time_total = time_total.parse::<i64>().map_err(|_| "invalid file time")?;
+
+    let mut timeline = BTreeMap::new();
+    for field in &fields[1..] {
+        let (epoch, time) = split_pair(field).ok_or("invalid timeline entry")?;
+        let epoch = epoch.parse::<i64>().map_err(|_| "invalid timeline entry")?;
+        let time = time.parse::<i64>().map_err(|_| "invalid timeline entry")?;
+        *timeline.entry(epoch).or_insert(0) += time;
+    }
+
+    Ok(NoteFile {
+        path: path.to_string(),
+        time_total,
+        timeline,
+        status,
+    })
+}
+
+/// Splits `key:value` at the last colon, so paths containing colons stay intact.
+fn split_pair(field: &str) -> Option<(&str, &str)> {
+    let idx = field.rfind(':')?;
+    Some((&field[..idx], &field[idx + 1..]))
+}
+
+/// Parses `git log --numstat` output into added and deleted lines per path.
+/// Binary files, reported as `-`, count as zero lines.
+pub fn parse_numstat(numstat: &str) -> Result<HashMap<String, (i64, i64)>, &'static str> {
+    let mut lines = HashMap::new();
+    for line in numstat.lines().filter(|l| !l.trim().is_empty()) {
+        let mut fields = line.splitn(3, '\t');
+        let added = parse_numstat_count(fields.next())?;
+        let deleted = parse_numstat_count(fields.next())?;
+        let path = fields.next().filter(|p| !p.is_empty()).ok_or("invalid numstat line")?;
+        lines.insert(path.to_string(), (added, deleted));
+    }
+    Ok(lines)
+}
+
+fn parse_numstat_count(field: Option<&str>) -> Result<i64, &'static str> {
+    match field {
+        Some("-") => Ok(0),
+        Some(count) => count.parse().map_err(|_| "invalid numstat line"),
+        None => Err("invalid numstat line"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timeline(entries: &[(i64, i64)]) -> BTreeMap<i64, i64> {
+        entries.iter().cloned().collect()
+    }
+
+    #[test]
+    fn test_parse_note_sums_blocks() {
+        let note = "[ver:2,total:480]\n\
+                    src/main.rs:420,1585861200:300,1585864800:120,m\n\
+                    README.md:60,1585861200:60,r\n\
+                    [ver:2,total:30]\n\
+                    src/main.rs:30,1585864800:30,d\n";
+
+        let files = parse_note(note).unwrap();
+        assert_eq!(files, vec![
+            NoteFile {
+                path: "src/main.rs".to_string(),
+                time_total: 450,
+                timeline: timeline(&[(1585861200, 300), (1585864800, 150)]),
+                status: "d".to_string(),
+            },
+            NoteFile {
+                path: "README.md".to_string(),
+                time_total: 60,
+                timeline: timeline(&[(1585861200, 60)]),
+                status: "r".to_string(),
+            },
+        ]);
+    }
+
+    #[test]
+    fn test_parse_note_v1_without_status() {
+        let files = parse_note("[ver:1,total:90]\nsrc/lib.rs:90,1585861200:60,1585864800:30\n").unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].path, "src/lib.rs");
+        assert_eq!(files[0].status, "m");
+        assert_eq!(files[0].timeline, timeline(&[(1585861200, 60), (1585864800, 30)]));
+    }
+
+    #[test]
+    fn test_parse_note_path_with_colons() {
+        let note = "[ver:2,total:100]\n\
+                    docs/a:b.md:60,1585861200:60,m\n\
+                    c:d.txt:40,1585861200:40\n";
+
+        let files = parse_note(note).unwrap();
+        assert_eq!(files[0].path, "docs/a:b.md");
+        assert_eq!(files[0].time_total, 60);
+        assert_eq!(files[0].status, "m");
+        assert_eq!(files[1].path, "c:d.txt");
+        assert_eq!(files[1].time_total, 40);
+        assert_eq!(files[1].status, "m");
+    }
+
+    #[test]
+    fn test_parse_note_mismatched_total() {
+        assert_eq!(
+            parse_note("[ver:2,total:100]\nsrc/main.rs:60,1585861200:60,m\n"),
+            Err("note total does not match file times"),
+        );
+        // Every block is checked, not only the last one.
+        let note = "[ver:2,total:100]\n\
+                    src/main.rs:60,1585861200:60,m\n\
+                    [ver:2,total:30]\n\
+                    src/main.rs:30,1585861200:30,m\n";
+        assert_eq!(parse_note(note), Err("note total does not match file times"));
+    }
+
+    #[test]
+    fn test_parse_note_malformed_entries() {
+        assert_eq!(
+            parse_note("[ver:2,total:60]\nsrc/main.rs:60,x1585861200:60,m\n"),
+            Err("invalid timeline entry"),
+        );
+        assert_eq!(
+            parse_note("[ver:2,total:60]\nsrc/main.rs:60,1585861200,m\n"),
+            Err("invalid timeline entry"),
+        );
+        assert_eq!(
+            parse_note("[ver:2,total:60]\nsrc/main.rs:60,1585861200:1m,m\n"),
+            Err("invalid timeline entry"),
+        );
+        assert_eq!(parse_note("[ver:2,total:60]\nsrc/main.rs:abc,m\n"), Err("invalid file time"));
+        assert_eq!(parse_note("[ver:2,total:60]\n:60,1585861200:60,m\n"), Err("invalid file entry"));
+        assert_eq!(parse_note("src/main.rs:60,1585861200:60,m\n"), Err("missing note header"));
+        assert_eq!(parse_note("[ver:3,total:60]\nsrc/main.rs:60,1585861200:60,m\n"), Err("unsupported note version"));
+    }
+
+    #[test]
+    fn test_parse_numstat() {
+        let lines = parse_numstat("12\t3\tsrc/main.rs\n-\t-\tlogo.png\n0\t7\tdocs/a b.md\n").unwrap();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines["src/main.rs"], (12, 3));
+        assert_eq!(lines["logo.png"], (0, 0));
+        assert_eq!(lines["docs/a b.md"], (0, 7));
+
+        assert_eq!(parse_numstat("12\tsrc/main.rs\n"), Err("invalid numstat line"));
+        assert_eq!(parse_numstat("x\t3\tsrc/main.rs\n"), Err("invalid numstat line"));
+    }
+}
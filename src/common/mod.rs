@@ -1,4 +1,7 @@
 pub mod sql;
+pub mod glob;
 pub mod random;
 pub mod json;
-pub mod git;
\ No newline at end of file
+pub mod git;
+pub mod gtm;
+pub mod sync_json;
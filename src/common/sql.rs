@@ -33,3 +33,72 @@ pub const GROUP_PARENTS_QUERY: &str =
                        ON m.child = group_parents_query.parent
          WHERE group_parents_query.depth < 100
      )";
+
+/// Keeps commits reachable from one of the branches bound as `$4`, a NULL array keeps all.
+pub const BRANCH_FILTER: &str =
+    "AND ($4::TEXT[] IS NULL OR EXISTS (
+        SELECT 1
+        FROM commit_branches
+        WHERE commit_branches.commit = commits.id
+          AND commit_branches.branch = ANY ($4)))";
+
+/// Language of `files.path` according to `language_mappings`, app time counts as `App` and
+/// unmapped extensions as `Other`.
+pub const FILE_LANGUAGE: &str =
+    "CASE WHEN files.path LIKE '%.app' THEN 'App'
+          ELSE coalesce((
+              SELECT language_mappings.language
+              FROM language_mappings
+              WHERE language_mappings.extension = lower(substring(files.path FROM '\\.([^./]+)$'))),
+              'Other')
+     END";
+
+/// Matches test sources by their directory (`test/`, `tests/`, `__tests__/`, `spec/`) or by
+/// their name (`foo_test.go`, `foo.spec.ts`, `FooTest.java`, `test_foo.py`).
+pub const TEST_FILE_PATTERN: &str =
+    "(^|/)(tests?|__tests__|spec)/|[._-](test|spec)\\.[^./]+$|[a-z0-9]Tests?\\.[^./]+$|(^|/)test_[^/]+$";
+
+/// Keeps files of one of the lowercase languages bound as `$5`, a NULL array keeps all.
+pub fn language_filter() -> String {
+    format!("AND ($5::TEXT[] IS NULL OR lower({}) = ANY ($5))", FILE_LANGUAGE)
+}
+
+/// Id of the group named `$1`, whose path rules apply to group queries.
+pub const QUERIED_GROUP: &str = "(SELECT groups.id FROM groups WHERE groups.name = $1)";
+
+/// Id of the group of the repository `$1`, whose path rules apply to repository queries.
+pub const REPOSITORY_GROUP: &str =
+    "(SELECT repositories.group FROM repositories WHERE repositories.id = $1)";
+
+/// Drops files excluded by the path rules of the group `group`, an SQL expression of its id. App
+/// time is kept when the group includes apps, regardless of the rules.
+pub fn path_rules_filter(group: &str) -> String {
+    format!("
+        AND (files.path IS NULL OR CASE
+            WHEN files.path LIKE '%.app' THEN (SELECT groups.include_apps FROM groups WHERE groups.id = {})
+            ELSE {}
+        END)", group, code_path_rules(group))
+}
+
+/// Whether `files.path` is kept by the path rules of the group `group`: no exclude rule matches
+/// it and, if the group has include rules, one of them does.
+pub fn code_path_rules(group: &str) -> String {
+    format!("
+        NOT EXISTS (
+            SELECT 1
+            FROM group_path_rules
+            WHERE group_path_rules.group = {group}
+              AND NOT group_path_rules.include
+              AND regexp_replace(files.path, '^(\\./|/)+', '') ~ group_path_rules.regex)
+        AND (NOT EXISTS (
+                SELECT 1
+                FROM group_path_rules
+                WHERE group_path_rules.group = {group}
+                  AND group_path_rules.include)
+            OR EXISTS (
+                SELECT 1
+                FROM group_path_rules
+                WHERE group_path_rules.group = {group}
+                  AND group_path_rules.include
+                  AND regexp_replace(files.path, '^(\\./|/)+', '') ~ group_path_rules.regex))", group = group)
+}
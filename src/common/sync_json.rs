@@ -0,0 +1,150 @@
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::io::{self, BufReader, Read};
+use std::marker::PhantomData;
+use std::rc::Rc;
+
+use flate2::read::GzDecoder;
+use okapi::Map;
+use okapi::openapi3::{MediaType, RequestBody};
+use rocket::{Data, Outcome, Request};
+use rocket::data::{self, FromDataSimple};
+use rocket::http::Status;
+use rocket_okapi::gen::OpenApiGenerator;
+use rocket_okapi::request::OpenApiFromData;
+use schemars::JsonSchema;
+use serde::de::DeserializeSeed;
+
+use crate::errors::Error;
+
+/// Default limit of the request body as it arrives over the wire.
+const DEFAULT_LIMIT: u64 = 32 * 1024 * 1024;
+/// Default limit of the body after `Content-Encoding` has been removed.
+const DEFAULT_DECODED_LIMIT: u64 = 256 * 1024 * 1024;
+
+enum Encoding {
+    Identity,
+    Gzip,
+    Zstd,
+}
+
+impl Encoding {
+    fn from_request(request: &Request) -> Option<Encoding> {
+        match request.headers().get_one("Content-Encoding").map(|e| e.trim().to_lowercase()) {
+            None => Some(Encoding::Identity),
+            Some(e) => match e.as_str() {
+                "" | "identity" => Some(Encoding::Identity),
+                "gzip" | "x-gzip" => Some(Encoding::Gzip),
+                "zstd" => Some(Encoding::Zstd),
+                _ => None,
+            },
+        }
+    }
+}
+
+/// Reader that fails once more than `limit` bytes have been read and remembers that it did.
+struct LimitedReader<R> {
+    inner: R,
+    remaining: u64,
+    exceeded: Rc<Cell<bool>>,
+}
+
+impl<R: Read> LimitedReader<R> {
+    fn new(inner: R, limit: u64, exceeded: Rc<Cell<bool>>) -> LimitedReader<R> {
+        LimitedReader { inner, remaining: limit, exceeded }
+    }
+}
+
+impl<R: Read> Read for LimitedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        if read as u64 > self.remaining {
+            self.exceeded.set(true);
+            return Err(io::Error::new(io::ErrorKind::Other, "payload too large"));
+        }
+        self.remaining -= read as u64;
+        Ok(read)
+    }
+}
+
+/// JSON data guard for sync uploads.
+///
+/// Accepts plain, `gzip` and `zstd` encoded bodies. The guard only opens the decoding stream, the
+/// handler deserializes it with a [`DeserializeSeed`] that consumes the payload piece by piece, so
+/// neither the raw body nor the whole deserialized value is ever held. The encoded and decoded
+/// sizes are capped by the `sync` and `sync-decoded` limits, exceeding either one fails with 413.
+/// `T` documents the payload in the OpenAPI spec.
+pub struct SyncStream<T> {
+    reader: BufReader<LimitedReader<Box<dyn Read>>>,
+    exceeded: Rc<Cell<bool>>,
+    payload: PhantomData<T>,
+}
+
+impl<T> SyncStream<T> {
+    /// Deserializes the body with `seed`. Errors raised by the seed itself should be kept by the
+    /// seed, the returned error then only describes where deserialization stopped.
+    pub fn deserialize<S>(self, seed: S) -> Result<S::Value, Error>
+        where S: for<'de> DeserializeSeed<'de>,
+    {
+        let mut deserializer = serde_json::Deserializer::from_reader(self.reader);
+        let value = seed.deserialize(&mut deserializer)
+            .and_then(|value| deserializer.end().map(|_| value));
+
+        match value {
+            Ok(value) => Ok(value),
+            Err(_) if self.exceeded.get() => Err(Error::PayloadTooLarge),
+            Err(e) if e.is_io() => Err(Error::BadRequest("Payload could not be read")),
+            Err(e) => {
+                let mut errors = BTreeMap::new();
+                errors.insert("body".to_string(), vec![e.to_string()]);
+                Err(Error::PayloadValidationError(errors))
+            }
+        }
+    }
+}
+
+impl<T> FromDataSimple for SyncStream<T> {
+    type Error = String;
+
+    fn from_data(request: &Request, data: Data) -> data::Outcome<Self, String> {
+        let encoding = match Encoding::from_request(request) {
+            Some(encoding) => encoding,
+            None => return Outcome::Failure((
+                Status::UnsupportedMediaType,
+                "Unsupported Content-Encoding".to_string(),
+            )),
+        };
+        let limit = request.limits().get("sync").unwrap_or(DEFAULT_LIMIT);
+        let decoded_limit = request.limits().get("sync-decoded").unwrap_or(DEFAULT_DECODED_LIMIT);
+
+        let exceeded = Rc::new(Cell::new(false));
+        let raw = LimitedReader::new(data.open(), limit, exceeded.clone());
+        let decoded: Box<dyn Read> = match encoding {
+            Encoding::Identity => Box::new(raw),
+            Encoding::Gzip => Box::new(GzDecoder::new(raw)),
+            Encoding::Zstd => match zstd::stream::read::Decoder::new(raw) {
+                Ok(decoder) => Box::new(decoder),
+                Err(e) => return Outcome::Failure((Status::BadRequest, e.to_string())),
+            },
+        };
+        let reader = BufReader::new(LimitedReader::new(decoded, decoded_limit, exceeded.clone()));
+
+        Outcome::Success(SyncStream { reader, exceeded, payload: PhantomData })
+    }
+}
+
+impl<'a, T: JsonSchema> OpenApiFromData<'a> for SyncStream<T> {
+    fn request_body(gen: &mut OpenApiGenerator) -> rocket_okapi::Result<RequestBody> {
+        let schema = gen.json_schema::<T>();
+        let mut content = Map::new();
+        content.insert("application/json".to_owned(), MediaType {
+            schema: Some(schema),
+            ..MediaType::default()
+        });
+        Ok(RequestBody {
+            content,
+            required: true,
+            ..RequestBody::default()
+        })
+    }
+}
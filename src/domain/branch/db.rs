@@ -0,0 +1,62 @@
+use diesel;
+use diesel::{Insertable, sql_query, sql_types};
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+
+use crate::common::sql::GROUP_CHILDREN_QUERY;
+use crate::domain::branch::dwh::BranchStats;
+use crate::errors::Error;
+use crate::schema::commit_branches;
+
+// Postgres accepts at most 65535 bind parameters per statement
+const INSERT_BATCH_SIZE: usize = 65535 / 2;
+
+#[derive(Insertable)]
+#[table_name = "commit_branches"]
+struct NewCommitBranch {
+    commit: i32,
+    branch: String,
+}
+
+/// Links commits to the branches they are reachable from, known links are left as they are.
+pub fn create_all(conn: &PgConnection, branches: Vec<(i32, String)>) -> Result<usize, Error> {
+    let new_branches: Vec<NewCommitBranch> = branches.into_iter()
+        .map(|(commit, branch)| NewCommitBranch { commit, branch })
+        .collect();
+
+    let mut count = 0;
+    for chunk in new_branches.chunks(INSERT_BATCH_SIZE) {
+        count += diesel::insert_into(commit_branches::table)
+            .values(chunk)
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+    }
+    Ok(count)
+}
+
+pub fn fetch_group_branches(conn: &PgConnection, group_name: &str) -> Result<Vec<BranchStats>, Error> {
+    let branches: Vec<BranchStats> = sql_query(format!("
+        {}
+        SELECT commit_branches.branch                         AS name,
+            count(DISTINCT commits.id)::bigint                AS commits,
+            count(DISTINCT commits.email)::bigint             AS users,
+            max(commits.timestamp)::bigint                    AS last_activity
+        FROM commit_branches
+            INNER JOIN commits ON commit_branches.commit = commits.id
+            INNER JOIN repositories ON commits.repository_id = repositories.id
+        WHERE repositories.group IN (
+            SELECT DISTINCT group_repos_query.child
+            FROM group_repos_query
+            UNION
+            (
+                SELECT g.id
+                FROM groups g
+                WHERE g.name = $1))
+            AND NOT commits.is_superseded
+        GROUP BY commit_branches.branch
+        ORDER BY last_activity DESC;", GROUP_CHILDREN_QUERY))
+        .bind::<sql_types::Text, _>(group_name)
+        .load(conn)?;
+
+    Ok(branches)
+}
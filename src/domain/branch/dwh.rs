@@ -0,0 +1,13 @@
+use diesel::sql_types::{BigInt, Text};
+
+#[derive(QueryableByName, Debug)]
+pub struct BranchStats {
+    #[sql_type = "Text"]
+    pub name: String,
+    #[sql_type = "BigInt"]
+    pub commits: i64,
+    #[sql_type = "BigInt"]
+    pub users: i64,
+    #[sql_type = "BigInt"]
+    pub last_activity: i64,
+}
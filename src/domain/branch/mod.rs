@@ -0,0 +1,5 @@
+pub mod db;
+pub mod dwh;
+pub mod resource;
+pub mod routes;
+pub mod service;
@@ -0,0 +1,24 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::domain::branch::dwh::BranchStats;
+
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BranchJson {
+    pub name: String,
+    pub commits: i64,
+    pub users: i64,
+    pub last_activity: i64,
+}
+
+impl From<BranchStats> for BranchJson {
+    fn from(stats: BranchStats) -> Self {
+        BranchJson {
+            name: stats.name,
+            commits: stats.commits,
+            users: stats.users,
+            last_activity: stats.last_activity,
+        }
+    }
+}
@@ -0,0 +1,24 @@
+use rocket_contrib::json::Json;
+use rocket_okapi::openapi;
+
+use crate::domain::branch;
+use crate::domain::branch::resource::BranchJson;
+use crate::domain::db::Conn;
+use crate::domain::role::model::ADMIN;
+use crate::domain::user::model::AuthUser;
+use crate::errors::Error;
+use crate::security;
+
+#[openapi]
+#[get("/groups/<group_name>/branches")]
+pub fn get_group_branches(
+    auth_user: AuthUser,
+    conn: Conn,
+    group_name: String,
+) -> Result<Json<Vec<BranchJson>>, Error> {
+    if auth_user.require_role(&ADMIN).is_err() {
+        security::service::check_group_access(&conn, auth_user.user_id, &group_name)?;
+    }
+    let branches = branch::service::get_group_branches(&conn, &group_name)?;
+    Ok(Json(branches))
+}
@@ -0,0 +1,12 @@
+use diesel::PgConnection;
+
+use crate::domain::branch;
+use crate::domain::branch::resource::BranchJson;
+use crate::errors::Error;
+
+pub fn get_group_branches(conn: &PgConnection, group_name: &str) -> Result<Vec<BranchJson>, Error> {
+    Ok(branch::db::fetch_group_branches(conn, group_name)?
+        .into_iter()
+        .map(BranchJson::from)
+        .collect())
+}
@@ -1,31 +1,46 @@
+use std::collections::{HashMap, HashSet};
+
+use crypto::digest::Digest;
+use crypto::sha2::Sha256;
 use diesel;
-use diesel::Insertable;
-use diesel::pg::PgConnection;
+use diesel::{Insertable, sql_query, sql_types};
+use diesel::pg::{Pg, PgConnection};
 use diesel::prelude::*;
-use lazy_static::lazy_static;
-use regex::Regex;
+use itertools::Itertools;
 
+use crate::common::git;
+use crate::domain::branch;
 use crate::domain::commit::model::{Commit};
-use crate::domain::commit::routes::NewCommitData;
+use crate::domain::commit::routes::{CommitListParams, NewCommitData};
 use crate::domain::file;
-use crate::schema::commits;
+use crate::domain::file::resource::{FileJson, NewFileData};
+use crate::domain::timeline;
+use crate::schema::{commit_branches, commits, files};
 use crate::errors::Error;
-use crate::domain::commit::resource::CommitJson;
+use crate::domain::commit::resource::IngestionSummaryJson;
 
-lazy_static! {
-    static ref GIT_USER_NAME_EMAIL_REGEX: Regex = Regex::new("^(.*)\\s+<(.*)>$").unwrap();
-}
+// Postgres accepts at most 65535 bind parameters per statement
+const INSERT_BATCH_SIZE: usize = 65535 / 8;
+/// Commits whose files are loaded at once when computing missing note digests.
+const BACKFILL_BATCH_SIZE: usize = 500;
 
 #[derive(Insertable)]
 #[table_name = "commits"]
-struct NewCommit<'a> {
+struct NewCommit {
     repository_id: i32,
-    email: &'a str,
-    git_user_name: &'a str,
-    branch: &'a str,
-    message: &'a str,
-    hash: &'a str,
+    email: String,
+    git_user_name: String,
+    branch: String,
+    message: String,
+    hash: String,
     timestamp: i64,
+    note_digest: Option<String>,
+}
+
+#[derive(QueryableByName)]
+struct SupersededHash {
+    #[sql_type = "sql_types::Text"]
+    hash: String,
 }
 
 pub fn find_last_by_repository_id(
@@ -34,6 +49,7 @@ pub fn find_last_by_repository_id(
 ) -> Result<Commit, Error> {
     commits::table
         .filter(commits::repository_id.eq(repository_id))
+        .filter(commits::is_superseded.eq(false))
         .order(commits::timestamp.desc())
         .limit(1)
         .get_result::<Commit>(conn)
@@ -53,43 +69,337 @@ pub fn find_all_by_repository_id(
         .map_err(Error::DatabaseError)
 }
 
+/// Hash of the gtm time recorded for a commit, equal for commits rewritten by a rebase or amend
+/// as long as their tracked time did not change. Commits without tracked time have no digest.
+fn note_digest(files: &[NewFileData]) -> Option<String> {
+    digest_entries(files.iter()
+        .map(|f| digest_entry(
+            f.path.as_deref().unwrap_or_default(),
+            f.status.as_deref().unwrap_or_default(),
+            f.time_total.unwrap_or_default(),
+            f.timeline.iter().map(|t| (t.timestamp.unwrap_or_default(), t.time.unwrap_or_default())).collect(),
+        ))
+        .collect())
+}
+
+/// Same as `note_digest`, for files that are already stored.
+fn stored_note_digest(files: &[FileJson]) -> Option<String> {
+    digest_entries(files.iter()
+        .map(|f| digest_entry(&f.path, &f.status, f.time, f.timeline.iter().map(|t| (t.timestamp, t.time)).collect()))
+        .collect())
+}
+
+fn digest_entry(path: &str, status: &str, time: i64, mut timeline: Vec<(i64, i64)>) -> String {
+    timeline.sort();
+    format!("{}:{}:{}:{:?}", path, status, time, timeline)
+}
+
+fn digest_entries(mut entries: Vec<String>) -> Option<String> {
+    if entries.is_empty() {
+        return None;
+    }
+    entries.sort();
+
+    let mut hasher = Sha256::new();
+    hasher.input_str(&entries.join("\n"));
+    Some(hasher.result_str())
+}
+
+/// Commits stored before note digests were introduced have none, the migration adding them leaves
+/// them empty. They are computed from the stored files and timeline on the next sync
+/// of their repository, before equivalent commits are looked for. Commits without files keep none.
+fn backfill_note_digests(conn: &PgConnection, repository_id: i32) -> Result<(), Error> {
+    let missing: Vec<i32> = commits::table
+        .filter(commits::repository_id.eq(repository_id))
+        .filter(commits::note_digest.is_null())
+        .filter(diesel::dsl::exists(files::table.filter(files::commit.eq(commits::id))))
+        .select(commits::id)
+        .load::<i32>(conn)?;
+
+    for chunk in missing.chunks(BACKFILL_BATCH_SIZE) {
+        for (commit, files) in file::db::find_all_by_commits(conn, &chunk.to_vec())? {
+            diesel::update(commits::table.find(commit))
+                .set(commits::note_digest.eq(stored_note_digest(&files)))
+                .execute(conn)?;
+        }
+    }
+    Ok(())
+}
+
+/// Path, status, time, line counts and timeline of every file of a commit in a canonical order. A
+/// resent commit is unchanged only if all of it equals what is stored.
+type FileContent = Vec<(String, String, i64, i64, i64, Vec<(i64, i64)>)>;
+
+fn incoming_content(files: &[NewFileData]) -> FileContent {
+    files.iter()
+        .map(|f| (
+            f.path.clone().unwrap_or_default(),
+            f.status.clone().unwrap_or_default(),
+            f.time_total.unwrap_or_default(),
+            f.added_lines.unwrap_or_default(),
+            f.deleted_lines.unwrap_or_default(),
+            f.timeline.iter()
+                .map(|t| (t.timestamp.unwrap_or_default(), t.time.unwrap_or_default()))
+                .sorted()
+                .collect(),
+        ))
+        .sorted()
+        .collect()
+}
+
+fn stored_content(files: &[FileJson]) -> FileContent {
+    files.iter()
+        .map(|f| (
+            f.path.clone(),
+            f.status.clone(),
+            f.time,
+            f.lines_added,
+            f.lines_deleted,
+            f.timeline.iter().map(|t| (t.timestamp, t.time)).sorted().collect(),
+        ))
+        .sorted()
+        .collect()
+}
+
+fn filter_commits<'a>(repository_id: i32, params: &'a CommitListParams) -> commits::BoxedQuery<'a, Pg> {
+    let mut query = commits::table
+        .filter(commits::repository_id.eq(repository_id))
+        .filter(commits::is_superseded.eq(false))
+        .into_boxed();
+
+    if let Some(author) = &params.author {
+        let pattern = like_pattern(author);
+        query = query.filter(commits::email.ilike(pattern.clone()).or(commits::git_user_name.ilike(pattern)));
+    }
+    if let Some(branch) = &params.branch {
+        query = query.filter(commits::id.eq_any(commit_branches::table
+            .filter(commit_branches::branch.eq(branch))
+            .select(commit_branches::commit)));
+    }
+    if let Some(start) = params.start {
+        query = query.filter(commits::timestamp.ge(start));
+    }
+    if let Some(end) = params.end {
+        query = query.filter(commits::timestamp.lt(end));
+    }
+    if let Some(message) = &params.message {
+        query = query.filter(commits::message.ilike(like_pattern(message)));
+    }
+    query
+}
+
+/// Builds an `ILIKE` pattern matching `text` anywhere, with wildcards in `text` taken literally.
+fn like_pattern(text: &str) -> String {
+    format!("%{}%", text.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_"))
+}
+
+/// Returns the total count of matching commits and the requested page, newest first.
+pub fn find_page(
+    conn: &PgConnection,
+    repository_id: i32,
+    params: &CommitListParams,
+    page: i64,
+    page_size: i64,
+) -> Result<(i64, Vec<Commit>), Error> {
+    let total = filter_commits(repository_id, params)
+        .count()
+        .get_result::<i64>(conn)?;
+    let commits = filter_commits(repository_id, params)
+        .order((commits::timestamp.desc(), commits::id.desc()))
+        .offset((page - 1) * page_size)
+        .limit(page_size)
+        .load::<Commit>(conn)?;
+    Ok((total, commits))
+}
+
+pub fn find_by_hash(conn: &PgConnection, repository_id: i32, hash: &str) -> Result<Commit, Error> {
+    Ok(commits::table
+        .filter(commits::repository_id.eq(repository_id))
+        .filter(commits::hash.eq(hash))
+        .get_result::<Commit>(conn)?)
+}
+
 pub fn create_all(
     conn: &PgConnection,
     commits: Vec<NewCommitData>,
+    removed_hashes: Vec<String>,
     repository_id: i32
-) -> Result<Vec<CommitJson>, Error> {
-    let mut vec = Vec::new();
-    for var in commits {
-        let author = var.author.unwrap_or_default();
-        let author_matches = GIT_USER_NAME_EMAIL_REGEX.captures(&author).unwrap();
-        let git_user_name = author_matches.get(1).unwrap().as_str().to_string();
-        let email = author_matches.get(2).unwrap().as_str().to_string();
-        let branch =var.branch.unwrap_or_default();
-        let message = var.message.unwrap_or_default();
-        let hash = var.hash.unwrap_or_default();
-        let timestamp = var.time.unwrap_or_default();
-
-        let new_commit = &NewCommit {
-            repository_id,
-            email: &email,
-            git_user_name: &git_user_name,
-            branch: &branch,
-            message: &message,
-            hash: &hash,
-            timestamp,
-        };
-
-        let commit = diesel::insert_into(commits::table)
-            .values(new_commit)
-            .get_result::<Commit>(conn)?;
-
-        let files_vec = file::db::create_all(
-            &conn,
-            var.files,
-            commit.id
-        )?;
-
-        vec.push(commit.attach(files_vec))
+) -> Result<IngestionSummaryJson, Error> {
+    conn.transaction::<_, Error, _>(|| {
+        let hashes: Vec<String> = commits.iter()
+            .map(|c| c.hash.clone().unwrap_or_default())
+            .collect();
+        let existing: HashMap<String, Commit> = commits::table
+            .filter(commits::repository_id.eq(repository_id))
+            .filter(commits::hash.eq_any(&hashes))
+            .load::<Commit>(conn)?
+            .into_iter()
+            .map(|c| (c.hash.clone(), c))
+            .collect();
+        let resent_ids: Vec<i32> = commits.iter()
+            .filter_map(|c| c.hash.as_ref().and_then(|hash| existing.get(hash)))
+            .map(|c| c.id)
+            .collect();
+        let known_files: HashMap<i32, Vec<FileJson>> = file::db::find_all_by_commits(conn, &resent_ids)?;
+
+        let mut summary = IngestionSummaryJson::default();
+        let mut seen: HashSet<String> = HashSet::new();
+        let mut updated: Vec<(Commit, Vec<NewFileData>)> = Vec::new();
+        let mut new_commits: Vec<NewCommit> = Vec::new();
+        let mut new_files: HashMap<String, Vec<NewFileData>> = HashMap::new();
+        let mut branches: HashMap<String, HashSet<String>> = HashMap::new();
+        for var in commits {
+            let (git_user_name, email) = git::parse_author(&var.author.unwrap_or_default())
+                .unwrap_or_default();
+            let branch =var.branch.unwrap_or_default();
+            let message = var.message.unwrap_or_default();
+            let hash = var.hash.unwrap_or_default();
+            let timestamp = var.time.unwrap_or_default();
+            let digest = note_digest(&var.files);
+            branches.entry(hash.clone())
+                .or_insert_with(HashSet::new)
+                .extend(var.branches.into_iter().chain(Some(branch.clone())).filter(|b| !b.is_empty()));
+
+            if !seen.insert(hash.clone()) {
+                summary.skipped.push(hash);
+                continue;
+            }
+
+            if let Some(known) = existing.get(&hash) {
+                let known_content = known_files.get(&known.id)
+                    .map(|files| stored_content(files))
+                    .unwrap_or_default();
+                let unchanged = known.email == email
+                    && known.git_user_name == git_user_name
+                    && known.message == message
+                    && known.time == timestamp
+                    && known_content == incoming_content(&var.files);
+                if unchanged {
+                    summary.skipped.push(hash);
+                    continue;
+                }
+
+                let commit = diesel::update(commits::table.find(known.id))
+                    .set((
+                        commits::email.eq(&email),
+                        commits::git_user_name.eq(&git_user_name),
+                        commits::message.eq(&message),
+                        commits::timestamp.eq(timestamp),
+                        commits::note_digest.eq(&digest),
+                    ))
+                    .get_result::<Commit>(conn)?;
+                summary.updated.push(hash);
+                updated.push((commit, var.files));
+                continue;
+            }
+
+            summary.inserted.push(hash.clone());
+            new_files.insert(hash.clone(), var.files);
+            new_commits.push(NewCommit {
+                repository_id,
+                email,
+                git_user_name,
+                branch,
+                message,
+                hash,
+                timestamp,
+                note_digest: digest,
+            });
+        }
+
+        let updated_ids: Vec<i32> = updated.iter().map(|(c, _)| c.id).collect();
+        file::db::delete_all_by_commits(conn, &updated_ids)?;
+
+        let mut inserted: Vec<Commit> = Vec::with_capacity(new_commits.len());
+        for chunk in new_commits.chunks(INSERT_BATCH_SIZE) {
+            inserted.extend(diesel::insert_into(commits::table)
+                .values(chunk)
+                .get_results::<Commit>(conn)?);
+        }
+        inserted.sort_by_key(|c| c.id);
+
+        let inserted_ids: Vec<i32> = inserted.iter().map(|c| c.id).collect();
+        let commit_ids: HashMap<&String, i32> = existing.values()
+            .chain(inserted.iter())
+            .map(|c| (&c.hash, c.id))
+            .collect();
+        let memberships: Vec<(i32, String)> = branches.into_iter()
+            .filter_map(|(hash, names)| commit_ids.get(&hash).map(|id| (*id, names)))
+            .flat_map(|(id, names)| names.into_iter().map(move |name| (id, name)))
+            .collect();
+        branch::db::create_all(conn, memberships)?;
+        backfill_note_digests(conn, repository_id)?;
+        summary.superseded = supersede(conn, repository_id, &inserted_ids, &removed_hashes)?;
+
+        // `RETURNING` gives no order guarantee, so inserted commits find their files by hash
+        let mut commits_vec: Vec<Commit> = Vec::with_capacity(updated.len() + inserted.len());
+        let mut files: Vec<(i32, Vec<NewFileData>)> = Vec::with_capacity(commits_vec.capacity());
+        for (commit, commit_files) in updated {
+            files.push((commit.id, commit_files));
+            commits_vec.push(commit);
+        }
+        for commit in inserted {
+            files.push((commit.id, new_files.remove(&commit.hash).unwrap_or_default()));
+            commits_vec.push(commit);
+        }
+        file::db::create_all(conn, files)?;
+        timeline::db::refresh_rollups(conn, &commits_vec.iter().map(|c| c.id).collect())?;
+        Ok(summary)
+    })
+}
+
+
+/// Marks commits whose gtm time is already counted by another commit as superseded.
+///
+/// Older commits equivalent to one of `inserted` (same author, author time and note digest) are
+/// superseded by it. Commits listed in `removed_hashes` no longer exist on any branch, they are
+/// superseded by an equivalent commit if there is one and excluded from stats either way.
+fn supersede(
+    conn: &PgConnection,
+    repository_id: i32,
+    inserted: &Vec<i32>,
+    removed_hashes: &Vec<String>,
+) -> Result<Vec<String>, Error> {
+    let mut superseded = sql_query("
+        UPDATE commits
+        SET is_superseded = TRUE,
+            superseded_by = new.id
+        FROM commits new
+        WHERE new.id = ANY($1)
+          AND commits.repository_id = new.repository_id
+          AND commits.note_digest = new.note_digest
+          AND commits.email = new.email
+          AND commits.timestamp = new.timestamp
+          AND commits.id < new.id
+          AND NOT commits.is_superseded
+        RETURNING commits.hash")
+        .bind::<sql_types::Array<sql_types::Integer>, _>(inserted)
+        .load::<SupersededHash>(conn)?;
+
+    if !removed_hashes.is_empty() {
+        superseded.extend(sql_query("
+            UPDATE commits
+            SET is_superseded = TRUE,
+                superseded_by = (
+                    SELECT c.id
+                    FROM commits c
+                    WHERE c.repository_id = commits.repository_id
+                      AND c.note_digest = commits.note_digest
+                      AND c.email = commits.email
+                      AND c.timestamp = commits.timestamp
+                      AND c.id <> commits.id
+                      AND NOT c.is_superseded
+                      AND NOT c.hash = ANY($2)
+                    ORDER BY c.id DESC
+                    LIMIT 1)
+            WHERE commits.repository_id = $1
+              AND commits.hash = ANY($2)
+              AND NOT commits.is_superseded
+            RETURNING commits.hash")
+            .bind::<sql_types::Integer, _>(repository_id)
+            .bind::<sql_types::Array<sql_types::Text>, _>(removed_hashes)
+            .load::<SupersededHash>(conn)?);
     }
-    Ok(vec)
-}
\ No newline at end of file
+
+    Ok(superseded.into_iter().map(|s| s.hash).collect())
+}
@@ -11,6 +11,9 @@ pub struct Commit {
     pub branch: String,
     pub time: i64,
     pub git_user_name: String,
+    pub is_superseded: bool,
+    pub superseded_by: Option<i32>,
+    pub note_digest: Option<String>,
 }
 
 impl Commit {
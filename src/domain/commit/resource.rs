@@ -20,4 +20,21 @@ pub struct CommitJson {
     pub hash: String,
     pub time: i64,
     pub files: Vec<FileJson>
+}
+
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CommitPageJson {
+    pub page: i64,
+    pub page_size: i64,
+    pub total: i64,
+    pub commits: Vec<CommitJson>,
+}
+
+#[derive(Serialize, JsonSchema, Default)]
+pub struct IngestionSummaryJson {
+    pub inserted: Vec<String>,
+    pub updated: Vec<String>,
+    pub superseded: Vec<String>,
+    pub skipped: Vec<String>,
 }
\ No newline at end of file
@@ -1,17 +1,18 @@
 use rocket_contrib::json::Json;
 use rocket_okapi::{JsonSchema, openapi};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use validator::Validate;
 
-use crate::domain::commit;
-use crate::domain::commit::resource::LastCommitHash;
+use crate::domain::{commit, repository};
+use crate::domain::commit::resource::{CommitJson, CommitPageJson, LastCommitHash};
 use crate::domain::db::Conn;
 use crate::domain::file::resource::NewFileData;
-use crate::errors::Error;
+use crate::domain::user::model::AuthUser;
+use crate::errors::{Error, FieldValidator};
 use crate::security::api_key::ApiKey;
 use rocket::request::Form;
 
-#[derive(Deserialize, Validate, JsonSchema)]
+#[derive(Serialize, Deserialize, Validate, JsonSchema)]
 pub struct NewCommitData {
     #[validate(length(min = 1))]
     pub author: Option<String>,
@@ -22,10 +23,34 @@ pub struct NewCommitData {
     #[validate(length(min = 1))]
     pub hash: Option<String>,
     pub time: Option<i64>,
+    /// Other branches the commit is reachable from, besides `branch`.
+    #[serde(default)]
+    pub branches: Vec<String>,
     #[serde(rename = "files")]
     pub files: Vec<NewFileData>,
 }
 
+/// Commit metadata from `git log` with its raw gtm note.
+#[derive(Deserialize, Validate, JsonSchema)]
+pub struct NewNoteCommitData {
+    #[validate(length(min = 1))]
+    pub author: Option<String>,
+    #[validate(length(min = 1))]
+    pub branch: Option<String>,
+    #[validate(length(min = 1))]
+    pub message: Option<String>,
+    #[validate(length(min = 1))]
+    pub hash: Option<String>,
+    pub time: Option<i64>,
+    /// Other branches the commit is reachable from, besides `branch`.
+    #[serde(default)]
+    pub branches: Vec<String>,
+    /// Content of `git notes --ref=gtm-data show <hash>`.
+    pub note: Option<String>,
+    /// Output of `git log --numstat --format= -n 1 <hash>`.
+    pub numstat: Option<String>,
+}
+
 #[derive(FromForm, Default, Validate, Deserialize, JsonSchema)]
 pub struct CommitHashParams {
     provider: String,
@@ -50,3 +75,47 @@ pub fn get_commit_hash(
     ))
 }
 
+
+#[derive(FromForm, Default, Validate, Deserialize, JsonSchema)]
+pub struct CommitListParams {
+    /// Part of the author's name or email.
+    pub author: Option<String>,
+    pub branch: Option<String>,
+    pub start: Option<i64>,
+    pub end: Option<i64>,
+    /// Part of the commit message.
+    pub message: Option<String>,
+    #[validate(range(min = 1))]
+    pub page: Option<i64>,
+    #[validate(range(min = 1, max = 500))]
+    pub page_size: Option<i64>,
+}
+
+#[openapi]
+#[get("/repositories/<repository_id>/commits?<params..>")]
+pub fn get_repository_commits(
+    auth_user: AuthUser,
+    conn: Conn,
+    repository_id: i32,
+    params: Form<CommitListParams>,
+) -> Result<Json<CommitPageJson>, Error> {
+    repository::service::find_with_access(&conn, &auth_user, repository_id)?;
+    let params = params.into_inner();
+    FieldValidator::validate(&params).check()?;
+
+    let page = commit::service::get_commit_page(&conn, repository_id, &params)?;
+    Ok(Json(page))
+}
+
+#[openapi]
+#[get("/repositories/<repository_id>/commits/<hash>")]
+pub fn get_repository_commit(
+    auth_user: AuthUser,
+    conn: Conn,
+    repository_id: i32,
+    hash: String,
+) -> Result<Json<CommitJson>, Error> {
+    repository::service::find_with_access(&conn, &auth_user, repository_id)?;
+    let commit = commit::service::get_commit(&conn, repository_id, &hash)?;
+    Ok(Json(commit))
+}
@@ -1,9 +1,16 @@
+use diesel::PgConnection;
+
+use crate::common::{git, gtm};
 use crate::domain::commit;
-use crate::domain::commit::resource::LastCommitHash;
+use crate::domain::commit::resource::{CommitJson, CommitPageJson, LastCommitHash};
 use crate::domain::db::Conn;
 use crate::domain::repository;
 use crate::domain::sync;
-use crate::errors::Error;
+use crate::domain::commit::routes::{CommitListParams, NewCommitData, NewNoteCommitData};
+use crate::domain::file;
+use crate::domain::file::resource::NewFileData;
+use crate::domain::timeline::routes::NewTimelineData;
+use crate::errors::{Error, PayloadValidator};
 use crate::security::api_key::ApiKey;
 
 pub fn find_last_commit_hash(
@@ -36,4 +43,124 @@ pub fn find_last_commit_hash(
         timestamp: last_commit.time,
         tracked_commit_hashes: hashes,
     })
-}
\ No newline at end of file
+}
+
+/// Validates a batch of commits, `offset` is the index of its first commit within the upload.
+pub fn validate_commits(commits: &[NewCommitData], offset: usize) -> Result<(), Error> {
+    let mut validator = PayloadValidator::default();
+    for (i, commit) in (offset..).zip(commits) {
+        let prefix = format!("commits[{}]", i);
+        validator.validate(&prefix, commit);
+        validator.require(&prefix, "hash", &commit.hash);
+        validator.require(&prefix, "author", &commit.author);
+        if let Some(author) = &commit.author {
+            if git::parse_author(author).is_none() {
+                validator.add(&format!("{}.author", prefix), "must be formatted as `name <email>`");
+            }
+        }
+
+        for (j, file) in commit.files.iter().enumerate() {
+            let prefix = format!("commits[{}].files[{}]", i, j);
+            validator.validate(&prefix, file);
+            validator.require(&prefix, "path", &file.path);
+            validator.require(&prefix, "status", &file.status);
+            validator.require(&prefix, "time_total", &file.time_total);
+            validator.require(&prefix, "added_lines", &file.added_lines);
+            validator.require(&prefix, "deleted_lines", &file.deleted_lines);
+
+            for (k, entry) in file.timeline.iter().enumerate() {
+                let prefix = format!("commits[{}].files[{}].timeline[{}]", i, j, k);
+                validator.require(&prefix, "timestamp", &entry.timestamp);
+                validator.require(&prefix, "time", &entry.time);
+            }
+        }
+    }
+    validator.check()
+}
+
+/// Converts commits carrying raw gtm notes into the regular commit payload, `offset` is the index
+/// of the first commit within the upload.
+pub fn commits_from_notes(commits: Vec<NewNoteCommitData>, offset: usize) -> Result<Vec<NewCommitData>, Error> {
+    let mut validator = PayloadValidator::default();
+    let mut converted = Vec::with_capacity(commits.len());
+
+    for (i, commit) in (offset..).zip(commits) {
+        let prefix = format!("commits[{}]", i);
+        validator.require(&prefix, "note", &commit.note);
+
+        let files = match commit.note.as_deref().map(gtm::parse_note).transpose() {
+            Ok(files) => files.unwrap_or_default(),
+            Err(code) => {
+                validator.add(&format!("{}.note", prefix), code);
+                vec![]
+            }
+        };
+        let lines = match commit.numstat.as_deref().map(gtm::parse_numstat).transpose() {
+            Ok(lines) => lines.unwrap_or_default(),
+            Err(code) => {
+                validator.add(&format!("{}.numstat", prefix), code);
+                Default::default()
+            }
+        };
+
+        let files = files.into_iter()
+            .map(|file| {
+                let (added_lines, deleted_lines) = lines.get(&file.path).cloned().unwrap_or((0, 0));
+                NewFileData {
+                    status: Some(file.status),
+                    time_total: Some(file.time_total),
+                    added_lines: Some(added_lines),
+                    deleted_lines: Some(deleted_lines),
+                    timeline: file.timeline.into_iter()
+                        .map(|(timestamp, time)| NewTimelineData {
+                            timestamp: Some(timestamp),
+                            time: Some(time),
+                        })
+                        .collect(),
+                    path: Some(file.path),
+                }
+            })
+            .collect();
+
+        converted.push(NewCommitData {
+            author: commit.author,
+            branch: commit.branch,
+            message: commit.message,
+            hash: commit.hash,
+            time: commit.time,
+            branches: commit.branches,
+            files,
+        });
+    }
+
+    validator.check()?;
+    Ok(converted)
+}
+
+pub fn get_commit_page(
+    conn: &PgConnection,
+    repository_id: i32,
+    params: &CommitListParams,
+) -> Result<CommitPageJson, Error> {
+    let page = params.page.unwrap_or(1);
+    let page_size = params.page_size.unwrap_or(50);
+    let (total, commits) = commit::db::find_page(conn, repository_id, params, page, page_size)?;
+
+    let ids: Vec<i32> = commits.iter().map(|c| c.id).collect();
+    let mut files = file::db::find_all_by_commits(conn, &ids)?;
+    Ok(CommitPageJson {
+        page,
+        page_size,
+        total,
+        commits: commits.iter()
+            .map(|c| c.attach(files.remove(&c.id).unwrap_or_default()))
+            .collect(),
+    })
+}
+
+pub fn get_commit(conn: &PgConnection, repository_id: i32, hash: &str) -> Result<CommitJson, Error> {
+    let commit = commit::db::find_by_hash(conn, repository_id, hash)
+        .map_err(|_| Error::BadRequest("Commit not found!"))?;
+    let mut files = file::db::find_all_by_commits(conn, &vec![commit.id])?;
+    Ok(commit.attach(files.remove(&commit.id).unwrap_or_default()))
+}
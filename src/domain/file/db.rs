@@ -1,5 +1,7 @@
+use std::collections::HashMap;
+
 use diesel;
-use diesel::{Insertable, PgConnection, RunQueryDsl, sql_query, sql_types};
+use diesel::{ExpressionMethods, Insertable, PgConnection, QueryDsl, RunQueryDsl, sql_query, sql_types};
 
 use crate::common::sql;
 use crate::errors::{Error, FieldValidator};
@@ -8,59 +10,127 @@ use crate::domain::file::resource::{NewFileData, FileJson};
 use crate::schema::files;
 use crate::domain::timeline;
 use crate::domain::timeline::dwh::{FileEditDWH, PathlessFileEditDWH};
+use crate::domain::timeline::resources::TimelineJson;
+
+// Postgres accepts at most 65535 bind parameters per statement
+const INSERT_BATCH_SIZE: usize = 65535 / 7;
 
 #[derive(Insertable)]
 #[table_name = "files"]
-struct NewFile<'a> {
+struct NewFile {
+    id: i32,
     commit: i32,
-    path: &'a str,
-    status: &'a str,
+    path: String,
+    status: String,
     time: i64,
     lines_added: i64,
     lines_deleted: i64,
 }
 
+#[derive(QueryableByName)]
+struct NextId {
+    #[sql_type = "sql_types::Integer"]
+    id: i32,
+}
+
+/// Inserts the files of the given commits with their timelines, returned grouped by commit id.
+/// File ids are drawn from the sequence up front, so timelines are attached by id rather than by
+/// the order of the inserted rows, which `RETURNING` doesn't guarantee.
 pub fn create_all(
     conn: &PgConnection,
-    files: Vec<NewFileData>,
-    commit: i32,
-) -> Result<Vec<FileJson>, Error> {
-    let mut vec = Vec::new();
-    for var in files {
-        let mut extractor = FieldValidator::validate(&var);
-        let path = &extractor.extract("path", var.path);
-        let status = &extractor.extract("status", var.status);
-        let time = extractor.extract("time_total", var.time_total);
-        let lines_added = extractor.extract("added_lines", var.added_lines);
-        let lines_deleted = extractor.extract("deleted_lines", var.deleted_lines);
-
-        let new_file = &NewFile {
-            commit,
-            path,
-            status,
-            time,
-            lines_added,
-            lines_deleted,
-        };
-
-        let file = diesel::insert_into(files::table)
-            .values(new_file)
-            .get_result::<File>(conn)?;
-
-        let timeline_vec = timeline::db::create_all(
-            &conn,
-            var.timeline,
-            file.id,
-        )?.into_iter()
-            .map(|f| f.attach())
+    files: Vec<(i32, Vec<NewFileData>)>,
+) -> Result<HashMap<i32, Vec<FileJson>>, Error> {
+    let count: usize = files.iter().map(|(_, commit_files)| commit_files.len()).sum();
+    let mut ids = next_ids(conn, count)?.into_iter();
+    let mut new_files = Vec::with_capacity(count);
+    let mut timelines = Vec::with_capacity(count);
+    for (commit, commit_files) in files {
+        for var in commit_files {
+            let mut extractor = FieldValidator::validate(&var);
+            let path = extractor.extract("path", var.path);
+            let status = extractor.extract("status", var.status);
+            let time = extractor.extract("time_total", var.time_total);
+            let lines_added = extractor.extract("added_lines", var.added_lines);
+            let lines_deleted = extractor.extract("deleted_lines", var.deleted_lines);
+            let id = ids.next().expect("Missing file id!");
+
+            new_files.push(NewFile {
+                id,
+                commit,
+                path,
+                status,
+                time,
+                lines_added,
+                lines_deleted,
+            });
+            timelines.push((id, var.timeline));
+        }
+    }
+
+    let mut inserted: Vec<File> = Vec::with_capacity(new_files.len());
+    for chunk in new_files.chunks(INSERT_BATCH_SIZE) {
+        inserted.extend(diesel::insert_into(files::table)
+            .values(chunk)
+            .get_results::<File>(conn)?);
+    }
+    inserted.sort_by_key(|f| f.id);
+
+    let mut timeline_map = timeline::db::create_all(&conn, timelines)?;
+    let mut res: HashMap<i32, Vec<FileJson>> = HashMap::new();
+    for file in inserted {
+        let timeline = timeline_map.remove(&file.id)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|t| t.attach())
             .collect();
+        res.entry(file.commit).or_insert_with(Vec::new).push(file.attach(timeline));
+    }
+    Ok(res)
+}
+
+fn next_ids(conn: &PgConnection, count: usize) -> Result<Vec<i32>, Error> {
+    Ok(sql_query("SELECT nextval('files_id_seq')::INTEGER AS id FROM generate_series(1, $1)")
+        .bind::<sql_types::Integer, _>(count as i32)
+        .load::<NextId>(conn)?
+        .into_iter()
+        .map(|n| n.id)
+        .collect())
+}
+
+/// Loads the files of the given commits with their timelines, grouped by commit id.
+pub fn find_all_by_commits(conn: &PgConnection, commits: &Vec<i32>) -> Result<HashMap<i32, Vec<FileJson>>, Error> {
+    let files = files::table
+        .filter(files::commit.eq_any(commits))
+        .order(files::id)
+        .load::<File>(conn)?;
 
-        vec.push(file.attach(timeline_vec))
+    let file_ids: Vec<i32> = files.iter().map(|f| f.id).collect();
+    let mut timelines: HashMap<i32, Vec<TimelineJson>> = HashMap::new();
+    for entry in timeline::db::find_all_by_files(conn, &file_ids)? {
+        timelines.entry(entry.file).or_insert_with(Vec::new).push(entry.attach());
     }
-    Ok(vec)
+
+    let mut res: HashMap<i32, Vec<FileJson>> = HashMap::new();
+    for file in files {
+        let timeline = timelines.remove(&file.id).unwrap_or_default();
+        res.entry(file.commit).or_insert_with(Vec::new).push(file.attach(timeline));
+    }
+    Ok(res)
+}
+
+pub fn delete_all_by_commits(conn: &PgConnection, commits: &Vec<i32>) -> Result<usize, Error> {
+    Ok(diesel::delete(files::table.filter(files::commit.eq_any(commits)))
+        .execute(conn)?)
 }
 
-pub fn fetch_pathless_file_edits(conn: &PgConnection, group_name: &str, start: i64, end: i64) -> Result<Vec<PathlessFileEditDWH>, Error> {
+pub fn fetch_pathless_file_edits(
+    conn: &PgConnection,
+    group_name: &str,
+    start: i64,
+    end: i64,
+    branches: &Option<Vec<String>>,
+    languages: &Option<Vec<String>>,
+) -> Result<Vec<PathlessFileEditDWH>, Error> {
     let edit_timeline: Vec<PathlessFileEditDWH> = sql_query(format!("
     {}
     SELECT coalesce(users.username, commits.email) AS user,
@@ -82,16 +152,65 @@ pub fn fetch_pathless_file_edits(conn: &PgConnection, group_name: &str, start: i
             FROM groups g
             WHERE g.name = $1))
         AND commits.timestamp >= $2
-        AND commits.timestamp < $3", sql::GROUP_CHILDREN_QUERY))
+        AND commits.timestamp < $3
+        AND NOT commits.is_superseded
+        {}
+        {}
+        {}", sql::GROUP_CHILDREN_QUERY, sql::BRANCH_FILTER, sql::language_filter(), sql::path_rules_filter(sql::QUERIED_GROUP)))
         .bind::<sql_types::Text, _>(group_name)
         .bind::<sql_types::BigInt, _>(start)
         .bind::<sql_types::BigInt, _>(end)
+        .bind::<sql_types::Nullable<sql_types::Array<sql_types::Text>>, _>(branches)
+        .bind::<sql_types::Nullable<sql_types::Array<sql_types::Text>>, _>(languages)
         .load(conn)?;
 
     Ok(edit_timeline)
 }
 
-pub fn fetch_file_edits(conn: &PgConnection, group_name: &str, start: i64, end: i64) -> Result<Vec<FileEditDWH>, Error> {
+/// File edits of all commits whose email belongs to the user, in any repository.
+pub fn fetch_user_pathless_file_edits(
+    conn: &PgConnection,
+    user_id: i32,
+    start: i64,
+    end: i64,
+    branches: &Option<Vec<String>>,
+    languages: &Option<Vec<String>>,
+) -> Result<Vec<PathlessFileEditDWH>, Error> {
+    let edit_timeline: Vec<PathlessFileEditDWH> = sql_query(format!("
+    SELECT users.username AS user,
+           timeline.time,
+           files.lines_added,
+           files.lines_deleted,
+           timeline.timestamp
+    FROM timeline
+        INNER JOIN files ON timeline.file = files.id
+        INNER JOIN commits ON files.commit = commits.id
+        INNER JOIN emails ON commits.email = emails.email
+        INNER JOIN users ON emails.user = users.id
+    WHERE emails.user = $1
+        AND commits.timestamp >= $2
+        AND commits.timestamp < $3
+        AND NOT commits.is_superseded
+        {}
+        {}", sql::BRANCH_FILTER, sql::language_filter()))
+        .bind::<sql_types::Integer, _>(user_id)
+        .bind::<sql_types::BigInt, _>(start)
+        .bind::<sql_types::BigInt, _>(end)
+        .bind::<sql_types::Nullable<sql_types::Array<sql_types::Text>>, _>(branches)
+        .bind::<sql_types::Nullable<sql_types::Array<sql_types::Text>>, _>(languages)
+        .load(conn)?;
+
+    Ok(edit_timeline)
+}
+
+pub fn fetch_file_edits(
+    conn: &PgConnection,
+    group_name: &str,
+    start: i64,
+    end: i64,
+    branches: &Option<Vec<String>>,
+    languages: &Option<Vec<String>>,
+) -> Result<Vec<FileEditDWH>, Error> {
     let edit_timeline: Vec<FileEditDWH> = sql_query(format!("
     {}
     SELECT
@@ -116,10 +235,16 @@ pub fn fetch_file_edits(conn: &PgConnection, group_name: &str, start: i64, end:
             FROM groups g
             WHERE g.name = $1))
         AND commits.timestamp >= $2
-        AND commits.timestamp < $3", sql::GROUP_CHILDREN_QUERY))
+        AND commits.timestamp < $3
+        AND NOT commits.is_superseded
+        {}
+        {}
+        {}", sql::GROUP_CHILDREN_QUERY, sql::BRANCH_FILTER, sql::language_filter(), sql::path_rules_filter(sql::QUERIED_GROUP)))
         .bind::<sql_types::Text, _>(group_name)
         .bind::<sql_types::BigInt, _>(start)
         .bind::<sql_types::BigInt, _>(end)
+        .bind::<sql_types::Nullable<sql_types::Array<sql_types::Text>>, _>(branches)
+        .bind::<sql_types::Nullable<sql_types::Array<sql_types::Text>>, _>(languages)
         .load(conn)?;
     Ok(edit_timeline)
 }
\ No newline at end of file
@@ -5,7 +5,7 @@ use validator::Validate;
 use crate::domain::timeline::resources::TimelineJson;
 use crate::domain::timeline::routes::NewTimelineData;
 
-#[derive(Deserialize, Validate, JsonSchema)]
+#[derive(Serialize, Deserialize, Validate, JsonSchema)]
 pub struct NewFileData {
     #[validate(length(min = 1))]
     pub path: Option<String>,
@@ -1,9 +1,10 @@
+use chrono::{DateTime, Utc};
 use diesel;
 use diesel::{Insertable, sql_query, sql_types};
 use diesel::pg::PgConnection;
 use diesel::prelude::*;
 
-use crate::common::sql::GROUP_CHILDREN_QUERY;
+use crate::common::sql::{GROUP_CHILDREN_QUERY, QUERIED_GROUP, path_rules_filter};
 use crate::domain::group::dwh::{GroupUserStats, GroupFileStats, GroupExportData};
 use crate::domain::group::model::Group;
 use crate::schema::groups;
@@ -47,10 +48,54 @@ pub fn find(conn: &PgConnection, name: &str) -> Result<Group, Error> {
         .first::<Group>(conn)?)
 }
 
+pub fn find_all_by_ids(conn: &PgConnection, ids: &Vec<i32>) -> Result<Vec<Group>, Error> {
+    Ok(groups::table
+        .filter(groups::id.eq_any(ids))
+        .load::<Group>(conn)?)
+}
+
+pub fn rename(conn: &PgConnection, id: i32, name: &str) -> Result<Group, Error> {
+    Ok(diesel::update(groups::table.filter(groups::id.eq(id)))
+        .set(groups::name.eq(name))
+        .get_result::<Group>(conn)?)
+}
+
+/// Deletes the group, its relations, accesses and path rules are deleted with it.
+pub fn delete(conn: &PgConnection, id: i32) -> Result<usize, Error> {
+    Ok(diesel::delete(groups::table.filter(groups::id.eq(id)))
+        .execute(conn)?)
+}
+
 pub fn find_all(conn: &PgConnection) -> Result<Vec<Group>, Error> {
     Ok(groups::table.load::<Group>(conn)?)
 }
 
+pub fn find_all_by_tag(conn: &PgConnection, tag: &str) -> Result<Vec<Group>, Error> {
+    Ok(groups::table
+        .filter(groups::tags.contains(vec![tag]))
+        .load::<Group>(conn)?)
+}
+
+pub fn update_metadata(
+    conn: &PgConnection,
+    id: i32,
+    display_name: Option<&str>,
+    description: Option<&str>,
+    tags: &Vec<String>,
+    period_start: Option<DateTime<Utc>>,
+    period_end: Option<DateTime<Utc>>,
+) -> Result<Group, Error> {
+    Ok(diesel::update(groups::table.filter(groups::id.eq(id)))
+        .set((
+            groups::display_name.eq(display_name),
+            groups::description.eq(description),
+            groups::tags.eq(tags),
+            groups::period_start.eq(period_start),
+            groups::period_end.eq(period_end),
+        ))
+        .get_result::<Group>(conn)?)
+}
+
 pub fn fetch_group_user_stats(
     conn: &PgConnection,
     group_name: &str,
@@ -80,8 +125,10 @@ pub fn fetch_group_user_stats(
                 WHERE g.name = $1))
             AND commits.timestamp >= $2
             AND commits.timestamp < $3
+            AND NOT commits.is_superseded
+            {}
         GROUP BY coalesce(users.username, commits.email)
-        ORDER BY total_time DESC;", GROUP_CHILDREN_QUERY))
+        ORDER BY total_time DESC;", GROUP_CHILDREN_QUERY, path_rules_filter(QUERIED_GROUP)))
         .bind::<sql_types::Text, _>(group_name)
         .bind::<sql_types::BigInt, _>(start)
         .bind::<sql_types::BigInt, _>(end)
@@ -115,8 +162,10 @@ pub fn fetch_group_file_stats(conn: &PgConnection, group_name: &str, start: i64,
                 WHERE g.name = $1))
             AND commits.timestamp >= $2
             AND commits.timestamp < $3
+            AND NOT commits.is_superseded
+            {}
             AND files.path IS NOT NULL
-        GROUP BY files.path, coalesce(users.username, commits.email);", GROUP_CHILDREN_QUERY))
+        GROUP BY files.path, coalesce(users.username, commits.email);", GROUP_CHILDREN_QUERY, path_rules_filter(QUERIED_GROUP)))
         .bind::<sql_types::Text, _>(group_name)
         .bind::<sql_types::BigInt, _>(start)
         .bind::<sql_types::BigInt, _>(end)
@@ -155,6 +204,8 @@ pub fn fetch_group_export_data(conn: &PgConnection, group_name: &str, start: i64
                 WHERE g.name = $1))
             AND commits.timestamp >= $2
             AND commits.timestamp < $3
+            AND NOT commits.is_superseded
+            {}
             AND files.path IS NOT NULL
         GROUP BY
             files.path,
@@ -163,7 +214,7 @@ pub fn fetch_group_export_data(conn: &PgConnection, group_name: &str, start: i64
             repositories.repo,
             repositories.user,
             commits.timestamp,
-            commits.message;", GROUP_CHILDREN_QUERY))
+            commits.message;", GROUP_CHILDREN_QUERY, path_rules_filter(QUERIED_GROUP)))
         .bind::<sql_types::Text, _>(group_name)
         .bind::<sql_types::BigInt, _>(start)
         .bind::<sql_types::BigInt, _>(end)
@@ -8,9 +8,6 @@ use crate::domain::timeline::mapper::cut_path;
 pub fn map_group_file_stats(data: &Vec<GroupFileStats>, depth: i32) -> Vec<GroupFileStatsJson> {
     let mut result: HashMap<String, GroupFileStatsWrapper> = Default::default();
     for file in data {
-        if file.path.ends_with(".app") {
-            continue;
-        }
         let path = cut_path(&file.path, depth);
         let entry = result.get_mut(&path);
         if entry.is_some() {
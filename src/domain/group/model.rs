@@ -1,5 +1,5 @@
 use chrono::{DateTime, Utc};
-use diesel::sql_types::{Integer, Text, Timestamptz};
+use diesel::sql_types::{Array, Bool, Integer, Nullable, Text, Timestamptz};
 
 use crate::config::DATE_FORMAT;
 use crate::domain::group::resource::{GroupJson, GroupWithAccessJson};
@@ -13,6 +13,18 @@ pub struct Group {
     pub name: String,
     #[sql_type = "Timestamptz"]
     pub added_at: DateTime<Utc>,
+    #[sql_type = "Bool"]
+    pub include_apps: bool,
+    #[sql_type = "Nullable<Text>"]
+    pub display_name: Option<String>,
+    #[sql_type = "Nullable<Text>"]
+    pub description: Option<String>,
+    #[sql_type = "Array<Text>"]
+    pub tags: Vec<String>,
+    #[sql_type = "Nullable<Timestamptz>"]
+    pub period_start: Option<DateTime<Utc>>,
+    #[sql_type = "Nullable<Timestamptz>"]
+    pub period_end: Option<DateTime<Utc>>,
 }
 
 impl Group {
@@ -21,6 +33,11 @@ impl Group {
             id: self.id,
             name: self.name,
             added_at: self.added_at.format(DATE_FORMAT).to_string(),
+            display_name: self.display_name,
+            description: self.description,
+            tags: self.tags,
+            period_start: self.period_start.map(|x| x.timestamp()),
+            period_end: self.period_end.map(|x| x.timestamp()),
         }
     }
 }
@@ -31,6 +48,7 @@ impl Group {
             id: self.id,
             name: self.name,
             added_at: self.added_at.format(DATE_FORMAT).to_string(),
+            display_name: self.display_name,
             group_access
         }
     }
@@ -56,6 +56,13 @@ pub struct GroupJson {
     pub id: i32,
     pub name: String,
     pub added_at: String,
+    pub display_name: Option<String>,
+    pub description: Option<String>,
+    pub tags: Vec<String>,
+    /// Start of the active period in seconds since the epoch.
+    pub period_start: Option<i64>,
+    /// End of the active period in seconds since the epoch.
+    pub period_end: Option<i64>,
 }
 
 #[derive(Serialize, JsonSchema)]
@@ -64,5 +71,39 @@ pub struct GroupWithAccessJson {
     pub id: i32,
     pub name: String,
     pub added_at: String,
+    pub display_name: Option<String>,
     pub group_access: Option<GroupAccessJson>
-}
\ No newline at end of file
+}
+
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupAuditJson {
+    pub cycles: Vec<Vec<String>>,
+    pub orphans: Vec<GroupJson>,
+}
+
+#[derive(Serialize, JsonSchema, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum GroupTreeAccess {
+    /// Admins see every group.
+    Admin,
+    /// Access to the group and all its descendants.
+    Recursive,
+    /// Access to the group only.
+    Direct,
+    /// Access through an ancestor with recursive access.
+    Inherited,
+}
+
+#[derive(Serialize, JsonSchema, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupTreeJson {
+    pub id: i32,
+    pub name: String,
+    pub display_name: Option<String>,
+    pub tags: Vec<String>,
+    pub access: GroupTreeAccess,
+    /// Repositories in the group and the visible groups below it.
+    pub repositories: usize,
+    pub children: Vec<GroupTreeJson>,
+}
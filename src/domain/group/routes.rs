@@ -6,7 +6,9 @@ use validator::Validate;
 
 use crate::domain::db::Conn;
 use crate::domain::group;
-use crate::domain::group::resource::{GroupExportDataJson, GroupJson, GroupStatsJson, GroupWithAccessJson};
+use crate::domain::group::resource::{
+    GroupAuditJson, GroupExportDataJson, GroupJson, GroupStatsJson, GroupTreeJson, GroupWithAccessJson,
+};
 use crate::domain::group::service;
 use crate::domain::group_access;
 use crate::domain::role::model::ADMIN;
@@ -26,21 +28,52 @@ pub struct NewGroupChildrenRelation {
     children: Option<Vec<String>>,
 }
 
+#[derive(Deserialize, Validate, JsonSchema)]
+pub struct GroupName {
+    #[validate(length(min = 1, max = 255))]
+    name: Option<String>,
+}
+
+#[derive(Deserialize, Validate, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupMetadata {
+    #[validate(length(max = 255))]
+    display_name: Option<String>,
+    #[validate(length(max = 5000))]
+    description: Option<String>,
+    #[validate(length(max = 50))]
+    tags: Option<Vec<String>>,
+    /// Start of the active period in seconds since the epoch.
+    period_start: Option<i64>,
+    /// End of the active period in seconds since the epoch.
+    period_end: Option<i64>,
+}
+
+/// Missing `start` and `end` default to all time, on group endpoints to the active period of the
+/// group if it has one.
 #[derive(FromForm, Default, Validate, Deserialize, JsonSchema)]
 pub struct GroupStatsParams {
-    start: Option<i64>,
-    end: Option<i64>,
-    depth: Option<i32>,
+    pub start: Option<i64>,
+    pub end: Option<i64>,
+    pub depth: Option<i32>,
 }
 
 #[openapi]
-#[get("/groups")]
-pub fn get_groups(auth_user: AuthUser, conn: Conn) -> Result<Json<Vec<GroupJson>>, Error> {
+#[get("/groups?<tag>")]
+pub fn get_groups(auth_user: AuthUser, conn: Conn, tag: Option<String>) -> Result<Json<Vec<GroupJson>>, Error> {
+    let tag = tag.map(|x| x.trim().to_lowercase());
     let groups: Vec<GroupJson> = if auth_user.roles.contains(&ADMIN) {
-        group::db::find_all(&conn)?.into_iter().map(|x| x.attach()).collect()
+        let groups = match tag {
+            Some(tag) => group::db::find_all_by_tag(&conn, &tag)?,
+            None => group::db::find_all(&conn)?,
+        };
+        groups.into_iter().map(|x| x.attach()).collect()
     } else {
         group::service::get_groups_with_access(&conn, auth_user.user_id)?
-            .into_iter().map(|x| x.attach()).collect()
+            .into_iter()
+            .filter(|x| tag.as_ref().map_or(true, |tag| x.tags.contains(tag)))
+            .map(|x| x.attach())
+            .collect()
     };
     Ok(Json(groups))
 }
@@ -74,6 +107,19 @@ pub fn get_groups_without_access(auth_user: AuthUser, conn: Conn, user_id: i32)
     Ok(Json(groups))
 }
 
+#[openapi]
+#[get("/groups/tree")]
+pub fn get_group_tree(auth_user: AuthUser, conn: Conn) -> Result<Json<Vec<GroupTreeJson>>, Error> {
+    Ok(Json(service::get_group_tree(&conn, &auth_user)?))
+}
+
+#[openapi]
+#[get("/groups/audit")]
+pub fn get_group_audit(auth_user: AuthUser, conn: Conn) -> Result<Json<GroupAuditJson>, Error> {
+    auth_user.require_role(&ADMIN)?;
+    Ok(Json(service::audit_groups(&conn)?))
+}
+
 #[openapi]
 #[get("/groups/<group_name>/stats?<params..>")]
 pub fn get_group_stats(
@@ -86,8 +132,9 @@ pub fn get_group_stats(
         security::service::check_group_access(&conn, auth_user.user_id, &group_name)?;
     }
     let period = params.into_inner();
-    let start = period.start.unwrap_or(0);
-    let end = period.end.unwrap_or(std::i64::MAX);
+    let (start, end) = service::default_period(&conn, &group_name, period.start, period.end);
+    let start = start.unwrap_or(0);
+    let end = end.unwrap_or(std::i64::MAX);
     let depth = period.depth.unwrap_or(1);
     let stats = service::get_group_stats(&conn, &group_name, start, end, depth)?;
     Ok(Json(stats))
@@ -105,8 +152,9 @@ pub fn get_group_export(
         security::service::check_group_access(&conn, auth_user.user_id, &group_name)?;
     }
     let period = params.into_inner();
-    let start = period.start.unwrap_or(0);
-    let end = period.end.unwrap_or(std::i64::MAX);
+    let (start, end) = service::default_period(&conn, &group_name, period.start, period.end);
+    let start = start.unwrap_or(0);
+    let end = end.unwrap_or(std::i64::MAX);
     let depth = period.depth.unwrap_or(1);
     let data = service::export_group_data(&conn, &group_name, start, end, depth)?;
     Ok(Json(data))
@@ -115,7 +163,7 @@ pub fn get_group_export(
 #[openapi]
 #[post("/groups/<group_name>/parents", format = "json", data = "<parents>")]
 pub fn post_group_parents(
-    //auth: Auth,
+    auth_user: AuthUser,
     group_name: String,
     parents: Json<NewGroupParentsRelation>,
     conn: Conn,
@@ -125,7 +173,7 @@ pub fn post_group_parents(
     let parents_vec = extractor.extract("parents", parents.parents);
     extractor.check()?;
 
-    service::add_group_relations(&conn, parents_vec, vec![group_name])?;
+    service::add_group_relations(&conn, &auth_user, parents_vec, vec![group_name])?;
     // TODO return something useful
     Ok(Json(true))
 }
@@ -133,7 +181,7 @@ pub fn post_group_parents(
 #[openapi]
 #[post("/groups/<group_name>/children", format = "json", data = "<children>")]
 pub fn post_group_children(
-    //auth: Auth,
+    auth_user: AuthUser,
     group_name: String,
     children: Json<NewGroupChildrenRelation>,
     conn: Conn,
@@ -143,7 +191,125 @@ pub fn post_group_children(
     let children_vec = extractor.extract("children", children.children);
     extractor.check()?;
 
-    service::add_group_relations(&conn, vec![group_name], children_vec)?;
+    service::add_group_relations(&conn, &auth_user, vec![group_name], children_vec)?;
     // TODO return something useful
     Ok(Json(true))
 }
+
+#[openapi]
+#[post("/groups", format = "json", data = "<group>")]
+pub fn post_group(
+    auth_user: AuthUser,
+    group: Json<GroupName>,
+    conn: Conn,
+) -> Result<Json<GroupJson>, Error> {
+    let group = group.into_inner();
+    let mut extractor = FieldValidator::validate(&group);
+    let name = extractor.extract("name", group.name);
+    extractor.check()?;
+
+    let group = service::create_group(&conn, &auth_user, name.trim())?;
+    Ok(Json(group.attach()))
+}
+
+#[openapi]
+#[put("/groups/<group_name>", format = "json", data = "<group>")]
+pub fn put_group(
+    auth_user: AuthUser,
+    group_name: String,
+    group: Json<GroupName>,
+    conn: Conn,
+) -> Result<Json<GroupJson>, Error> {
+    service::check_manage_access(&conn, &auth_user, &group_name)?;
+    let group = group.into_inner();
+    let mut extractor = FieldValidator::validate(&group);
+    let name = extractor.extract("name", group.name);
+    extractor.check()?;
+
+    let group = service::rename_group(&conn, &group_name, name.trim())?;
+    Ok(Json(group.attach()))
+}
+
+#[openapi]
+#[put("/groups/<group_name>/metadata", format = "json", data = "<metadata>")]
+pub fn put_group_metadata(
+    auth_user: AuthUser,
+    group_name: String,
+    metadata: Json<GroupMetadata>,
+    conn: Conn,
+) -> Result<Json<GroupJson>, Error> {
+    service::check_manage_access(&conn, &auth_user, &group_name)?;
+    let metadata = metadata.into_inner();
+    let mut validator = FieldValidator::validate(&metadata);
+    if let (Some(start), Some(end)) = (metadata.period_start, metadata.period_end) {
+        validator.validate_group_period(start, end);
+    }
+    validator.check()?;
+
+    let group = service::update_group_metadata(
+        &conn,
+        &group_name,
+        metadata.display_name,
+        metadata.description,
+        metadata.tags.unwrap_or_default(),
+        metadata.period_start,
+        metadata.period_end,
+    )?;
+    Ok(Json(group.attach()))
+}
+
+#[openapi]
+#[delete("/groups/<group_name>?<force>")]
+pub fn delete_group(
+    auth_user: AuthUser,
+    group_name: String,
+    force: Option<bool>,
+    conn: Conn,
+) -> Result<Json<usize>, Error> {
+    service::check_manage_access(&conn, &auth_user, &group_name)?;
+    let count = service::delete_group(&conn, &group_name, force.unwrap_or(false))?;
+    Ok(Json(count))
+}
+
+#[openapi]
+#[put("/groups/<group_name>/parents", format = "json", data = "<parents>")]
+pub fn put_group_parents(
+    auth_user: AuthUser,
+    group_name: String,
+    parents: Json<NewGroupParentsRelation>,
+    conn: Conn,
+) -> Result<Json<Vec<GroupJson>>, Error> {
+    let parents = parents.into_inner();
+    let mut extractor = FieldValidator::default();
+    let parents_vec = extractor.extract("parents", parents.parents);
+    extractor.check()?;
+
+    let parents = service::set_group_parents(&conn, &auth_user, &group_name, parents_vec)?;
+    Ok(Json(parents.into_iter().map(|x| x.attach()).collect()))
+}
+
+#[openapi]
+#[delete("/groups/<group_name>/parents/<parent_name>")]
+pub fn delete_group_parent(
+    auth_user: AuthUser,
+    group_name: String,
+    parent_name: String,
+    conn: Conn,
+) -> Result<Json<usize>, Error> {
+    service::check_manage_access(&conn, &auth_user, &parent_name)?;
+    let count = service::remove_group_relation(&conn, &parent_name, &group_name)?;
+    Ok(Json(count))
+}
+
+#[openapi]
+#[delete("/groups/<group_name>/children/<child_name>")]
+pub fn delete_group_child(
+    auth_user: AuthUser,
+    group_name: String,
+    child_name: String,
+    conn: Conn,
+) -> Result<Json<usize>, Error> {
+    service::check_manage_access(&conn, &auth_user, &group_name)?;
+    let count = service::remove_group_relation(&conn, &group_name, &child_name)?;
+    Ok(Json(count))
+}
@@ -1,31 +1,278 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::{TimeZone, Utc};
+use diesel::{Connection, PgConnection};
+use itertools::Itertools;
+
 use crate::domain::db::Conn;
-use crate::domain::{group, group_access, group_group_member};
+use crate::domain::{group, group_access, group_group_member, repository};
 use crate::domain::group::mapper::{map_group_file_stats, map_group_user_stats};
 use crate::domain::group::model::Group;
-use crate::domain::group::resource::{GroupExportDataJson, GroupStatsJson};
+use crate::domain::group::resource::{
+    GroupAuditJson, GroupExportDataJson, GroupStatsJson, GroupTreeAccess, GroupTreeJson,
+};
+use crate::domain::group_access::db::NewGroupAccess;
 use crate::domain::group_access::model::GroupAccess;
 use crate::domain::group_group_member::model::GroupRelation;
+use crate::domain::role::model::{ADMIN, LECTURER};
+use crate::domain::user::model::AuthUser;
 use crate::errors::Error;
+use crate::security;
 
-pub fn add_group_relations(conn: &Conn, parents_vec: Vec<String>, children_vec: Vec<String>) -> Result<(), Error> {
-    for child in children_vec {
-        let relation_child = group::db::find(&conn, &child)
-            .unwrap_or(group::db::create(&conn, &child).unwrap());
+/// Deepest chain of group relations the recursive queries in `common::sql` follow.
+pub const MAX_GROUP_DEPTH: usize = 100;
+const MAX_TAG_LENGTH: usize = 50;
 
-        for parent in &parents_vec {
-            let relation_parent = if !group::db::exists(&conn, &parent) {
-                group::db::create(&conn, &parent)?
-            } else {
-                group::db::find(&conn, &parent)?
-            };
-            if !group_group_member::db::exists(&conn, &relation_parent.id, &relation_child.id) {
-                group_group_member::db::create(&conn, relation_parent.id, relation_child.id);
+/// Links every child to every parent. Admins may link any groups and missing groups are created
+/// for them, lecturers only link existing groups they manage.
+pub fn add_group_relations(
+    conn: &Conn,
+    auth_user: &AuthUser,
+    parents_vec: Vec<String>,
+    children_vec: Vec<String>,
+) -> Result<(), Error> {
+    if !auth_user.roles.contains(&ADMIN) {
+        for name in parents_vec.iter().chain(children_vec.iter()) {
+            find_group(conn, name)?;
+            check_manage_access(conn, auth_user, name)?;
+        }
+    }
+    conn.transaction::<_, Error, _>(|| {
+        let mut relations = group_group_member::db::find_all(conn);
+        for child in children_vec {
+            let relation_child = find_or_create_group(conn, &child)?;
+
+            for parent in &parents_vec {
+                let relation_parent = find_or_create_group(conn, parent)?;
+                if !group_group_member::db::exists(&conn, &relation_parent.id, &relation_child.id) {
+                    check_group_relation(&relations, relation_parent.id, relation_child.id)?;
+                    relations.push(group_group_member::db::create(&conn, relation_parent.id, relation_child.id));
+                }
             }
         }
+        Ok(())
+    })
+}
+
+fn find_or_create_group(conn: &PgConnection, group_name: &str) -> Result<Group, Error> {
+    if group::db::exists(conn, group_name) {
+        group::db::find(conn, group_name)
+    } else {
+        group::db::create(conn, group_name)
+    }
+}
+
+/// Rejects a relation that would make a group its own descendant or nest the hierarchy deeper
+/// than `MAX_GROUP_DEPTH`.
+fn check_group_relation(relations: &[GroupRelation], parent: i32, child: i32) -> Result<(), Error> {
+    if parent == child || is_descendant(relations, child, parent) {
+        return Err(Error::BadRequest("Group relation would create a cycle!"));
+    }
+    if chain_length(relations, parent, false) + 1 + chain_length(relations, child, true) > MAX_GROUP_DEPTH {
+        return Err(Error::BadRequest("Group hierarchy would be too deep!"));
     }
     Ok(())
 }
 
+fn is_descendant(relations: &[GroupRelation], ancestor: i32, id: i32) -> bool {
+    let mut visited = HashSet::new();
+    let mut queue = vec![ancestor];
+    while let Some(current) = queue.pop() {
+        for relation in relations.iter().filter(|r| r.parent == current) {
+            if relation.child == id {
+                return true;
+            }
+            if visited.insert(relation.child) {
+                queue.push(relation.child);
+            }
+        }
+    }
+    false
+}
+
+/// Longest chain of relations below (`down`) or above a group. Gives up after `MAX_GROUP_DEPTH`
+/// steps, so cycles created before relations were checked can't loop forever.
+fn chain_length(relations: &[GroupRelation], id: i32, down: bool) -> usize {
+    let mut frontier: HashSet<i32> = vec![id].into_iter().collect();
+    let mut length = 0;
+    while length <= MAX_GROUP_DEPTH {
+        frontier = relations.iter()
+            .filter(|r| frontier.contains(if down { &r.parent } else { &r.child }))
+            .map(|r| if down { r.child } else { r.parent })
+            .collect();
+        if frontier.is_empty() {
+            break;
+        }
+        length += 1;
+    }
+    length
+}
+
+pub fn find_group(conn: &PgConnection, group_name: &str) -> Result<Group, Error> {
+    group::db::find(conn, group_name)
+        .map_err(|_| Error::BadRequest("Group not found!"))
+}
+
+/// Admins manage all groups, lecturers the groups they have recursive access to.
+pub fn check_manage_access(conn: &PgConnection, auth_user: &AuthUser, group_name: &str) -> Result<(), Error> {
+    if auth_user.roles.contains(&ADMIN) {
+        return Ok(());
+    }
+    auth_user.require_role(&LECTURER)?;
+    security::service::check_recursive_group_access(conn, auth_user.user_id, group_name)
+}
+
+/// Creates an empty group, a lecturer creating it gets recursive access to it.
+pub fn create_group(conn: &Conn, auth_user: &AuthUser, name: &str) -> Result<Group, Error> {
+    let is_admin = auth_user.roles.contains(&ADMIN);
+    if !is_admin {
+        auth_user.require_role(&LECTURER)?;
+    }
+    if group::db::exists(conn, name) {
+        return Err(Error::BadRequest("Group already exists!"));
+    }
+    conn.transaction::<_, Error, _>(|| {
+        let group = group::db::create(conn, name)?;
+        if !is_admin {
+            group_access::db::create(conn, vec![NewGroupAccess {
+                user: auth_user.user_id,
+                group: group.id,
+                access_level_recursive: true,
+            }])?;
+        }
+        Ok(group)
+    })
+}
+
+/// Renames a group. Repository groups are named after their repository and keep their name.
+pub fn rename_group(conn: &Conn, group_name: &str, new_name: &str) -> Result<Group, Error> {
+    let group = find_group(conn, group_name)?;
+    if !repository::db::find_all_by_groups(conn, &vec![group.id])?.is_empty() {
+        return Err(Error::BadRequest("Repository groups can't be renamed!"));
+    }
+    if group::db::exists(conn, new_name) {
+        return Err(Error::BadRequest("Group already exists!"));
+    }
+    group::db::rename(conn, group.id, new_name)
+}
+
+/// Deletes a group and unlinks its parents and children. Repositories attached to the group are
+/// deleted with it only if forced.
+pub fn delete_group(conn: &Conn, group_name: &str, force: bool) -> Result<usize, Error> {
+    let group = find_group(conn, group_name)?;
+    if !force && !repository::db::find_all_by_groups(conn, &vec![group.id])?.is_empty() {
+        return Err(Error::BadRequest("Group has repositories, delete it with force to delete them too!"));
+    }
+    conn.transaction::<_, Error, _>(|| {
+        repository::db::delete_all_by_group(conn, group.id)?;
+        group::db::delete(conn, group.id)
+    })
+}
+
+/// Replaces the display name, description, tags and active period of a group. Tags are trimmed,
+/// lowercased and deduplicated so they can be searched for exactly.
+pub fn update_group_metadata(
+    conn: &Conn,
+    group_name: &str,
+    display_name: Option<String>,
+    description: Option<String>,
+    tags: Vec<String>,
+    period_start: Option<i64>,
+    period_end: Option<i64>,
+) -> Result<Group, Error> {
+    let group = find_group(conn, group_name)?;
+    let tags: Vec<String> = tags.iter()
+        .map(|tag| tag.trim().to_lowercase())
+        .filter(|tag| !tag.is_empty())
+        .sorted()
+        .dedup()
+        .collect();
+    if tags.iter().any(|tag| tag.chars().count() > MAX_TAG_LENGTH) {
+        return Err(Error::BadRequest("Tag is too long!"));
+    }
+    let non_blank = |x: Option<String>| x.map(|x| x.trim().to_string()).filter(|x| !x.is_empty());
+    group::db::update_metadata(
+        conn,
+        group.id,
+        non_blank(display_name).as_deref(),
+        non_blank(description).as_deref(),
+        &tags,
+        period_start.map(|x| Utc.timestamp(x, 0)),
+        period_end.map(|x| Utc.timestamp(x, 0)),
+    )
+}
+
+/// Fills a missing `start` or `end` with the active period of the group, if it has one.
+pub fn default_period(
+    conn: &PgConnection,
+    group_name: &str,
+    start: Option<i64>,
+    end: Option<i64>,
+) -> (Option<i64>, Option<i64>) {
+    if start.is_some() && end.is_some() {
+        return (start, end);
+    }
+    match group::db::find(conn, group_name) {
+        Ok(group) => (
+            start.or(group.period_start.map(|x| x.timestamp())),
+            end.or(group.period_end.map(|x| x.timestamp())),
+        ),
+        Err(_) => (start, end),
+    }
+}
+
+pub fn remove_group_relation(conn: &Conn, parent_name: &str, child_name: &str) -> Result<usize, Error> {
+    let parent = find_group(conn, parent_name)?;
+    let child = find_group(conn, child_name)?;
+    group_group_member::db::delete(conn, parent.id, child.id)
+}
+
+/// Replaces the parents of a group, e.g. to move a repository group to another course. The user
+/// has to be able to manage the group and every parent that is added or removed.
+pub fn set_group_parents(
+    conn: &Conn,
+    auth_user: &AuthUser,
+    group_name: &str,
+    parent_names: Vec<String>,
+) -> Result<Vec<Group>, Error> {
+    let group = find_group(conn, group_name)?;
+    check_manage_access(conn, auth_user, group_name)?;
+    let parents = parent_names.iter()
+        .map(|name| find_group(conn, name))
+        .collect::<Result<Vec<Group>, Error>>()?;
+    let current = group_group_member::db::find_parents(conn, group.id)?;
+    let removed: Vec<i32> = current.iter()
+        .filter(|id| !parents.iter().any(|p| p.id == **id))
+        .cloned()
+        .collect();
+    let added: Vec<&Group> = parents.iter()
+        .filter(|p| !current.contains(&p.id))
+        .collect();
+    for parent in group::db::find_all_by_ids(conn, &removed)?.iter().chain(added.iter().cloned()) {
+        check_manage_access(conn, auth_user, &parent.name)?;
+    }
+
+    let mut relations: Vec<GroupRelation> = group_group_member::db::find_all(conn)
+        .into_iter()
+        .filter(|r| r.child != group.id || !removed.contains(&r.parent))
+        .collect();
+    for parent in &added {
+        check_group_relation(&relations, parent.id, group.id)?;
+        relations.push(GroupRelation { parent: parent.id, child: group.id });
+    }
+
+    conn.transaction::<_, Error, _>(|| {
+        for parent in &removed {
+            group_group_member::db::delete(conn, *parent, group.id)?;
+        }
+        for parent in &added {
+            group_group_member::db::create(conn, parent.id, group.id);
+        }
+        Ok(())
+    })?;
+    Ok(parents)
+}
+
 pub fn get_group_stats(
     conn: &Conn,
     group_name: &String,
@@ -63,36 +310,206 @@ pub fn get_groups_with_access(conn: &Conn, user_id: i32) -> Result<Vec<Group>, E
     let group_accesses: Vec<GroupAccess> = group_access::db::find_by_user(conn, user_id);
     let group_relations: Vec<GroupRelation> = group_group_member::db::find_all(conn);
     let mut res: Vec<Group> = vec![];
+    let mut visited: HashSet<i32> = HashSet::new();
     for group_access in &group_accesses {
-        if group_access.access_level_recursive {
-            let group = groups.iter().find(|x| x.id == group_access.group).unwrap().clone();
-            res.push(group.clone());
-            let rec_res: Vec<Group> = get_groups_with_access_recursive(group, groups.clone(), group_relations.clone());
-            res = [res, rec_res.clone()].concat();
-        } else {
-            res.push(groups.iter().find(|x| x.id == group_access.group).unwrap().clone())
+        let group = groups.iter().find(|x| x.id == group_access.group).unwrap().clone();
+        if group_access.access_level_recursive && visited.insert(group.id) {
+            res.append(&mut get_groups_with_access_recursive(&group, &groups, &group_relations, &mut visited));
         }
+        res.push(group);
     }
     res.sort_by_key(|e| e.id);
     res.dedup_by_key(|e| e.id);
     Ok(res)
 }
 
+/// Descendants of `group` that are not `visited` yet, the visited set keeps cycles in the group
+/// graph from recursing forever.
 pub fn get_groups_with_access_recursive(
-    group: Group,
-    groups: Vec<Group>,
-    group_relations: Vec<GroupRelation>
+    group: &Group,
+    groups: &[Group],
+    group_relations: &[GroupRelation],
+    visited: &mut HashSet<i32>,
 ) -> Vec<Group> {
     let mut children = Vec::new();
-    let relations: Vec<GroupRelation> = group_relations
-        .clone()
-        .into_iter()
-        .filter(|x| x.parent == group.id)
-        .collect::<Vec<GroupRelation>>();
-    for group_relation in &relations {
+    for group_relation in group_relations.iter().filter(|x| x.parent == group.id) {
+        if !visited.insert(group_relation.child) {
+            continue;
+        }
         let child_group = groups.iter().find(|x| x.id == group_relation.child).unwrap().clone();
-        children.push(child_group.clone());
-        children = [children, get_groups_with_access_recursive(child_group, groups.clone(), group_relations.clone()).clone()].concat()
+        children.append(&mut get_groups_with_access_recursive(&child_group, groups, group_relations, visited));
+        children.push(child_group);
     }
     children
-}
\ No newline at end of file
+}
+
+/// Hierarchy of the groups visible to the user. Groups without a visible parent are the roots, a
+/// group with several visible parents appears below each of them.
+pub fn get_group_tree(conn: &Conn, auth_user: &AuthUser) -> Result<Vec<GroupTreeJson>, Error> {
+    let is_admin = auth_user.roles.contains(&ADMIN);
+    let groups: Vec<Group> = if is_admin {
+        group::db::find_all(conn)?
+    } else {
+        get_groups_with_access(conn, auth_user.user_id)?
+    };
+    let visible: HashSet<i32> = groups.iter().map(|g| g.id).collect();
+    let group_relations: Vec<GroupRelation> = group_group_member::db::find_all(conn)
+        .into_iter()
+        .filter(|r| visible.contains(&r.parent) && visible.contains(&r.child))
+        .collect();
+    let group_accesses: Vec<GroupAccess> = group_access::db::find_by_user(conn, auth_user.user_id);
+    let repositories = repository::db::count_by_groups(conn, &visible.iter().copied().collect::<Vec<i32>>())?;
+
+    let mut inherited: HashSet<i32> = HashSet::new();
+    for group_access in group_accesses.iter().filter(|a| a.access_level_recursive) {
+        if let Some(group) = groups.iter().find(|g| g.id == group_access.group) {
+            let mut visited: HashSet<i32> = vec![group.id].into_iter().collect();
+            inherited.extend(get_groups_with_access_recursive(group, &groups, &group_relations, &mut visited)
+                .iter()
+                .map(|g| g.id));
+        }
+    }
+    let access: HashMap<i32, GroupTreeAccess> = groups.iter()
+        .map(|group| {
+            let level = match group_accesses.iter().find(|a| a.group == group.id) {
+                Some(a) if a.access_level_recursive => GroupTreeAccess::Recursive,
+                _ if inherited.contains(&group.id) => GroupTreeAccess::Inherited,
+                Some(_) => GroupTreeAccess::Direct,
+                None => GroupTreeAccess::Admin,
+            };
+            (group.id, level)
+        })
+        .collect();
+
+    let by_id: HashMap<i32, &Group> = groups.iter().map(|g| (g.id, g)).collect();
+    let mut children: HashMap<i32, Vec<&Group>> = HashMap::new();
+    for relation in &group_relations {
+        children.entry(relation.parent).or_insert_with(Vec::new).push(by_id[&relation.child]);
+    }
+    for siblings in children.values_mut() {
+        siblings.sort_by(|a, b| a.name.cmp(&b.name));
+        siblings.dedup_by_key(|g| g.id);
+    }
+    let has_parent: HashSet<i32> = group_relations.iter().map(|r| r.child).collect();
+
+    let mut tree = GroupTree {
+        children: &children,
+        repositories: &repositories,
+        access: &access,
+        built: HashMap::new(),
+    };
+    Ok(groups.iter()
+        .filter(|g| !has_parent.contains(&g.id))
+        .sorted_by(|a, b| a.name.cmp(&b.name))
+        .map(|g| tree.build(g, &mut vec![]).0)
+        .collect())
+}
+
+struct GroupTree<'a> {
+    children: &'a HashMap<i32, Vec<&'a Group>>,
+    repositories: &'a HashMap<i32, usize>,
+    access: &'a HashMap<i32, GroupTreeAccess>,
+    /// Finished subtrees, a group with several parents is only built once.
+    built: HashMap<i32, (GroupTreeJson, HashSet<i32>)>,
+}
+
+impl<'a> GroupTree<'a> {
+    /// Node of `group` and the ids of the groups in its subtree, `path` holds the ancestors so
+    /// cycles created before relations were checked end the branch instead of recursing forever.
+    fn build(&mut self, group: &Group, path: &mut Vec<i32>) -> (GroupTreeJson, HashSet<i32>) {
+        if let Some(built) = self.built.get(&group.id) {
+            return built.clone();
+        }
+        path.push(group.id);
+        let mut subtree: HashSet<i32> = vec![group.id].into_iter().collect();
+        let mut children = Vec::new();
+        // A subtree cut short by a cycle depends on the path it was reached by, it isn't reused
+        let mut cut = false;
+        let relations = self.children;
+        for child in relations.get(&group.id).into_iter().flatten() {
+            if path.contains(&child.id) {
+                cut = true;
+                continue;
+            }
+            let (node, child_subtree) = self.build(child, path);
+            cut |= !self.built.contains_key(&child.id);
+            subtree.extend(child_subtree);
+            children.push(node);
+        }
+        path.pop();
+
+        let node = GroupTreeJson {
+            id: group.id,
+            name: group.name.clone(),
+            display_name: group.display_name.clone(),
+            tags: group.tags.clone(),
+            access: self.access[&group.id],
+            repositories: subtree.iter().map(|id| self.repositories.get(id).unwrap_or(&0)).sum(),
+            children,
+        };
+        if !cut {
+            self.built.insert(group.id, (node.clone(), subtree.clone()));
+        }
+        (node, subtree)
+    }
+}
+
+/// Lists cycles in the group graph and groups that have neither relations nor repositories.
+pub fn audit_groups(conn: &Conn) -> Result<GroupAuditJson, Error> {
+    let groups: Vec<Group> = group::db::find_all(conn)?;
+    let group_relations: Vec<GroupRelation> = group_group_member::db::find_all(conn);
+    let repository_groups: HashSet<i32> = repository::db::find_all(conn)?
+        .into_iter()
+        .map(|(_, group)| group.id)
+        .collect();
+    let name = |id: i32| groups.iter()
+        .find(|g| g.id == id)
+        .map(|g| g.name.clone())
+        .unwrap_or_default();
+
+    let cycles = find_cycles(&group_relations)
+        .into_iter()
+        .map(|cycle| cycle.into_iter().map(&name).collect())
+        .collect();
+    let orphans = groups.iter()
+        .filter(|g| !repository_groups.contains(&g.id)
+            && !group_relations.iter().any(|r| r.parent == g.id || r.child == g.id))
+        .map(|g| g.clone().attach())
+        .collect();
+    Ok(GroupAuditJson { cycles, orphans })
+}
+
+fn find_cycles(relations: &[GroupRelation]) -> Vec<Vec<i32>> {
+    // `on_stack` is true while the group is on the current path and false once it is finished
+    fn visit(
+        id: i32,
+        relations: &[GroupRelation],
+        on_stack: &mut HashMap<i32, bool>,
+        path: &mut Vec<i32>,
+        cycles: &mut Vec<Vec<i32>>,
+    ) {
+        on_stack.insert(id, true);
+        path.push(id);
+        for relation in relations.iter().filter(|r| r.parent == id) {
+            match on_stack.get(&relation.child) {
+                Some(true) => {
+                    let position = path.iter().position(|x| *x == relation.child).unwrap();
+                    cycles.push(path[position..].to_vec());
+                }
+                Some(false) => {}
+                None => visit(relation.child, relations, on_stack, path, cycles),
+            }
+        }
+        path.pop();
+        on_stack.insert(id, false);
+    }
+
+    let mut on_stack = HashMap::new();
+    let mut cycles = Vec::new();
+    for id in relations.iter().map(|r| r.parent).sorted() {
+        if !on_stack.contains_key(&id) {
+            visit(id, relations, &mut on_stack, &mut vec![], &mut cycles);
+        }
+    }
+    cycles
+}
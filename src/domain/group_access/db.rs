@@ -97,6 +97,27 @@ pub fn fetch_group_access_count(
     Ok(count.sum)
 }
 
+/// Counts the recursive accesses of `user` to the group or one of its parents.
+pub fn fetch_recursive_group_access_count(
+    conn: &PgConnection,
+    user: i32,
+    group_name: &str,
+) -> Result<i64, Error> {
+    let count = sql_query(format!("
+        {}
+        SELECT count(*)::bigint AS sum
+        FROM group_accesses
+        WHERE group_accesses.access_level_recursive IS TRUE
+        AND group_accesses.user = $2
+        AND (group_accesses.group IN (SELECT group_parents_query.parent FROM group_parents_query)
+            OR group_accesses.group = (SELECT groups.id FROM groups WHERE groups.name = $1))",
+        GROUP_PARENTS_QUERY))
+        .bind::<sql_types::Text, _>(group_name)
+        .bind::<sql_types::Int4, _>(user)
+        .get_result::<GroupAccessCountDWH>(conn)?;
+    Ok(count.sum)
+}
+
 pub fn update(
     conn: &PgConnection,
     access: GroupAccess,
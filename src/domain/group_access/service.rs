@@ -92,3 +92,7 @@ pub fn toggle_access(
 pub fn get_group_access_count(conn: &PgConnection, user: i32, group: &str) -> Result<i64, Error>{
     group_access::db::fetch_group_access_count(conn, user, group)
 }
+
+pub fn get_recursive_group_access_count(conn: &PgConnection, user: i32, group: &str) -> Result<i64, Error> {
+    group_access::db::fetch_recursive_group_access_count(conn, user, group)
+}
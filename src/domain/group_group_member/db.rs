@@ -3,6 +3,7 @@ use diesel::pg::PgConnection;
 use diesel::prelude::*;
 use diesel::{Insertable};
 use crate::domain::group_group_member::model::GroupRelation;
+use crate::errors::Error;
 use crate::schema::group_group_members;
 
 #[derive(Insertable)]
@@ -40,6 +41,20 @@ pub fn find(
         .expect("Cannot load GroupRepository")
 }
 
+pub fn delete(conn: &PgConnection, parent: i32, child: i32) -> Result<usize, Error> {
+    Ok(diesel::delete(group_group_members::table
+        .filter(group_group_members::parent.eq(parent))
+        .filter(group_group_members::child.eq(child)))
+        .execute(conn)?)
+}
+
+pub fn find_parents(conn: &PgConnection, child: i32) -> Result<Vec<i32>, Error> {
+    Ok(group_group_members::table
+        .filter(group_group_members::child.eq(child))
+        .select(group_group_members::parent)
+        .load::<i32>(conn)?)
+}
+
 pub fn exists(conn: &PgConnection, parent: &i32, child: &i32) -> bool {
     use diesel::dsl::exists;
     use diesel::select;
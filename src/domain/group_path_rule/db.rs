@@ -0,0 +1,61 @@
+use diesel::Insertable;
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+
+use crate::domain::group_path_rule::model::GroupPathRule;
+use crate::errors::Error;
+use crate::schema::{group_path_rules, groups};
+
+#[derive(Insertable)]
+#[table_name = "group_path_rules"]
+pub struct NewGroupPathRule {
+    pub group: i32,
+    pub pattern: String,
+    pub regex: String,
+    pub include: bool,
+}
+
+pub fn create(
+    conn: &PgConnection,
+    new_rules: Vec<NewGroupPathRule>,
+) -> Result<usize, Error> {
+    if new_rules.len() > 0 {
+        return Ok(diesel::insert_into(group_path_rules::table)
+            .values(new_rules)
+            .on_conflict_do_nothing()
+            .execute(conn)?);
+    }
+    Ok(0)
+}
+
+pub fn delete(conn: &PgConnection, group: i32, rule_id: i32) -> Result<usize, Error> {
+    Ok(diesel::delete(group_path_rules::table
+        .filter(group_path_rules::id.eq(rule_id))
+        .filter(group_path_rules::group.eq(group)))
+        .execute(conn)?)
+}
+
+pub fn find_by_group(conn: &PgConnection, group: i32) -> Result<Vec<GroupPathRule>, Error> {
+    Ok(group_path_rules::table
+        .filter(group_path_rules::group.eq(group))
+        .order(group_path_rules::id)
+        .load::<GroupPathRule>(conn)?)
+}
+
+/// Whether the group has any path rules, rollups can't tell paths apart and are skipped then.
+pub fn has_rules(conn: &PgConnection, group_name: &str) -> bool {
+    use diesel::dsl::exists;
+    use diesel::select;
+
+    select(exists(group_path_rules::table
+        .inner_join(groups::table)
+        .filter(groups::name.eq(group_name))))
+        .get_result(conn)
+        .unwrap_or(true)
+}
+
+pub fn set_include_apps(conn: &PgConnection, group: i32, include_apps: bool) -> Result<usize, Error> {
+    Ok(diesel::update(groups::table.filter(groups::id.eq(group)))
+        .set(groups::include_apps.eq(include_apps))
+        .execute(conn)?)
+}
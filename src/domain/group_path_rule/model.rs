@@ -0,0 +1,20 @@
+use crate::domain::group_path_rule::resource::GroupPathRuleJson;
+
+#[derive(Queryable, Debug, Clone)]
+pub struct GroupPathRule {
+    pub id: i32,
+    pub group: i32,
+    pub pattern: String,
+    pub regex: String,
+    pub include: bool,
+}
+
+impl GroupPathRule {
+    pub fn attach(self) -> GroupPathRuleJson {
+        GroupPathRuleJson {
+            id: self.id,
+            pattern: self.pattern,
+            include: self.include,
+        }
+    }
+}
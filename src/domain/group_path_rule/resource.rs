@@ -0,0 +1,17 @@
+use serde::Serialize;
+use schemars::JsonSchema;
+
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupPathRuleJson {
+    pub id: i32,
+    pub pattern: String,
+    pub include: bool,
+}
+
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupPathRulesJson {
+    pub include_apps: bool,
+    pub rules: Vec<GroupPathRuleJson>,
+}
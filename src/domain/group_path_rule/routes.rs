@@ -0,0 +1,86 @@
+use rocket_contrib::json::Json;
+use rocket_okapi::{JsonSchema, openapi};
+use serde::Deserialize;
+use validator::Validate;
+
+use crate::domain::db::Conn;
+use crate::domain::group_path_rule;
+use crate::domain::group_path_rule::resource::GroupPathRulesJson;
+use crate::domain::role::model::ADMIN;
+use crate::domain::user::model::AuthUser;
+use crate::errors::{Error, FieldValidator};
+use crate::security;
+
+#[derive(Deserialize, Validate, JsonSchema)]
+pub struct NewGroupPathRule {
+    /// Glob matched against paths relative to the repository root, e.g. `target/` or `*.lock`.
+    #[validate(length(min = 1))]
+    pub pattern: Option<String>,
+    /// Include rules keep only matching files, exclude rules drop them.
+    pub include: Option<bool>,
+}
+
+#[derive(Deserialize, Validate, JsonSchema)]
+pub struct GroupAppsToggle {
+    pub include_apps: Option<bool>,
+}
+
+#[openapi]
+#[get("/groups/<group_name>/path-rules")]
+pub fn get_group_path_rules(
+    auth_user: AuthUser,
+    group_name: String,
+    conn: Conn,
+) -> Result<Json<GroupPathRulesJson>, Error> {
+    if auth_user.require_role(&ADMIN).is_err() {
+        security::service::check_group_access(&conn, auth_user.user_id, &group_name)?;
+    }
+    let rules = group_path_rule::service::get_path_rules(&conn, &group_name)?;
+    Ok(Json(rules))
+}
+
+#[openapi]
+#[post("/groups/<group_name>/path-rules", format = "json", data = "<rules>")]
+pub fn post_group_path_rules(
+    auth_user: AuthUser,
+    group_name: String,
+    rules: Json<Vec<NewGroupPathRule>>,
+    conn: Conn,
+) -> Result<Json<GroupPathRulesJson>, Error> {
+    auth_user.require_role(&ADMIN)?;
+    group_path_rule::service::add_path_rules(&conn, &group_name, rules.into_inner())?;
+    let rules = group_path_rule::service::get_path_rules(&conn, &group_name)?;
+    Ok(Json(rules))
+}
+
+#[openapi]
+#[delete("/groups/<group_name>/path-rules/<rule_id>")]
+pub fn delete_group_path_rule(
+    auth_user: AuthUser,
+    group_name: String,
+    rule_id: i32,
+    conn: Conn,
+) -> Result<Json<usize>, Error> {
+    auth_user.require_role(&ADMIN)?;
+    let count = group_path_rule::service::delete_path_rule(&conn, &group_name, rule_id)?;
+    Ok(Json(count))
+}
+
+#[openapi]
+#[put("/groups/<group_name>/path-rules/apps", format = "json", data = "<toggle>")]
+pub fn put_group_include_apps(
+    auth_user: AuthUser,
+    group_name: String,
+    toggle: Json<GroupAppsToggle>,
+    conn: Conn,
+) -> Result<Json<GroupPathRulesJson>, Error> {
+    auth_user.require_role(&ADMIN)?;
+    let toggle = toggle.into_inner();
+    let mut extractor = FieldValidator::validate(&toggle);
+    let include_apps = extractor.extract("include_apps", toggle.include_apps);
+    extractor.check()?;
+
+    group_path_rule::service::set_include_apps(&conn, &group_name, include_apps)?;
+    let rules = group_path_rule::service::get_path_rules(&conn, &group_name)?;
+    Ok(Json(rules))
+}
@@ -0,0 +1,49 @@
+use diesel::PgConnection;
+
+use crate::domain::group::service::find_group;
+use crate::domain::group_path_rule::db;
+use crate::domain::group_path_rule::resource::GroupPathRulesJson;
+use crate::domain::group_path_rule::routes::NewGroupPathRule;
+use crate::errors::{Error, FieldValidator};
+
+pub fn get_path_rules(conn: &PgConnection, group_name: &str) -> Result<GroupPathRulesJson, Error> {
+    let group = find_group(conn, group_name)?;
+    let rules = db::find_by_group(conn, group.id)?;
+    Ok(GroupPathRulesJson {
+        include_apps: group.include_apps,
+        rules: rules.into_iter().map(|r| r.attach()).collect(),
+    })
+}
+
+pub fn add_path_rules(
+    conn: &PgConnection,
+    group_name: &str,
+    rules: Vec<NewGroupPathRule>,
+) -> Result<usize, Error> {
+    let group = find_group(conn, group_name)?;
+    let mut new_rules = Vec::with_capacity(rules.len());
+    for rule in rules {
+        let mut extractor = FieldValidator::validate(&rule);
+        let pattern = extractor.extract("pattern", rule.pattern);
+        let include = extractor.extract("include", rule.include);
+        let regex = extractor.validate_glob(&pattern);
+        extractor.check()?;
+        new_rules.push(db::NewGroupPathRule {
+            group: group.id,
+            pattern: pattern.trim().to_string(),
+            regex,
+            include,
+        });
+    }
+    db::create(conn, new_rules)
+}
+
+pub fn delete_path_rule(conn: &PgConnection, group_name: &str, rule_id: i32) -> Result<usize, Error> {
+    let group = find_group(conn, group_name)?;
+    db::delete(conn, group.id, rule_id)
+}
+
+pub fn set_include_apps(conn: &PgConnection, group_name: &str, include_apps: bool) -> Result<usize, Error> {
+    let group = find_group(conn, group_name)?;
+    db::set_include_apps(conn, group.id, include_apps)
+}
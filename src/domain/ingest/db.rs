@@ -0,0 +1,169 @@
+use chrono::Utc;
+use diesel;
+use diesel::{Insertable, sql_query, sql_types};
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+
+use crate::domain::ingest::{STATUS_DONE, STATUS_FAILED};
+use crate::domain::ingest::model::IngestJob;
+use crate::errors::Error;
+use crate::schema::{ingest_job_batches, ingest_jobs};
+
+#[derive(Insertable)]
+#[table_name = "ingest_jobs"]
+struct NewIngestJob<'a> {
+    sync_client: i32,
+    kind: &'a str,
+    payload: &'a str,
+    commits_total: i32,
+}
+
+pub fn create(
+    conn: &PgConnection,
+    sync_client: i32,
+    kind: &str,
+    payload: &str,
+    commits_total: i32,
+) -> Result<IngestJob, Error> {
+    let new_job = &NewIngestJob {
+        sync_client,
+        kind,
+        payload,
+        commits_total,
+    };
+
+    Ok(diesel::insert_into(ingest_jobs::table)
+        .values(new_job)
+        .get_result::<IngestJob>(conn)?)
+}
+
+#[derive(Insertable)]
+#[table_name = "ingest_job_batches"]
+struct NewIngestJobBatch<'a> {
+    job: i32,
+    seq: i32,
+    payload: &'a str,
+}
+
+/// Updates the repository description and commit count of a job that is still being queued.
+pub fn update_payload(conn: &PgConnection, id: i32, payload: &str, commits_total: i32) -> Result<IngestJob, Error> {
+    Ok(diesel::update(ingest_jobs::table.find(id))
+        .set((
+            ingest_jobs::payload.eq(payload),
+            ingest_jobs::commits_total.eq(commits_total),
+        ))
+        .get_result::<IngestJob>(conn)?)
+}
+
+pub fn create_batch(conn: &PgConnection, job: i32, seq: i32, payload: &str) -> Result<usize, Error> {
+    Ok(diesel::insert_into(ingest_job_batches::table)
+        .values(&NewIngestJobBatch { job, seq, payload })
+        .execute(conn)?)
+}
+
+/// Sequence number and commits of the first batch of the job that has not been ingested yet.
+pub fn next_batch(conn: &PgConnection, job: i32) -> Result<Option<(i32, String)>, Error> {
+    Ok(ingest_job_batches::table
+        .filter(ingest_job_batches::job.eq(job))
+        .order(ingest_job_batches::seq)
+        .select((ingest_job_batches::seq, ingest_job_batches::payload))
+        .first::<(i32, String)>(conn)
+        .optional()?)
+}
+
+pub fn delete_batch(conn: &PgConnection, job: i32, seq: i32) -> Result<usize, Error> {
+    Ok(diesel::delete(ingest_job_batches::table.find((job, seq)))
+        .execute(conn)?)
+}
+
+fn delete_batches(conn: &PgConnection, job: i32) -> Result<usize, Error> {
+    Ok(diesel::delete(ingest_job_batches::table.filter(ingest_job_batches::job.eq(job)))
+        .execute(conn)?)
+}
+
+pub fn find(conn: &PgConnection, id: i32) -> Result<IngestJob, Error> {
+    Ok(ingest_jobs::table
+        .find(id)
+        .get_result::<IngestJob>(conn)?)
+}
+
+/// Marks the oldest queued job as running and returns it. Concurrent workers skip rows
+/// locked by each other, so every job is claimed exactly once. A running job whose worker has
+/// not reported progress for `timeout_secs` is considered abandoned and claimed again, keeping
+/// the progress of the batches it already ingested.
+pub fn claim_next(conn: &PgConnection, timeout_secs: i32) -> Result<Option<IngestJob>, Error> {
+    Ok(sql_query("
+        UPDATE ingest_jobs
+        SET status = 'running',
+            started_at = NOW(),
+            heartbeat_at = NOW(),
+            attempts = attempts + 1
+        WHERE id = (
+            SELECT id
+            FROM ingest_jobs
+            WHERE status = 'queued'
+               OR (status = 'running' AND heartbeat_at < NOW() - $1 * INTERVAL '1 second')
+            ORDER BY id
+            FOR UPDATE SKIP LOCKED
+            LIMIT 1)
+        RETURNING *")
+        .bind::<sql_types::Integer, _>(timeout_secs)
+        .get_result::<IngestJob>(conn)
+        .optional()?)
+}
+
+/// Records the commits processed so far and that the worker is still alive.
+pub fn update_progress(
+    conn: &PgConnection,
+    id: i32,
+    repository_id: Option<i32>,
+    processed: i32,
+    inserted: i32,
+    updated: i32,
+    skipped: i32,
+) -> Result<usize, Error> {
+    Ok(diesel::update(ingest_jobs::table.find(id))
+        .set((
+            ingest_jobs::repository_id.eq(repository_id),
+            ingest_jobs::commits_processed.eq(processed),
+            ingest_jobs::inserted.eq(inserted),
+            ingest_jobs::updated.eq(updated),
+            ingest_jobs::skipped.eq(skipped),
+            ingest_jobs::heartbeat_at.eq(Utc::now()),
+        ))
+        .execute(conn)?)
+}
+
+pub fn finish(
+    conn: &PgConnection,
+    id: i32,
+    repository_id: Option<i32>,
+    inserted: i32,
+    updated: i32,
+    skipped: i32,
+) -> Result<usize, Error> {
+    delete_batches(conn, id)?;
+    Ok(diesel::update(ingest_jobs::table.find(id))
+        .set((
+            ingest_jobs::status.eq(STATUS_DONE),
+            ingest_jobs::payload.eq(None::<String>),
+            ingest_jobs::repository_id.eq(repository_id),
+            ingest_jobs::inserted.eq(inserted),
+            ingest_jobs::updated.eq(updated),
+            ingest_jobs::skipped.eq(skipped),
+            ingest_jobs::finished_at.eq(Utc::now()),
+        ))
+        .execute(conn)?)
+}
+
+pub fn fail(conn: &PgConnection, id: i32, errors: &str) -> Result<usize, Error> {
+    delete_batches(conn, id)?;
+    Ok(diesel::update(ingest_jobs::table.find(id))
+        .set((
+            ingest_jobs::status.eq(STATUS_FAILED),
+            ingest_jobs::payload.eq(None::<String>),
+            ingest_jobs::errors.eq(errors),
+            ingest_jobs::finished_at.eq(Utc::now()),
+        ))
+        .execute(conn)?)
+}
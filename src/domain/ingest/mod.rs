@@ -0,0 +1,14 @@
+pub mod db;
+pub mod model;
+pub mod resource;
+pub mod routes;
+pub mod service;
+pub mod worker;
+
+pub const KIND_CREATE: &str = "create";
+pub const KIND_UPDATE: &str = "update";
+
+pub const STATUS_QUEUED: &str = "queued";
+pub const STATUS_RUNNING: &str = "running";
+pub const STATUS_DONE: &str = "done";
+pub const STATUS_FAILED: &str = "failed";
@@ -0,0 +1,58 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::config::DATE_FORMAT;
+use crate::domain::ingest::resource::IngestJobJson;
+use crate::schema::ingest_jobs;
+
+#[derive(Queryable, QueryableByName, Identifiable)]
+#[table_name = "ingest_jobs"]
+pub struct IngestJob {
+    pub id: i32,
+    pub sync_client: i32,
+    pub kind: String,
+    pub status: String,
+    pub payload: Option<String>,
+    pub commits_total: i32,
+    pub inserted: i32,
+    pub updated: i32,
+    pub skipped: i32,
+    pub repository_id: Option<i32>,
+    pub errors: Option<String>,
+    pub added_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub commits_processed: i32,
+    pub attempts: i32,
+    pub heartbeat_at: Option<DateTime<Utc>>,
+}
+
+/// Repository a job ingests into, its commits are stored as batches in `ingest_job_batches`.
+#[derive(Serialize, Deserialize)]
+pub struct IngestPayload {
+    pub user: String,
+    pub provider: String,
+    pub repo: String,
+    #[serde(default)]
+    pub removed_hashes: Vec<String>,
+}
+
+impl IngestJob {
+    pub fn attach(self) -> IngestJobJson {
+        IngestJobJson {
+            id: self.id,
+            kind: self.kind,
+            status: self.status,
+            commits_total: self.commits_total,
+            commits_processed: self.commits_processed,
+            inserted: self.inserted,
+            updated: self.updated,
+            skipped: self.skipped,
+            repository_id: self.repository_id,
+            errors: self.errors.and_then(|e| serde_json::from_str(&e).ok()),
+            added_at: self.added_at.format(DATE_FORMAT).to_string(),
+            started_at: self.started_at.map(|t| t.format(DATE_FORMAT).to_string()),
+            finished_at: self.finished_at.map(|t| t.format(DATE_FORMAT).to_string()),
+        }
+    }
+}
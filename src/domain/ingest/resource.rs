@@ -0,0 +1,46 @@
+use okapi::openapi3::Responses;
+use rocket_contrib::json::Json;
+use rocket_okapi::gen::OpenApiGenerator;
+use rocket_okapi::OpenApiError;
+use rocket_okapi::response::OpenApiResponder;
+use rocket_okapi::util::add_schema_response;
+use schemars::JsonSchema;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::domain::repository::resource::RepositoryJson;
+
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct IngestJobJson {
+    pub id: i32,
+    pub kind: String,
+    pub status: String,
+    pub commits_total: i32,
+    pub commits_processed: i32,
+    pub inserted: i32,
+    pub updated: i32,
+    pub skipped: i32,
+    pub repository_id: Option<i32>,
+    pub errors: Option<Value>,
+    pub added_at: String,
+    pub started_at: Option<String>,
+    pub finished_at: Option<String>,
+}
+
+#[derive(Responder)]
+pub enum IngestResponse {
+    #[response(status = 200, content_type = "json")]
+    Done(Json<RepositoryJson>),
+    #[response(status = 202, content_type = "json")]
+    Queued(Json<IngestJobJson>),
+}
+
+impl<'a> OpenApiResponder<'a> for IngestResponse {
+    fn responses(gen: &mut OpenApiGenerator) -> Result<Responses, OpenApiError> {
+        let mut responses = Responses::default();
+        add_schema_response(&mut responses, 200, "application/json", gen.json_schema::<RepositoryJson>())?;
+        add_schema_response(&mut responses, 202, "application/json", gen.json_schema::<IngestJobJson>())?;
+        Ok(responses)
+    }
+}
@@ -0,0 +1,18 @@
+use rocket_contrib::json::Json;
+
+use crate::domain::db::Conn;
+use crate::domain::ingest;
+use crate::domain::ingest::resource::IngestJobJson;
+use crate::errors::Error;
+use crate::security::api_key::ApiKey;
+
+#[openapi]
+#[get("/ingest/jobs/<job_id>")]
+pub fn get_ingest_job(
+    conn: Conn,
+    api_key: ApiKey,
+    job_id: i32,
+) -> Result<Json<IngestJobJson>, Error> {
+    let job = ingest::service::find_job(&conn, &api_key, job_id)?;
+    Ok(Json(job))
+}
@@ -0,0 +1,233 @@
+use diesel::{Connection, PgConnection};
+
+use crate::domain::{commit, ingest, repository};
+use crate::domain::ingest::KIND_UPDATE;
+use crate::domain::commit::routes::NewCommitData;
+use crate::domain::ingest::model::{IngestJob, IngestPayload};
+use crate::domain::ingest::resource::IngestJobJson;
+use crate::domain::repository::resource::RepositoryJson;
+use crate::domain::repository::upload::UploadHeader;
+use crate::domain::sync;
+use crate::errors::Error;
+use crate::security::api_key::ApiKey;
+
+/// Seconds without progress after which a running job counts as abandoned by its worker.
+const JOB_TIMEOUT_SECS: i32 = 10 * 60;
+const MAX_JOB_ATTEMPTS: i32 = 3;
+
+/// Queues a sync upload batch by batch as it is parsed, so neither the request nor a single job
+/// row holds all of its commits. The job only becomes visible to workers once the surrounding
+/// transaction commits.
+pub struct JobUpload<'a> {
+    conn: &'a PgConnection,
+    sync_client: i32,
+    kind: &'a str,
+    job: Option<IngestJob>,
+    batches: i32,
+}
+
+impl<'a> JobUpload<'a> {
+    pub fn new(conn: &'a PgConnection, api_key: &ApiKey, kind: &'a str) -> Result<JobUpload<'a>, Error> {
+        let client = sync::db::find_by_api_key(conn, &api_key.key)
+            .map_err(|_| Error::AuthorizationError("Unauthorized repository update!"))?;
+        Ok(JobUpload { conn, sync_client: client.id, kind, job: None, batches: 0 })
+    }
+
+    pub fn batch(&mut self, header: &UploadHeader, offset: usize, commits: Vec<NewCommitData>) -> Result<(), Error> {
+        commit::service::validate_commits(&commits, offset)?;
+        let job = self.job(header)?;
+        let payload = serde_json::to_string(&commits)
+            .map_err(|_| Error::Custom("Cannot queue repository payload!"))?;
+        ingest::db::create_batch(self.conn, job, self.batches, &payload)?;
+        self.batches += 1;
+        Ok(())
+    }
+
+    pub fn finish(
+        mut self,
+        header: &UploadHeader,
+        removed_hashes: Vec<String>,
+        commits_total: usize,
+    ) -> Result<IngestJobJson, Error> {
+        let job = self.job(header)?;
+        let payload = serde_json::to_string(&IngestPayload {
+            user: header.user.clone(),
+            provider: header.provider.clone(),
+            repo: header.repo.clone(),
+            removed_hashes,
+        }).map_err(|_| Error::Custom("Cannot queue repository payload!"))?;
+
+        let job = ingest::db::update_payload(self.conn, job, &payload, commits_total as i32)?;
+        Ok(job.attach())
+    }
+
+    /// Creates the job with the first batch, once the repository is known.
+    fn job(&mut self, header: &UploadHeader) -> Result<i32, Error> {
+        if let Some(job) = &self.job {
+            return Ok(job.id);
+        }
+        if self.kind == KIND_UPDATE {
+            let repository = repository::db::find(self.conn, &header.user, &header.provider, &header.repo)
+                .map_err(|_| Error::BadRequest("Repository not found!"))?;
+            if repository.sync_client.is_some() && repository.sync_client.unwrap() != self.sync_client {
+                return Err(Error::AuthorizationError("Illegal repository update!"));
+            }
+        }
+        let payload = serde_json::to_string(&IngestPayload {
+            user: header.user.clone(),
+            provider: header.provider.clone(),
+            repo: header.repo.clone(),
+            removed_hashes: vec![],
+        }).map_err(|_| Error::Custom("Cannot queue repository payload!"))?;
+
+        let job = ingest::db::create(self.conn, self.sync_client, self.kind, &payload, 0)?;
+        let id = job.id;
+        self.job = Some(job);
+        Ok(id)
+    }
+}
+
+pub fn find_job(conn: &PgConnection, api_key: &ApiKey, id: i32) -> Result<IngestJobJson, Error> {
+    let client = sync::db::find_by_api_key(conn, &api_key.key)
+        .map_err(|_| Error::AuthorizationError("Unauthorized!"))?;
+    let job = ingest::db::find(conn, id)
+        .map_err(|_| Error::BadRequest("Job not found!"))?;
+    if job.sync_client != client.id {
+        return Err(Error::AuthorizationError("Unauthorized!"));
+    }
+    Ok(job.attach())
+}
+
+/// Runs the oldest queued or abandoned job, returns `false` when the queue is empty.
+pub fn process_next_job(conn: &PgConnection) -> Result<bool, Error> {
+    let job = match ingest::db::claim_next(conn, JOB_TIMEOUT_SECS)? {
+        Some(job) => job,
+        None => return Ok(false),
+    };
+    if job.attempts > MAX_JOB_ATTEMPTS {
+        ingest::db::fail(conn, job.id, &describe_error(Error::Custom("Job was abandoned too often!")))?;
+        return Ok(true);
+    }
+
+    let result = job.payload.as_ref()
+        .and_then(|p| serde_json::from_str::<IngestPayload>(p).ok())
+        .ok_or(Error::Custom("Malformed job payload!"))
+        .and_then(|p| run_job(conn, &job, p));
+
+    match result {
+        Ok(progress) => {
+            ingest::db::finish(
+                conn,
+                job.id,
+                progress.repository_id,
+                progress.inserted,
+                progress.updated,
+                progress.skipped,
+            )?;
+        }
+        Err(err) => {
+            ingest::db::fail(conn, job.id, &describe_error(err))?;
+        }
+    }
+    Ok(true)
+}
+
+struct JobProgress {
+    repository_id: Option<i32>,
+    processed: i32,
+    inserted: i32,
+    updated: i32,
+    skipped: i32,
+}
+
+impl JobProgress {
+    /// Progress recorded by the previous attempts of the job.
+    fn resume(job: &IngestJob) -> JobProgress {
+        JobProgress {
+            repository_id: job.repository_id,
+            processed: job.commits_processed,
+            inserted: job.inserted,
+            updated: job.updated,
+            skipped: job.skipped,
+        }
+    }
+
+    fn add(&mut self, repository: &RepositoryJson, processed: i32) {
+        self.repository_id = Some(repository.id);
+        self.processed += processed;
+        self.inserted += repository.ingestion.inserted.len() as i32;
+        self.updated += repository.ingestion.updated.len() as i32;
+        self.skipped += repository.ingestion.skipped.len() as i32;
+    }
+}
+
+/// Ingests the stored batches of a job one after another. Each batch is deleted in the same
+/// transaction that stores its commits and records the progress, so a job that is claimed again
+/// resumes with the first batch that was not stored and never recreates its repository.
+fn run_job(conn: &PgConnection, job: &IngestJob, payload: IngestPayload) -> Result<JobProgress, Error> {
+    let header = UploadHeader {
+        user: payload.user,
+        provider: payload.provider,
+        repo: payload.repo,
+    };
+    let mut progress = JobProgress::resume(job);
+
+    while let Some((seq, batch)) = ingest::db::next_batch(conn, job.id)? {
+        let commits: Vec<NewCommitData> = serde_json::from_str(&batch)
+            .map_err(|_| Error::Custom("Malformed job payload!"))?;
+        let batch_size = commits.len() as i32;
+        conn.transaction::<_, Error, _>(|| {
+            let repository = repository::service::ingest_batch(
+                conn,
+                job.sync_client,
+                &job.kind,
+                seq == 0,
+                &header,
+                commits,
+                vec![],
+            )?;
+
+            progress.add(&repository, batch_size);
+            ingest::db::delete_batch(conn, job.id, seq)?;
+            ingest::db::update_progress(
+                conn,
+                job.id,
+                progress.repository_id,
+                progress.processed,
+                progress.inserted,
+                progress.updated,
+                progress.skipped,
+            )
+        })?;
+    }
+
+    // Removed hashes are applied once all new commits are stored, a job without commits still
+    // creates its repository
+    let first = progress.repository_id.is_none();
+    if first || (job.kind == KIND_UPDATE && !payload.removed_hashes.is_empty()) {
+        let removed_hashes = if job.kind == KIND_UPDATE { payload.removed_hashes } else { vec![] };
+        let repository = repository::service::ingest_batch(
+            conn,
+            job.sync_client,
+            &job.kind,
+            first,
+            &header,
+            vec![],
+            removed_hashes,
+        )?;
+        progress.add(&repository, 0);
+    }
+    Ok(progress)
+}
+
+fn describe_error(err: Error) -> String {
+    let errors = match err {
+        Error::PayloadValidationError(errors) => json!(errors),
+        Error::AuthorizationError(msg) | Error::BadRequest(msg) | Error::Custom(msg) => json!({ "error": msg }),
+        err => {
+            error!("Ingest job failed: {:?}", err);
+            json!({ "error": "Something went wrong! :(" })
+        }
+    };
+    errors.to_string()
+}
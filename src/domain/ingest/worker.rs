@@ -0,0 +1,42 @@
+use std::thread;
+use std::time::Duration;
+
+use diesel::{Connection, PgConnection};
+use rocket::fairing::AdHoc;
+use rocket_contrib::databases::database_config;
+
+use crate::domain::ingest;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Starts a background thread that works through queued ingest jobs once the server launches.
+pub fn start() -> AdHoc {
+    AdHoc::on_launch("Ingest worker", |rocket| {
+        match database_config("diesel_postgres_pool", rocket.config()) {
+            Ok(config) => {
+                let url = config.url.to_string();
+                thread::spawn(move || run(&url));
+            }
+            Err(e) => error!("Ingest worker not started: {:?}", e),
+        }
+    })
+}
+
+fn run(url: &str) {
+    loop {
+        match PgConnection::establish(url) {
+            Ok(conn) => loop {
+                match ingest::service::process_next_job(&conn) {
+                    Ok(true) => continue,
+                    Ok(false) => thread::sleep(POLL_INTERVAL),
+                    Err(e) => {
+                        error!("Ingest worker failed: {:?}", e);
+                        break;
+                    }
+                }
+            },
+            Err(e) => error!("Ingest worker cannot connect to database: {}", e),
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
@@ -0,0 +1,90 @@
+use diesel::{Insertable, sql_query, sql_types};
+use diesel::pg::PgConnection;
+use diesel::pg::upsert::excluded;
+use diesel::prelude::*;
+
+use crate::common::sql::{FILE_LANGUAGE, GROUP_CHILDREN_QUERY, QUERIED_GROUP, TEST_FILE_PATTERN, path_rules_filter};
+use crate::domain::language::dwh::LanguageStats;
+use crate::domain::language::model::LanguageMapping;
+use crate::errors::Error;
+use crate::schema::language_mappings;
+
+#[derive(Insertable)]
+#[table_name = "language_mappings"]
+pub struct NewLanguageMapping {
+    pub extension: String,
+    pub language: String,
+}
+
+pub fn find_all(conn: &PgConnection) -> Result<Vec<LanguageMapping>, Error> {
+    Ok(language_mappings::table
+        .order(language_mappings::extension)
+        .load::<LanguageMapping>(conn)?)
+}
+
+/// Inserts the mappings, extensions that are already mapped get the new language.
+pub fn upsert(
+    conn: &PgConnection,
+    new_mappings: Vec<NewLanguageMapping>,
+) -> Result<usize, Error> {
+    if new_mappings.len() > 0 {
+        return Ok(diesel::insert_into(language_mappings::table)
+            .values(new_mappings)
+            .on_conflict(language_mappings::extension)
+            .do_update()
+            .set(language_mappings::language.eq(excluded(language_mappings::language)))
+            .execute(conn)?);
+    }
+    Ok(0)
+}
+
+pub fn delete(conn: &PgConnection, extension: &str) -> Result<usize, Error> {
+    Ok(diesel::delete(language_mappings::table
+        .filter(language_mappings::extension.eq(extension)))
+        .execute(conn)?)
+}
+
+pub fn fetch_group_language_stats(
+    conn: &PgConnection,
+    group_name: &str,
+    start: i64,
+    end: i64,
+) -> Result<Vec<LanguageStats>, Error> {
+    let stats: Vec<LanguageStats> = sql_query(format!("
+        {}
+        SELECT {}                                                          AS language,
+            files.path ~ '{}'                                              AS is_test,
+            coalesce(sum(files.time)::bigint, 0)                           AS total_time,
+            coalesce(sum(files.lines_added)::bigint, 0)                    AS lines_added,
+            coalesce(sum(files.lines_deleted)::bigint, 0)                  AS lines_removed,
+            count(DISTINCT (repositories.id, files.path))::bigint          AS files,
+            count(DISTINCT commits.hash)::bigint                           AS commits,
+            count(DISTINCT coalesce(users.username, commits.email))::bigint AS users
+        FROM groups gr
+            LEFT JOIN repositories on gr.id = repositories.group
+            LEFT JOIN commits ON commits.repository_id = repositories.id
+            LEFT JOIN files ON files.commit = commits.id
+            LEFT JOIN emails ON commits.email = emails.email
+            LEFT JOIN users ON emails.user = users.id
+        WHERE repositories.group IN (
+            SELECT DISTINCT group_repos_query.child
+            FROM group_repos_query
+            UNION
+            (
+                SELECT g.id
+                FROM groups g
+                WHERE g.name = $1))
+            AND commits.timestamp >= $2
+            AND commits.timestamp < $3
+            AND NOT commits.is_superseded
+            {}
+            AND files.path IS NOT NULL
+        GROUP BY 1, 2
+        ORDER BY total_time DESC, language, is_test;", GROUP_CHILDREN_QUERY, FILE_LANGUAGE, TEST_FILE_PATTERN, path_rules_filter(QUERIED_GROUP)))
+        .bind::<sql_types::Text, _>(group_name)
+        .bind::<sql_types::BigInt, _>(start)
+        .bind::<sql_types::BigInt, _>(end)
+        .load(conn)?;
+
+    Ok(stats)
+}
@@ -0,0 +1,21 @@
+use diesel::sql_types::{BigInt, Bool, Text};
+
+#[derive(QueryableByName, Debug)]
+pub struct LanguageStats {
+    #[sql_type = "Text"]
+    pub language: String,
+    #[sql_type = "Bool"]
+    pub is_test: bool,
+    #[sql_type = "BigInt"]
+    pub total_time: i64,
+    #[sql_type = "BigInt"]
+    pub lines_added: i64,
+    #[sql_type = "BigInt"]
+    pub lines_removed: i64,
+    #[sql_type = "BigInt"]
+    pub files: i64,
+    #[sql_type = "BigInt"]
+    pub commits: i64,
+    #[sql_type = "BigInt"]
+    pub users: i64,
+}
@@ -0,0 +1,17 @@
+use crate::domain::language::dwh::LanguageStats;
+use crate::domain::language::resource::LanguageStatsJson;
+
+pub fn map_language_stats(data: Vec<LanguageStats>) -> Vec<LanguageStatsJson> {
+    data.into_iter()
+        .map(|stats| LanguageStatsJson {
+            language: stats.language,
+            is_test: stats.is_test,
+            total_time: (stats.total_time as f64 / 60.0 / 60.0 * 10.0).round() / 10.0,
+            lines_added: stats.lines_added,
+            lines_removed: stats.lines_removed,
+            files: stats.files,
+            commits: stats.commits,
+            users: stats.users,
+        })
+        .collect()
+}
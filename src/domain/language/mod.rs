@@ -0,0 +1,7 @@
+pub mod db;
+pub mod dwh;
+pub mod mapper;
+pub mod model;
+pub mod resource;
+pub mod routes;
+pub mod service;
@@ -0,0 +1,17 @@
+use crate::domain::language::resource::LanguageMappingJson;
+
+#[derive(Queryable, Debug, Clone)]
+pub struct LanguageMapping {
+    pub id: i32,
+    pub extension: String,
+    pub language: String,
+}
+
+impl LanguageMapping {
+    pub fn attach(self) -> LanguageMappingJson {
+        LanguageMappingJson {
+            extension: self.extension,
+            language: self.language,
+        }
+    }
+}
@@ -0,0 +1,20 @@
+use serde::Serialize;
+use schemars::JsonSchema;
+
+#[derive(Serialize, JsonSchema)]
+pub struct LanguageMappingJson {
+    pub extension: String,
+    pub language: String,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct LanguageStatsJson {
+    pub language: String,
+    pub is_test: bool,
+    pub total_time: f64,
+    pub lines_added: i64,
+    pub lines_removed: i64,
+    pub files: i64,
+    pub commits: i64,
+    pub users: i64,
+}
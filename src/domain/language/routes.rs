@@ -0,0 +1,83 @@
+use rocket::request::Form;
+use rocket_contrib::json::Json;
+use rocket_okapi::{JsonSchema, openapi};
+use serde::Deserialize;
+use validator::Validate;
+
+use crate::domain::db::Conn;
+use crate::domain::group;
+use crate::domain::group::routes::GroupStatsParams;
+use crate::domain::language;
+use crate::domain::language::resource::{LanguageMappingJson, LanguageStatsJson};
+use crate::domain::role::model::ADMIN;
+use crate::domain::user::model::AuthUser;
+use crate::errors::Error;
+use crate::security;
+
+#[derive(Deserialize, Validate, JsonSchema)]
+pub struct NewLanguageMapping {
+    #[validate(length(min = 1, max = 32))]
+    pub extension: Option<String>,
+    #[validate(length(min = 1, max = 64))]
+    pub language: Option<String>,
+}
+
+#[openapi]
+#[get("/languages")]
+pub fn get_language_mappings(
+    _auth_user: AuthUser,
+    conn: Conn,
+) -> Result<Json<Vec<LanguageMappingJson>>, Error> {
+    let mappings = language::db::find_all(&conn)?
+        .into_iter()
+        .map(|m| m.attach())
+        .collect();
+    Ok(Json(mappings))
+}
+
+#[openapi]
+#[post("/languages", format = "json", data = "<mappings>")]
+pub fn post_language_mappings(
+    auth_user: AuthUser,
+    mappings: Json<Vec<NewLanguageMapping>>,
+    conn: Conn,
+) -> Result<Json<Vec<LanguageMappingJson>>, Error> {
+    auth_user.require_role(&ADMIN)?;
+    language::service::add_language_mappings(&conn, mappings.into_inner())?;
+    let mappings = language::db::find_all(&conn)?
+        .into_iter()
+        .map(|m| m.attach())
+        .collect();
+    Ok(Json(mappings))
+}
+
+#[openapi]
+#[delete("/languages/<extension>")]
+pub fn delete_language_mapping(
+    auth_user: AuthUser,
+    extension: String,
+    conn: Conn,
+) -> Result<Json<usize>, Error> {
+    auth_user.require_role(&ADMIN)?;
+    let count = language::service::delete_language_mapping(&conn, &extension)?;
+    Ok(Json(count))
+}
+
+#[openapi]
+#[get("/groups/<group_name>/languages?<params..>")]
+pub fn get_group_languages(
+    auth_user: AuthUser,
+    conn: Conn,
+    group_name: String,
+    params: Form<GroupStatsParams>,
+) -> Result<Json<Vec<LanguageStatsJson>>, Error> {
+    if auth_user.require_role(&ADMIN).is_err() {
+        security::service::check_group_access(&conn, auth_user.user_id, &group_name)?;
+    }
+    let period = params.into_inner();
+    let (start, end) = group::service::default_period(&conn, &group_name, period.start, period.end);
+    let start = start.unwrap_or(0);
+    let end = end.unwrap_or(std::i64::MAX);
+    let stats = language::service::get_group_language_stats(&conn, &group_name, start, end)?;
+    Ok(Json(stats))
+}
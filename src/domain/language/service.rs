@@ -0,0 +1,44 @@
+use diesel::PgConnection;
+
+use crate::domain::language::db;
+use crate::domain::language::mapper::map_language_stats;
+use crate::domain::language::resource::LanguageStatsJson;
+use crate::domain::language::routes::NewLanguageMapping;
+use crate::errors::{Error, FieldValidator};
+
+pub fn add_language_mappings(
+    conn: &PgConnection,
+    mappings: Vec<NewLanguageMapping>,
+) -> Result<usize, Error> {
+    let mut new_mappings = Vec::with_capacity(mappings.len());
+    for mapping in mappings {
+        let mut extractor = FieldValidator::validate(&mapping);
+        let extension = extractor.extract("extension", mapping.extension);
+        let language = extractor.extract("language", mapping.language);
+        extractor.check()?;
+        new_mappings.push(db::NewLanguageMapping {
+            extension: normalize_extension(&extension),
+            language: language.trim().to_string(),
+        });
+    }
+    db::upsert(conn, new_mappings)
+}
+
+pub fn delete_language_mapping(conn: &PgConnection, extension: &str) -> Result<usize, Error> {
+    db::delete(conn, &normalize_extension(extension))
+}
+
+pub fn get_group_language_stats(
+    conn: &PgConnection,
+    group_name: &str,
+    start: i64,
+    end: i64,
+) -> Result<Vec<LanguageStatsJson>, Error> {
+    let stats = db::fetch_group_language_stats(conn, group_name, start, end)?;
+    Ok(map_language_stats(stats))
+}
+
+// Paths are matched by their lowercase extension without the dot
+fn normalize_extension(extension: &str) -> String {
+    extension.trim().trim_start_matches('.').to_lowercase()
+}
@@ -1,4 +1,5 @@
 pub mod db;
+pub mod branch;
 pub mod commit;
 pub mod file;
 pub mod email;
@@ -9,5 +10,9 @@ pub mod group;
 pub mod role;
 pub mod user_role_member;
 pub mod group_access;
+pub mod group_path_rule;
+pub mod ingest;
+pub mod language;
+pub mod session;
 pub mod sync;
 pub mod timeline;
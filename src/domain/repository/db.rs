@@ -1,15 +1,20 @@
+use std::collections::HashMap;
+
 use diesel;
 use diesel::{Insertable, sql_query, sql_types};
 use diesel::pg::PgConnection;
 use diesel::prelude::*;
 
+use crate::common::sql::{REPOSITORY_GROUP, path_rules_filter};
 use crate::domain::commit;
 use crate::domain::commit::routes::NewCommitData;
+use crate::domain::group::dwh::{GroupFileStats, GroupUserStats};
+use crate::domain::group::model::Group;
 use crate::errors::Error;
 use crate::domain::repository;
 use crate::domain::repository::model::Repository;
 use crate::domain::repository::resource::RepositoryJson;
-use crate::schema::repositories;
+use crate::schema::{groups, repositories};
 
 #[derive(Insertable)]
 #[table_name = "repositories"]
@@ -28,26 +33,31 @@ pub fn update(
     repo: &str,
     sync_client: i32,
     commits: Vec<NewCommitData>,
+    removed_hashes: Vec<String>,
 ) -> Result<RepositoryJson, Error> {
-    let repository = repository::db::find(&conn, &user, &provider, &repo)?;
-
-    if repository.sync_client.is_none() {
-        diesel::update(&repository).set(
-            repositories::sync_client.eq(sync_client)
-        ).execute(conn)?;
-    } else {
-        if repository.sync_client.unwrap() != sync_client {
-            return Err(Error::AuthorizationError("Illegal repository update!"));
+    conn.transaction::<_, Error, _>(|| {
+        let repository = repository::db::find(&conn, &user, &provider, &repo)?;
+
+        if repository.sync_client.is_none() {
+            diesel::update(&repository).set(
+                repositories::sync_client.eq(sync_client)
+            ).execute(conn)?;
+        } else {
+            if repository.sync_client.unwrap() != sync_client {
+                return Err(Error::AuthorizationError("Illegal repository update!"));
+            }
         }
-    }
 
-    let commits_vec = commit::db::create_all(
-        &conn,
-        commits,
-        repository.id,
-    )?;
+        let commits_total = commits.len();
+        let ingestion = commit::db::create_all(
+            &conn,
+            commits,
+            removed_hashes,
+            repository.id,
+        )?;
 
-    Ok(repository.attach(commits_vec))
+        Ok(repository.attach(commits_total, ingestion))
+    })
 }
 
 pub fn create(
@@ -67,21 +77,24 @@ pub fn create(
         sync_client
     };
 
-    if exists(conn, user, provider, repo) {
-        remove_repo(conn, user, provider, repo)?;
-    }
+    conn.transaction::<_, Error, _>(|| {
+        if exists(conn, user, provider, repo) {
+            remove_repo(conn, user, provider, repo)?;
+        }
 
-    let repository = diesel::insert_into(repositories::table)
-        .values(new_repository)
-        .get_result::<Repository>(conn)
-        .expect("Error creating repository");
-
-    let commits_vec = commit::db::create_all(
-        &conn,
-        commits,
-        repository.id
-    )?;
-    Ok(repository.attach(commits_vec))
+        let repository = diesel::insert_into(repositories::table)
+            .values(new_repository)
+            .get_result::<Repository>(conn)?;
+
+        let commits_total = commits.len();
+        let ingestion = commit::db::create_all(
+            &conn,
+            commits,
+            vec![],
+            repository.id
+        )?;
+        Ok(repository.attach(commits_total, ingestion))
+    })
 }
 
 pub fn exists(conn: &PgConnection, user: &str, provider: &str, repo: &str) -> bool {
@@ -105,6 +118,46 @@ pub fn find(conn: &PgConnection, user: &str, provider: &str, repo: &str) -> Resu
         .map_err(Error::DatabaseError)
 }
 
+pub fn find_by_id(conn: &PgConnection, repository_id: i32) -> Result<(Repository, Group), Error> {
+    Ok(repositories::table
+        .inner_join(groups::table)
+        .filter(repositories::id.eq(repository_id))
+        .get_result::<(Repository, Group)>(conn)?)
+}
+
+pub fn find_all(conn: &PgConnection) -> Result<Vec<(Repository, Group)>, Error> {
+    Ok(repositories::table
+        .inner_join(groups::table)
+        .order(repositories::id)
+        .load::<(Repository, Group)>(conn)?)
+}
+
+pub fn find_all_by_groups(conn: &PgConnection, group_ids: &Vec<i32>) -> Result<Vec<(Repository, Group)>, Error> {
+    Ok(repositories::table
+        .inner_join(groups::table)
+        .filter(repositories::group.eq_any(group_ids))
+        .order(repositories::id)
+        .load::<(Repository, Group)>(conn)?)
+}
+
+/// Number of repositories directly in each of the given groups, groups without any are left out.
+pub fn count_by_groups(conn: &PgConnection, group_ids: &[i32]) -> Result<HashMap<i32, usize>, Error> {
+    let mut counts = HashMap::new();
+    for group in repositories::table
+        .select(repositories::group)
+        .filter(repositories::group.eq_any(group_ids))
+        .load::<i32>(conn)?
+    {
+        *counts.entry(group).or_insert(0) += 1;
+    }
+    Ok(counts)
+}
+
+pub fn delete_all_by_group(conn: &PgConnection, group_id: i32) -> Result<usize, Error> {
+    Ok(diesel::delete(repositories::table.filter(repositories::group.eq(group_id)))
+        .execute(conn)?)
+}
+
 pub fn remove_repo(conn: &PgConnection, user: &str, provider: &str, repo: &str) -> Result<usize, Error> {
     let count = diesel::delete(repositories::table.filter(repositories::user.eq(user)
         .and(repositories::provider.eq(provider)
@@ -151,3 +204,66 @@ pub fn find_all_repository_ids_in_group(conn: &PgConnection, name: &str) -> Resu
         .load(conn)?;
     Ok(res)
 }
+
+
+pub fn fetch_repository_user_stats(
+    conn: &PgConnection,
+    repository_id: i32,
+    start: i64,
+    end: i64
+) -> Result<Vec<GroupUserStats>, Error> {
+    let stats: Vec<GroupUserStats> = sql_query(format!("
+        SELECT coalesce(users.username, commits.email)        AS name,
+            coalesce(sum(files.time)::bigint, 0)              AS total_time,
+            coalesce(sum(files.lines_added)::bigint, 0)       AS lines_added,
+            coalesce(sum(files.lines_deleted)::bigint, 0)     AS lines_removed,
+            coalesce(count(DISTINCT commits.hash)::bigint, 0) AS commits
+        FROM commits
+            LEFT JOIN files ON files.commit = commits.id
+            LEFT JOIN emails ON commits.email = emails.email
+            LEFT JOIN users ON emails.user = users.id
+        WHERE commits.repository_id = $1
+            AND commits.timestamp >= $2
+            AND commits.timestamp < $3
+            AND NOT commits.is_superseded
+            {}
+        GROUP BY coalesce(users.username, commits.email)
+        ORDER BY total_time DESC;", path_rules_filter(REPOSITORY_GROUP)))
+        .bind::<sql_types::Integer, _>(repository_id)
+        .bind::<sql_types::BigInt, _>(start)
+        .bind::<sql_types::BigInt, _>(end)
+        .load(conn)?;
+
+    Ok(stats)
+}
+
+pub fn fetch_repository_file_stats(
+    conn: &PgConnection,
+    repository_id: i32,
+    start: i64,
+    end: i64
+) -> Result<Vec<GroupFileStats>, Error> {
+    let stats: Vec<GroupFileStats> = sql_query(format!("
+        SELECT files.path                                     AS path,
+            coalesce(sum(files.time)::bigint, 0)              AS total_time,
+            coalesce(sum(files.lines_added)::bigint, 0)       AS lines_added,
+            coalesce(sum(files.lines_deleted)::bigint, 0)     AS lines_removed,
+            coalesce(count(DISTINCT commits.hash)::bigint, 0) AS commits,
+            coalesce(users.username, commits.email)           AS user
+        FROM commits
+            INNER JOIN files ON files.commit = commits.id
+            LEFT JOIN emails ON commits.email = emails.email
+            LEFT JOIN users ON emails.user = users.id
+        WHERE commits.repository_id = $1
+            AND commits.timestamp >= $2
+            AND commits.timestamp < $3
+            AND NOT commits.is_superseded
+            {}
+        GROUP BY files.path, coalesce(users.username, commits.email);", path_rules_filter(REPOSITORY_GROUP)))
+        .bind::<sql_types::Integer, _>(repository_id)
+        .bind::<sql_types::BigInt, _>(start)
+        .bind::<sql_types::BigInt, _>(end)
+        .load(conn)?;
+
+    Ok(stats)
+}
@@ -2,11 +2,12 @@ use schemars::JsonSchema;
 use serde::Serialize;
 use crate::domain::repository::model::Repository;
 use crate::config::DATE_FORMAT;
-use crate::domain::commit::resource::CommitJson;
+use crate::domain::commit::resource::IngestionSummaryJson;
+use crate::domain::group::model::Group;
 
 
 impl Repository {
-    pub fn attach(self, commits: Vec<CommitJson>) -> RepositoryJson {
+    pub fn attach(self, commits_total: usize, ingestion: IngestionSummaryJson) -> RepositoryJson {
         RepositoryJson {
             id: self.id,
             user: self.user,
@@ -14,7 +15,22 @@ impl Repository {
             repo: self.repo,
             sync_client: self.sync_client,
             timestamp: self.added_at.format(DATE_FORMAT).to_string(),
-            commits
+            commits_total,
+            ingestion,
+        }
+    }
+}
+
+impl Repository {
+    pub fn attach_info(self, group: Group) -> RepositoryInfoJson {
+        RepositoryInfoJson {
+            id: self.id,
+            group: group.name,
+            user: self.user,
+            provider: self.provider,
+            repo: self.repo,
+            sync_client: self.sync_client,
+            timestamp: self.added_at.format(DATE_FORMAT).to_string(),
         }
     }
 }
@@ -28,5 +44,18 @@ pub struct RepositoryJson {
     pub repo: String,
     pub sync_client: Option<i32>,
     pub timestamp: String,
-    pub commits: Vec<CommitJson>
-}
\ No newline at end of file
+    /// Commits in the upload, stored commits are read back through the commit endpoints.
+    pub commits_total: usize,
+    pub ingestion: IngestionSummaryJson,
+}
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RepositoryInfoJson {
+    pub id: i32,
+    pub group: String,
+    pub user: String,
+    pub provider: String,
+    pub repo: String,
+    pub sync_client: Option<i32>,
+    pub timestamp: String,
+}
@@ -1,85 +1,226 @@
+use diesel::Connection;
+use rocket::request::Form;
 use rocket_contrib::json::Json;
 use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
 use serde::Deserialize;
-use validator::Validate;
 
-use crate::domain::commit::routes::NewCommitData;
+use crate::common::sync_json::SyncStream;
+use crate::domain::commit;
+use crate::domain::commit::routes::{NewCommitData, NewNoteCommitData};
 use crate::domain::db::Conn;
-use crate::domain::repository;
-use crate::domain::repository::resource::RepositoryJson;
+use crate::domain::group::resource::GroupStatsJson;
+use crate::domain::group::routes::GroupStatsParams;
+use crate::domain::{ingest, repository};
+use crate::domain::ingest::{KIND_CREATE, KIND_UPDATE};
+use crate::domain::ingest::resource::IngestResponse;
+use crate::domain::repository::resource::RepositoryInfoJson;
+use crate::domain::timeline;
+use crate::domain::timeline::resources::IntervalJson;
+use crate::domain::timeline::routes::{split_branches, split_languages, TimelineParams};
 use crate::domain::user::model::AuthUser;
 use crate::errors::{Error, FieldValidator};
 use crate::security::api_key::ApiKey;
 
+/// Schema of a sync upload, the payload itself is read in batches by `repository::upload::read`.
+#[allow(dead_code)]
 #[derive(Deserialize, JsonSchema)]
 pub struct NewRepository {
     repository: NewRepositoryData,
 }
 
-#[derive(Deserialize, Validate, JsonSchema)]
+#[allow(dead_code)]
+#[derive(Deserialize, JsonSchema)]
 pub struct NewRepositoryData {
-    #[validate(length(min = 1))]
     user: Option<String>,
-    #[validate(length(min = 1))]
     provider: Option<String>,
-    #[validate(length(min = 1))]
     repo: Option<String>,
+    /// Commits are ingested in batches as they are parsed, send them after `user`, `provider` and
+    /// `repo` so they don't have to be buffered.
     #[serde(rename = "commits")]
     commits: Vec<NewCommitData>,
+    /// Hashes that disappeared from their branch, e.g. after a rebase. Ignored when creating.
+    #[serde(default)]
+    removed_hashes: Vec<String>,
+}
+
+/// Schema of a sync upload carrying raw gtm notes, read like [`NewRepository`].
+#[allow(dead_code)]
+#[derive(Deserialize, JsonSchema)]
+pub struct NewNotesRepository {
+    repository: NewNotesRepositoryData,
+}
+
+#[allow(dead_code)]
+#[derive(Deserialize, JsonSchema)]
+pub struct NewNotesRepositoryData {
+    user: Option<String>,
+    provider: Option<String>,
+    repo: Option<String>,
+    #[serde(rename = "commits")]
+    commits: Vec<NewNoteCommitData>,
+    /// Hashes that disappeared from their branch, e.g. after a rebase. Ignored when creating.
+    #[serde(default)]
+    removed_hashes: Vec<String>,
+}
+
+#[derive(FromForm, Default, Deserialize, JsonSchema)]
+pub struct IngestParams {
+    /// Queue the payload and return the job instead of ingesting it within the request.
+    #[form(field = "async")]
+    #[serde(rename = "async")]
+    run_async: Option<bool>,
 }
 
 #[openapi]
-#[post("/repositories", format = "json", data = "<new_repository>")]
+#[post("/repositories?<params..>", format = "json", data = "<new_repository>")]
 pub fn post_repository(
     conn: Conn,
     api_key: ApiKey,
-    new_repository: Json<NewRepository>,
-) -> Result<Json<RepositoryJson>, Error> {
-    let new_repository = new_repository.into_inner().repository;
-
-    let mut extractor = FieldValidator::validate(&new_repository);
-    let user = extractor.extract("user", new_repository.user);
-    let provider = extractor.extract("provider", new_repository.provider);
-    let repo = extractor.extract("repo", new_repository.repo);
-    extractor.check()?;
+    new_repository: SyncStream<NewRepository>,
+    params: Form<IngestParams>,
+) -> Result<IngestResponse, Error> {
+    ingest_upload(&conn, &api_key, KIND_CREATE, &params, new_repository, |commits: Vec<NewCommitData>, _| Ok(commits))
+}
 
-    let repository = repository::service::create_repo(
-        &conn,
-        &api_key,
-        &user,
-        &provider,
-        &repo,
-        new_repository.commits,
-    )?;
+#[openapi]
+#[put("/repositories?<params..>", format = "json", data = "<new_repository>")]
+pub fn put_repository(
+    api_key: ApiKey,
+    new_repository: SyncStream<NewRepository>,
+    params: Form<IngestParams>,
+    conn: Conn,
+) -> Result<IngestResponse, Error> {
+    ingest_upload(&conn, &api_key, KIND_UPDATE, &params, new_repository, |commits: Vec<NewCommitData>, _| Ok(commits))
+}
 
-    Ok(Json(repository))
+#[openapi]
+#[post("/repositories/notes?<params..>", format = "json", data = "<new_repository>")]
+pub fn post_repository_notes(
+    conn: Conn,
+    api_key: ApiKey,
+    new_repository: SyncStream<NewNotesRepository>,
+    params: Form<IngestParams>,
+) -> Result<IngestResponse, Error> {
+    ingest_upload(&conn, &api_key, KIND_CREATE, &params, new_repository, commit::service::commits_from_notes)
 }
 
 #[openapi]
-#[put("/repositories", format = "json", data = "<new_repository>")]
-pub fn put_repository(
+#[put("/repositories/notes?<params..>", format = "json", data = "<new_repository>")]
+pub fn put_repository_notes(
+    conn: Conn,
     api_key: ApiKey,
-    new_repository: Json<NewRepository>,
+    new_repository: SyncStream<NewNotesRepository>,
+    params: Form<IngestParams>,
+) -> Result<IngestResponse, Error> {
+    ingest_upload(&conn, &api_key, KIND_UPDATE, &params, new_repository, commit::service::commits_from_notes)
+}
+
+/// Reads the upload and ingests or queues its commits batch by batch. Ingested batches are stored
+/// one transaction each, so a malformed batch leaves the ones before it in place and a resent
+/// upload skips them as unchanged. A queued upload is written in one transaction, its job is
+/// only picked up by a worker once all of its batches are stored.
+fn ingest_upload<T, C, F>(
+    conn: &Conn,
+    api_key: &ApiKey,
+    kind: &str,
+    params: &IngestParams,
+    stream: SyncStream<T>,
+    convert: F,
+) -> Result<IngestResponse, Error>
+    where C: DeserializeOwned,
+          F: Fn(Vec<C>, usize) -> Result<Vec<NewCommitData>, Error>,
+{
+    if params.run_async.unwrap_or(false) {
+        return conn.transaction::<_, Error, _>(|| {
+            let mut job = ingest::service::JobUpload::new(conn, api_key, kind)?;
+            let upload = repository::upload::read(stream, |header, offset, commits| {
+                job.batch(header, offset, convert(commits, offset)?)
+            })?;
+            let job = job.finish(&upload.header, upload.removed_hashes, upload.commits_total)?;
+            Ok(IngestResponse::Queued(Json(job)))
+        });
+    }
+
+    let mut repository = repository::service::RepositoryUpload::new(conn, api_key, kind)?;
+    let upload = repository::upload::read(stream, |header, offset, commits| {
+        repository.batch(header, offset, convert(commits, offset)?)
+    })?;
+    let repository = repository.finish(&upload.header, upload.removed_hashes)?;
+    Ok(IngestResponse::Done(Json(repository)))
+}
+
+#[openapi]
+#[get("/repositories")]
+pub fn get_repositories(
+    auth_user: AuthUser,
+    conn: Conn,
+) -> Result<Json<Vec<RepositoryInfoJson>>, Error> {
+    let repositories = repository::service::get_repositories(&conn, &auth_user)?;
+    Ok(Json(repositories))
+}
+
+#[openapi]
+#[get("/repositories/<repository_id>")]
+pub fn get_repository(
+    auth_user: AuthUser,
     conn: Conn,
-) -> Result<Json<RepositoryJson>, Error> {
-    let new_repository = new_repository.into_inner().repository;
+    repository_id: i32,
+) -> Result<Json<RepositoryInfoJson>, Error> {
+    let (repository, group) = repository::service::find_with_access(&conn, &auth_user, repository_id)?;
+    Ok(Json(repository.attach_info(group)))
+}
 
-    let mut extractor = FieldValidator::validate(&new_repository);
-    let user = extractor.extract("user", new_repository.user);
-    let provider = extractor.extract("provider", new_repository.provider);
-    let repo = extractor.extract("repo", new_repository.repo);
-    extractor.check()?;
+#[openapi]
+#[get("/repositories/<repository_id>/stats?<params..>")]
+pub fn get_repository_stats(
+    auth_user: AuthUser,
+    conn: Conn,
+    repository_id: i32,
+    params: Form<GroupStatsParams>,
+) -> Result<Json<GroupStatsJson>, Error> {
+    repository::service::find_with_access(&conn, &auth_user, repository_id)?;
+    let period = params.into_inner();
+    let start = period.start.unwrap_or(0);
+    let end = period.end.unwrap_or(std::i64::MAX);
+    let depth = period.depth.unwrap_or(1);
+    let stats = repository::service::get_repository_stats(&conn, repository_id, start, end, depth)?;
+    Ok(Json(stats))
+}
 
-    let repository = repository::service::update_repo(
+#[openapi]
+#[get("/repositories/<repository_id>/timeline?<params..>")]
+pub fn get_repository_timeline(
+    auth_user: AuthUser,
+    conn: Conn,
+    repository_id: i32,
+    params: Form<TimelineParams>,
+) -> Result<Json<Vec<IntervalJson>>, Error> {
+    repository::service::find_with_access(&conn, &auth_user, repository_id)?;
+    let params = params.into_inner();
+    let mut validator = FieldValidator::validate(&params);
+    let start = validator.extract("start", params.start);
+    let end = validator.extract("end", params.end);
+    let interval = validator.extract("interval", params.interval);
+    let timezone = validator.extract("timezone", params.timezone);
+    let cumulative = params.cumulative.unwrap_or(false);
+    let branches = split_branches(params.branch);
+    let languages = split_languages(params.language);
+    let interval = validator.validate_timeline_period(start, end, &interval, params.buckets.as_deref());
+    validator.check()?;
+
+    let timeline = timeline::service::get_repository_timeline(
         &conn,
-        &api_key,
-        &user,
-        &provider,
-        &repo,
-        new_repository.commits,
+        repository_id,
+        start,
+        end,
+        &timezone,
+        &interval,
+        cumulative,
+        &branches,
+        &languages,
     )?;
-
-    Ok(Json(repository))
+    Ok(Json(timeline))
 }
 
 #[openapi]
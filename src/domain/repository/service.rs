@@ -1,69 +1,140 @@
+use diesel::{Connection, PgConnection};
+
 use crate::domain::commit::routes::NewCommitData;
 use crate::common::git;
 use crate::domain::db::Conn;
-use crate::domain::{group, repository};
-use crate::domain::repository::resource::RepositoryJson;
+use crate::domain::ingest::{KIND_CREATE, KIND_UPDATE};
+use crate::domain::{commit, group, repository};
+use crate::domain::group::mapper::{map_group_file_stats, map_group_user_stats};
+use crate::domain::group::model::Group;
+use crate::domain::group::resource::GroupStatsJson;
+use crate::domain::repository::model::Repository;
+use crate::domain::repository::resource::{RepositoryInfoJson, RepositoryJson};
+use crate::domain::repository::upload::UploadHeader;
 use crate::domain::role::model::ADMIN;
 use crate::domain::sync;
 use crate::domain::user::model::AuthUser;
 use crate::errors::Error;
 use crate::errors::Error::AuthorizationError;
+use crate::security;
 use crate::security::api_key::ApiKey;
 
-pub fn update_repo(
-    conn: &Conn,
-    api_key: &ApiKey,
-    user: &String,
-    provider: &String,
-    repo: &String,
+/// Stores one batch of a sync upload. The first batch of a create replaces the repository, every
+/// other batch is added to it like an update.
+pub fn ingest_batch(
+    conn: &PgConnection,
+    sync_client: i32,
+    kind: &str,
+    first: bool,
+    header: &UploadHeader,
     commits: Vec<NewCommitData>,
+    removed_hashes: Vec<String>,
 ) -> Result<RepositoryJson, Error> {
-    let client = sync::db::find_by_api_key(conn, &api_key.key)
-        .map_err(|_| Error::AuthorizationError("Unauthorized repository update!"))?;
+    if kind == KIND_CREATE && first {
+        create_repo_for_client(conn, sync_client, &header.user, &header.provider, &header.repo, commits)
+    } else if kind == KIND_CREATE || kind == KIND_UPDATE {
+        update_repo_for_client(conn, sync_client, &header.user, &header.provider, &header.repo, commits, removed_hashes)
+    } else {
+        Err(Error::Custom("Unknown ingest kind!"))
+    }
+}
 
-    repository::db::find(conn, user, provider, repo)
-        .map_err(|_| Error::BadRequest("Repository not found!"))?;
+/// Collects the results of a sync upload that is ingested batch by batch within the request. Each
+/// batch is stored in its own transaction, the response only sums them up.
+pub struct RepositoryUpload<'a> {
+    conn: &'a PgConnection,
+    sync_client: i32,
+    kind: &'a str,
+    repository: Option<RepositoryJson>,
+}
 
-    let repository = repository::db::update(
-        &conn,
-        &user,
-        &provider,
-        &repo,
-        client.id,
-        commits,
-    );
+impl<'a> RepositoryUpload<'a> {
+    pub fn new(conn: &'a PgConnection, api_key: &ApiKey, kind: &'a str) -> Result<RepositoryUpload<'a>, Error> {
+        let client = sync::db::find_by_api_key(conn, &api_key.key)
+            .map_err(|_| Error::AuthorizationError("Unauthorized repository update!"))?;
+        Ok(RepositoryUpload { conn, sync_client: client.id, kind, repository: None })
+    }
+
+    pub fn batch(&mut self, header: &UploadHeader, offset: usize, commits: Vec<NewCommitData>) -> Result<(), Error> {
+        commit::service::validate_commits(&commits, offset)?;
+        let batch = ingest_batch(self.conn, self.sync_client, self.kind, self.repository.is_none(), header, commits, vec![])?;
+        self.add(batch);
+        Ok(())
+    }
 
-    repository
+    /// Applies the removed hashes once all commits are stored, an upload without commits still
+    /// creates its repository.
+    pub fn finish(mut self, header: &UploadHeader, removed_hashes: Vec<String>) -> Result<RepositoryJson, Error> {
+        if self.repository.is_none() || (self.kind == KIND_UPDATE && !removed_hashes.is_empty()) {
+            let removed_hashes = if self.kind == KIND_UPDATE { removed_hashes } else { vec![] };
+            let batch = ingest_batch(self.conn, self.sync_client, self.kind, self.repository.is_none(), header, vec![], removed_hashes)?;
+            self.add(batch);
+        }
+        Ok(self.repository.expect("repository was ingested"))
+    }
+
+    fn add(&mut self, batch: RepositoryJson) {
+        match self.repository.as_mut() {
+            None => self.repository = Some(batch),
+            Some(repository) => {
+                repository.commits_total += batch.commits_total;
+                repository.ingestion.inserted.extend(batch.ingestion.inserted);
+                repository.ingestion.updated.extend(batch.ingestion.updated);
+                repository.ingestion.superseded.extend(batch.ingestion.superseded);
+                repository.ingestion.skipped.extend(batch.ingestion.skipped);
+            }
+        }
+    }
 }
 
-pub fn create_repo(
-    conn: &Conn,
-    api_key: &ApiKey,
-    user: &String,
-    provider: &String,
-    repo: &String,
+pub fn update_repo_for_client(
+    conn: &PgConnection,
+    sync_client: i32,
+    user: &str,
+    provider: &str,
+    repo: &str,
     commits: Vec<NewCommitData>,
+    removed_hashes: Vec<String>,
 ) -> Result<RepositoryJson, Error> {
-    let client = sync::db::find_by_api_key(conn, &api_key.key)
-        .map_err(|_| Error::AuthorizationError("Unauthorized repository update!"))?;
-
-    let group_name = git::generate_group_name(provider, user, repo);
-    if !group::db::exists(&conn, &group_name) {
-        group::db::create(&conn, &group_name)?;
-    }
-    let group = group::db::find(&conn, &group_name).unwrap();
+    repository::db::find(conn, user, provider, repo)
+        .map_err(|_| Error::BadRequest("Repository not found!"))?;
 
-    let repository = repository::db::create(
+    repository::db::update(
         &conn,
-        &group.id,
         &user,
         &provider,
         &repo,
-        client.id,
+        sync_client,
         commits,
-    )?;
+        removed_hashes,
+    )
+}
 
-    Ok(repository)
+pub fn create_repo_for_client(
+    conn: &PgConnection,
+    sync_client: i32,
+    user: &str,
+    provider: &str,
+    repo: &str,
+    commits: Vec<NewCommitData>,
+) -> Result<RepositoryJson, Error> {
+    conn.transaction::<_, Error, _>(|| {
+        let group_name = git::generate_group_name(provider, user, repo);
+        if !group::db::exists(&conn, &group_name) {
+            group::db::create(&conn, &group_name)?;
+        }
+        let group = group::db::find(&conn, &group_name)?;
+
+        repository::db::create(
+            &conn,
+            &group.id,
+            &user,
+            &provider,
+            &repo,
+            sync_client,
+            commits,
+        )
+    })
 }
 
 pub fn delete_repo(conn: &Conn, auth_user: &AuthUser, repository_id: i32) -> Result<(), Error> {
@@ -81,4 +152,48 @@ pub fn delete_repo(conn: &Conn, auth_user: &AuthUser, repository_id: i32) -> Res
 
     repository::db::delete_repo(conn, repository_id)?;
     Ok(())
-}
\ No newline at end of file
+}
+
+pub fn get_repositories(conn: &Conn, auth_user: &AuthUser) -> Result<Vec<RepositoryInfoJson>, Error> {
+    let repositories = if auth_user.roles.contains(&ADMIN) {
+        repository::db::find_all(conn)?
+    } else {
+        let groups: Vec<i32> = group::service::get_groups_with_access(conn, auth_user.user_id)?
+            .iter()
+            .map(|g| g.id)
+            .collect();
+        repository::db::find_all_by_groups(conn, &groups)?
+    };
+    Ok(repositories.into_iter()
+        .map(|(repository, group)| repository.attach_info(group))
+        .collect())
+}
+
+/// Finds the repository if the user has access to the group it belongs to.
+pub fn find_with_access(
+    conn: &PgConnection,
+    auth_user: &AuthUser,
+    repository_id: i32,
+) -> Result<(Repository, Group), Error> {
+    let (repository, group) = repository::db::find_by_id(conn, repository_id)
+        .map_err(|_| Error::BadRequest("Repository not found!"))?;
+    if !auth_user.roles.contains(&ADMIN) {
+        security::service::check_group_access(conn, auth_user.user_id, &group.name)?;
+    }
+    Ok((repository, group))
+}
+
+pub fn get_repository_stats(
+    conn: &PgConnection,
+    repository_id: i32,
+    start: i64,
+    end: i64,
+    depth: i32,
+) -> Result<GroupStatsJson, Error> {
+    let user_stats = repository::db::fetch_repository_user_stats(conn, repository_id, start, end)?;
+    let file_stats = repository::db::fetch_repository_file_stats(conn, repository_id, start, end)?;
+    Ok(GroupStatsJson {
+        users: map_group_user_stats(&user_stats),
+        files: map_group_file_stats(&file_stats, depth),
+    })
+}
@@ -0,0 +1,286 @@
+use std::fmt;
+
+use serde::de::{self, DeserializeOwned, DeserializeSeed, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde::Deserialize;
+use validator::Validate;
+
+use crate::common::sync_json::SyncStream;
+use crate::errors::{Error, FieldValidator};
+
+/// Commits handed to the sink at once, the parsed commits of one batch are all that is held.
+pub const BATCH_SIZE: usize = 500;
+
+/// Repository a sync upload belongs to.
+pub struct UploadHeader {
+    pub user: String,
+    pub provider: String,
+    pub repo: String,
+}
+
+/// A parsed sync upload, its commits have already been passed to the sink.
+pub struct Upload {
+    pub header: UploadHeader,
+    pub removed_hashes: Vec<String>,
+    pub commits_total: usize,
+}
+
+#[derive(Default, Validate)]
+struct HeaderData {
+    #[validate(length(min = 1))]
+    user: Option<String>,
+    #[validate(length(min = 1))]
+    provider: Option<String>,
+    #[validate(length(min = 1))]
+    repo: Option<String>,
+}
+
+impl HeaderData {
+    fn complete(&self) -> Option<UploadHeader> {
+        match (&self.user, &self.provider, &self.repo) {
+            (Some(user), Some(provider), Some(repo))
+            if !user.is_empty() && !provider.is_empty() && !repo.is_empty() => Some(UploadHeader {
+                user: user.clone(),
+                provider: provider.clone(),
+                repo: repo.clone(),
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Reads a `{"repository": {...}}` sync upload and passes its `commits` to `sink` in batches of
+/// `BATCH_SIZE` while they are parsed, together with the index of the first commit of the batch.
+///
+/// Batches can only be passed on once `user`, `provider` and `repo` are known. The sync client
+/// sends them before `commits`, if a payload puts them after, its commits are buffered until the
+/// end of the repository object.
+pub fn read<T, C, F>(stream: SyncStream<T>, sink: F) -> Result<Upload, Error>
+    where C: DeserializeOwned,
+          F: FnMut(&UploadHeader, usize, Vec<C>) -> Result<(), Error>,
+{
+    let mut state = State {
+        header: HeaderData::default(),
+        pending: Vec::new(),
+        offset: 0,
+        removed_hashes: Vec::new(),
+        sink,
+        error: None,
+    };
+
+    let parsed = stream.deserialize(PayloadSeed { state: &mut state });
+    if let Some(err) = state.error {
+        return Err(err);
+    }
+    parsed?;
+
+    let mut extractor = FieldValidator::validate(&state.header);
+    let user = extractor.extract("user", state.header.user);
+    let provider = extractor.extract("provider", state.header.provider);
+    let repo = extractor.extract("repo", state.header.repo);
+    extractor.check()?;
+
+    let header = UploadHeader { user, provider, repo };
+    let mut sink = state.sink;
+    let mut offset = state.offset;
+    let mut pending = state.pending.into_iter().peekable();
+    while pending.peek().is_some() {
+        let batch: Vec<C> = pending.by_ref().take(BATCH_SIZE).collect();
+        let size = batch.len();
+        sink(&header, offset, batch)?;
+        offset += size;
+    }
+
+    Ok(Upload {
+        header,
+        removed_hashes: state.removed_hashes,
+        commits_total: offset,
+    })
+}
+
+struct State<C, F> {
+    header: HeaderData,
+    pending: Vec<C>,
+    /// Index of the first pending commit.
+    offset: usize,
+    removed_hashes: Vec<String>,
+    sink: F,
+    error: Option<Error>,
+}
+
+impl<C, F> State<C, F>
+    where F: FnMut(&UploadHeader, usize, Vec<C>) -> Result<(), Error>,
+{
+    /// Passes the pending commits on once the header is known, keeps the sink's error so it is
+    /// not lost in the deserializer's.
+    fn flush<E: de::Error>(&mut self) -> Result<(), E> {
+        let header = match self.header.complete() {
+            Some(header) => header,
+            None => return Ok(()),
+        };
+        let batch = std::mem::replace(&mut self.pending, Vec::new());
+        let size = batch.len();
+        if let Err(err) = (self.sink)(&header, self.offset, batch) {
+            self.error = Some(err);
+            return Err(E::custom("commits could not be ingested"));
+        }
+        self.offset += size;
+        Ok(())
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(field_identifier, rename_all = "snake_case")]
+enum PayloadField {
+    Repository,
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize)]
+#[serde(field_identifier, rename_all = "snake_case")]
+enum RepositoryField {
+    User,
+    Provider,
+    Repo,
+    Commits,
+    RemovedHashes,
+    #[serde(other)]
+    Other,
+}
+
+struct PayloadSeed<'a, C, F> {
+    state: &'a mut State<C, F>,
+}
+
+impl<'de, 'a, C, F> DeserializeSeed<'de> for PayloadSeed<'a, C, F>
+    where C: DeserializeOwned,
+          F: FnMut(&UploadHeader, usize, Vec<C>) -> Result<(), Error>,
+{
+    type Value = ();
+
+    fn deserialize<D: de::Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de, 'a, C, F> Visitor<'de> for PayloadSeed<'a, C, F>
+    where C: DeserializeOwned,
+          F: FnMut(&UploadHeader, usize, Vec<C>) -> Result<(), Error>,
+{
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an object with a repository")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        let mut found = false;
+        while let Some(field) = map.next_key::<PayloadField>()? {
+            match field {
+                PayloadField::Repository => {
+                    if found {
+                        return Err(de::Error::duplicate_field("repository"));
+                    }
+                    map.next_value_seed(RepositorySeed { state: &mut *self.state })?;
+                    found = true;
+                }
+                PayloadField::Other => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        if !found {
+            return Err(de::Error::missing_field("repository"));
+        }
+        Ok(())
+    }
+}
+
+struct RepositorySeed<'a, C, F> {
+    state: &'a mut State<C, F>,
+}
+
+impl<'de, 'a, C, F> DeserializeSeed<'de> for RepositorySeed<'a, C, F>
+    where C: DeserializeOwned,
+          F: FnMut(&UploadHeader, usize, Vec<C>) -> Result<(), Error>,
+{
+    type Value = ();
+
+    fn deserialize<D: de::Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de, 'a, C, F> Visitor<'de> for RepositorySeed<'a, C, F>
+    where C: DeserializeOwned,
+          F: FnMut(&UploadHeader, usize, Vec<C>) -> Result<(), Error>,
+{
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a repository object")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        let mut commits = false;
+        while let Some(field) = map.next_key::<RepositoryField>()? {
+            match field {
+                RepositoryField::User => self.state.header.user = map.next_value()?,
+                RepositoryField::Provider => self.state.header.provider = map.next_value()?,
+                RepositoryField::Repo => self.state.header.repo = map.next_value()?,
+                RepositoryField::Commits => {
+                    map.next_value_seed(CommitsSeed { state: &mut *self.state })?;
+                    commits = true;
+                }
+                RepositoryField::RemovedHashes => self.state.removed_hashes = map.next_value()?,
+                RepositoryField::Other => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        if !commits {
+            return Err(de::Error::missing_field("commits"));
+        }
+        Ok(())
+    }
+}
+
+struct CommitsSeed<'a, C, F> {
+    state: &'a mut State<C, F>,
+}
+
+impl<'de, 'a, C, F> DeserializeSeed<'de> for CommitsSeed<'a, C, F>
+    where C: DeserializeOwned,
+          F: FnMut(&UploadHeader, usize, Vec<C>) -> Result<(), Error>,
+{
+    type Value = ();
+
+    fn deserialize<D: de::Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de, 'a, C, F> Visitor<'de> for CommitsSeed<'a, C, F>
+    where C: DeserializeOwned,
+          F: FnMut(&UploadHeader, usize, Vec<C>) -> Result<(), Error>,
+{
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a list of commits")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        while let Some(commit) = seq.next_element::<C>()? {
+            self.state.pending.push(commit);
+            if self.state.pending.len() >= BATCH_SIZE {
+                self.state.flush::<A::Error>()?;
+            }
+        }
+        if !self.state.pending.is_empty() {
+            self.state.flush::<A::Error>()?;
+        }
+        Ok(())
+    }
+}
+
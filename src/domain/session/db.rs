@@ -0,0 +1,52 @@
+use diesel::{sql_query, sql_types};
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+
+use crate::common::sql::{BRANCH_FILTER, GROUP_CHILDREN_QUERY, QUERIED_GROUP, language_filter, path_rules_filter};
+use crate::domain::session::dwh::SessionEntryDWH;
+use crate::errors::Error;
+
+/// Timeline slots of the group, time of all files edited in the same slot summed up. Ordered by
+/// user and timestamp, as sessions are merged from them.
+pub fn fetch_session_entries(
+    conn: &PgConnection,
+    group_name: &str,
+    start: i64,
+    end: i64,
+    branches: &Option<Vec<String>>,
+    languages: &Option<Vec<String>>,
+) -> Result<Vec<SessionEntryDWH>, Error> {
+    let entries: Vec<SessionEntryDWH> = sql_query(format!("
+    {}
+    SELECT coalesce(users.username, commits.email) AS user,
+           timeline.timestamp                      AS timestamp,
+           sum(timeline.time)::bigint              AS time
+    FROM timeline
+        INNER JOIN files ON timeline.file = files.id
+        INNER JOIN commits ON files.commit = commits.id
+        INNER JOIN repositories ON commits.repository_id = repositories.id
+        LEFT JOIN emails ON commits.email = emails.email
+        LEFT JOIN users ON emails.user = users.id
+    WHERE repositories.group IN (
+        SELECT DISTINCT group_repos_query.child
+        FROM    group_repos_query
+        UNION (
+            SELECT g.id
+            FROM groups g
+            WHERE g.name = $1))
+        AND timeline.timestamp >= $2
+        AND timeline.timestamp < $3
+        AND NOT commits.is_superseded
+        {}
+        {}
+        {}
+    GROUP BY coalesce(users.username, commits.email), timeline.timestamp
+    ORDER BY 1, 2", GROUP_CHILDREN_QUERY, BRANCH_FILTER, language_filter(), path_rules_filter(QUERIED_GROUP)))
+        .bind::<sql_types::Text, _>(group_name)
+        .bind::<sql_types::BigInt, _>(start)
+        .bind::<sql_types::BigInt, _>(end)
+        .bind::<sql_types::Nullable<sql_types::Array<sql_types::Text>>, _>(branches)
+        .bind::<sql_types::Nullable<sql_types::Array<sql_types::Text>>, _>(languages)
+        .load(conn)?;
+    Ok(entries)
+}
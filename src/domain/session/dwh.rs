@@ -0,0 +1,11 @@
+use diesel::sql_types::{BigInt, Text};
+
+#[derive(QueryableByName, Debug)]
+pub struct SessionEntryDWH {
+    #[sql_type = "Text"]
+    pub user: String,
+    #[sql_type = "BigInt"]
+    pub timestamp: i64,
+    #[sql_type = "BigInt"]
+    pub time: i64,
+}
@@ -0,0 +1,93 @@
+use chrono_tz::Tz;
+use itertools::Itertools;
+
+use crate::domain::session::dwh::SessionEntryDWH;
+use crate::domain::session::resource::{
+    Session, SessionStartJson, SessionStatsJson, SessionsJson, UserSessionStatsJson,
+};
+use crate::domain::timeline::granularity::Granularity;
+use crate::domain::timeline::helper::{generate_activity_interval, get_activity_id};
+use crate::domain::timeline::mapper::get_datetime_tz_from_seconds;
+
+/// Length of a timeline slot, gtm records time per hour.
+const SLOT_SECONDS: i64 = 60 * 60;
+
+/// Merges timeline slots into sessions. The time of a slot may have been spent anywhere within its
+/// hour, so the gap to the next slot is measured from the end of that hour and a slot starting more
+/// than `idle_gap` seconds later starts a new session. `entries` must be ordered by user and
+/// timestamp.
+pub fn merge_sessions(entries: Vec<SessionEntryDWH>, idle_gap: i64) -> Vec<Session> {
+    let mut sessions: Vec<Session> = vec![];
+    let mut slot_end = 0;
+    for entry in entries {
+        let end = entry.timestamp + entry.time;
+        match sessions.last_mut() {
+            Some(session) if session.user == entry.user && entry.timestamp - slot_end <= idle_gap => {
+                session.end = session.end.max(end);
+                session.time += entry.time;
+            }
+            _ => sessions.push(Session {
+                user: entry.user,
+                start: entry.timestamp,
+                end,
+                time: entry.time,
+            }),
+        }
+        slot_end = entry.timestamp + SLOT_SECONDS;
+    }
+    sessions
+}
+
+/// Session stats of the whole group and of every user, `sessions` ordered by user. Session
+/// starts are distributed over the same buckets as the activity timeline of `interval`.
+pub fn map_sessions(sessions: Vec<Session>, timezone: &str, interval: &Granularity, idle_gap: i64) -> SessionsJson {
+    let tz: Tz = timezone.parse().unwrap();
+    let starts: Vec<i32> = sessions.iter()
+        .map(|s| get_activity_id(&get_datetime_tz_from_seconds(s.start, &tz), interval))
+        .collect();
+    let all: Vec<(&Session, i32)> = sessions.iter().zip(starts).collect();
+
+    let users = all.iter()
+        .group_by(|(s, _)| s.user.clone())
+        .into_iter()
+        .map(|(user, group)| UserSessionStatsJson {
+            user,
+            stats: map_session_stats(group.cloned().collect(), interval),
+        })
+        .collect();
+
+    SessionsJson {
+        idle_gap,
+        group: map_session_stats(all, interval),
+        users,
+    }
+}
+
+fn map_session_stats(sessions: Vec<(&Session, i32)>, interval: &Granularity) -> SessionStatsJson {
+    let mut start_distribution: Vec<SessionStartJson> = generate_activity_interval(interval)
+        .into_iter()
+        .map(|a| SessionStartJson {
+            label: a.label,
+            label_key: a.id,
+            sessions: 0,
+        })
+        .collect();
+    for (_, id) in &sessions {
+        if let Some(bucket) = start_distribution.iter_mut().find(|b| b.label_key == *id) {
+            bucket.sessions += 1;
+        }
+    }
+
+    let lengths: Vec<i64> = sessions.iter().map(|(s, _)| s.end - s.start).collect();
+    SessionStatsJson {
+        sessions: sessions.len() as i64,
+        average_length: if lengths.is_empty() {
+            0.0
+        } else {
+            lengths.iter().sum::<i64>() as f64 / lengths.len() as f64
+        },
+        longest_session: lengths.iter().cloned().max().unwrap_or(0),
+        time: sessions.iter().map(|(s, _)| s.time).sum(),
+        start_distribution,
+    }
+}
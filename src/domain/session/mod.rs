@@ -0,0 +1,6 @@
+pub mod db;
+pub mod dwh;
+pub mod mapper;
+pub mod resource;
+pub mod routes;
+pub mod service;
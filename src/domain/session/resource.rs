@@ -0,0 +1,45 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+
+#[derive(Debug)]
+pub struct Session {
+    pub user: String,
+    pub start: i64,
+    pub end: i64,
+    pub time: i64,
+}
+
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionStartJson {
+    pub label: String,
+    pub label_key: i32,
+    pub sessions: i64,
+}
+
+/// Session lengths are wall-clock seconds from the first to the end of the last slot, `time`
+/// is the coding time recorded within the sessions.
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionStatsJson {
+    pub sessions: i64,
+    pub average_length: f64,
+    pub longest_session: i64,
+    pub time: i64,
+    pub start_distribution: Vec<SessionStartJson>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct UserSessionStatsJson {
+    pub user: String,
+    #[serde(flatten)]
+    pub stats: SessionStatsJson,
+}
+
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionsJson {
+    pub idle_gap: i64,
+    pub group: SessionStatsJson,
+    pub users: Vec<UserSessionStatsJson>,
+}
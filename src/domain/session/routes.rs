@@ -0,0 +1,56 @@
+use rocket::request::Form;
+use rocket_contrib::json::Json;
+use rocket_okapi::openapi;
+
+use crate::domain::db::Conn;
+use crate::domain::group;
+use crate::domain::role::model::ADMIN;
+use crate::domain::session;
+use crate::domain::session::resource::SessionsJson;
+use crate::domain::timeline::routes::{split_branches, split_languages, TimelineParams};
+use crate::domain::user::model::AuthUser;
+use crate::errors::{Error, FieldValidator};
+use crate::security;
+
+/// Coding sessions of the group, hourly timeline slots less than `idle_gap` seconds apart
+/// (15 minutes by default) belong to the same session. `interval` picks the buckets
+/// of the session start distribution, as for the activity timeline.
+#[openapi]
+#[get("/<group_name>/sessions?<idle_gap>&<params..>")]
+pub fn get_sessions(
+    auth_user: AuthUser,
+    group_name: String,
+    idle_gap: Option<i64>,
+    params: Form<TimelineParams>,
+    conn: Conn,
+) -> Result<Json<SessionsJson>, Error> {
+    if auth_user.require_role(&ADMIN).is_err() {
+        security::service::check_group_access(&conn, auth_user.user_id, &group_name)?;
+    }
+    let params = params.into_inner();
+    let mut validator = FieldValidator::validate(&params);
+    let (start, end) = group::service::default_period(&conn, &group_name, params.start, params.end);
+    let start = validator.extract("start", start);
+    let end = validator.extract("end", end);
+    let interval = validator.extract("interval", params.interval);
+    let timezone = validator.extract("timezone", params.timezone);
+    let idle_gap = idle_gap.unwrap_or(session::service::DEFAULT_IDLE_GAP);
+    let branches = split_branches(params.branch);
+    let languages = split_languages(params.language);
+    let interval = validator.validate_timeline_period(start, end, &interval, params.buckets.as_deref());
+    validator.validate_idle_gap(idle_gap);
+    validator.check()?;
+
+    let sessions = session::service::get_sessions(
+        &conn,
+        &group_name,
+        start,
+        end,
+        &timezone,
+        &interval,
+        idle_gap,
+        &branches,
+        &languages,
+    )?;
+    Ok(Json(sessions))
+}
@@ -0,0 +1,25 @@
+use diesel::PgConnection;
+
+use crate::domain::session::db::fetch_session_entries;
+use crate::domain::session::mapper::{map_sessions, merge_sessions};
+use crate::domain::session::resource::SessionsJson;
+use crate::domain::timeline::granularity::Granularity;
+use crate::errors::Error;
+
+pub const DEFAULT_IDLE_GAP: i64 = 15 * 60;
+
+pub fn get_sessions(
+    conn: &PgConnection,
+    group_name: &str,
+    start: i64,
+    end: i64,
+    timezone: &str,
+    interval: &Granularity,
+    idle_gap: i64,
+    branches: &Option<Vec<String>>,
+    languages: &Option<Vec<String>>,
+) -> Result<SessionsJson, Error> {
+    let entries = fetch_session_entries(conn, group_name, start, end, branches, languages)?;
+    let sessions = merge_sessions(entries, idle_gap);
+    Ok(map_sessions(sessions, timezone, interval, idle_gap))
+}
@@ -1,14 +1,22 @@
+use std::collections::HashMap;
+
 use diesel;
 use diesel::{Insertable, sql_query, sql_types};
 use diesel::prelude::*;
 
-use crate::common::sql::GROUP_CHILDREN_QUERY;
+use crate::common::sql::{
+    BRANCH_FILTER, GROUP_CHILDREN_QUERY, QUERIED_GROUP, REPOSITORY_GROUP, code_path_rules, language_filter,
+    path_rules_filter,
+};
 use crate::errors::{Error, FieldValidator};
-use crate::schema::timeline;
-use crate::domain::timeline::dwh::{TimelineDWH, ComparisonDWH};
+use crate::schema::{timeline, timeline_rollups};
+use crate::domain::timeline::dwh::{AppTimeDWH, TimelineDWH, ComparisonDWH, FileEditDWH, PathlessFileEditDWH};
 use crate::domain::timeline::model::{Timeline};
 use crate::domain::timeline::routes::NewTimelineData;
 
+// Postgres accepts at most 65535 bind parameters per statement
+const INSERT_BATCH_SIZE: usize = 65535 / 3;
+
 #[derive(Insertable)]
 #[table_name = "timeline"]
 struct NewTimeline<> {
@@ -17,33 +25,215 @@ struct NewTimeline<> {
     time: i64,
 }
 
+/// Inserts the timeline entries of the given files, returned grouped by the file of each inserted
+/// row and ordered by timestamp.
 pub fn create_all(
     conn: &PgConnection,
-    files: Vec<NewTimelineData>,
-    file: i32
-) -> Result<Vec<Timeline>, Error> {
-    let mut vec = Vec::new();
-    for var in files {
-        let mut extractor = FieldValidator::validate(&var);
-        let timestamp = extractor.extract("timestamp", var.timestamp);
-        let time = extractor.extract("time", var.time);
-        extractor.check()?;
-
-        let new_timeline = &NewTimeline {
-            file,
-            timestamp,
-            time,
-        };
-
-        let timeline_json = diesel::insert_into(timeline::table)
-            .values(new_timeline)
-            .get_result::<Timeline>(conn)?;
-        vec.push(timeline_json)
+    timelines: Vec<(i32, Vec<NewTimelineData>)>,
+) -> Result<HashMap<i32, Vec<Timeline>>, Error> {
+    let mut new_timelines = Vec::new();
+    for (file, entries) in timelines {
+        for var in entries {
+            let mut extractor = FieldValidator::validate(&var);
+            let timestamp = extractor.extract("timestamp", var.timestamp);
+            let time = extractor.extract("time", var.time);
+            extractor.check()?;
+
+            new_timelines.push(NewTimeline {
+                file,
+                timestamp,
+                time,
+            });
+        }
+    }
+
+    let mut res: HashMap<i32, Vec<Timeline>> = HashMap::new();
+    for chunk in new_timelines.chunks(INSERT_BATCH_SIZE) {
+        for entry in diesel::insert_into(timeline::table)
+            .values(chunk)
+            .get_results::<Timeline>(conn)?
+        {
+            res.entry(entry.file).or_insert_with(Vec::new).push(entry);
+        }
+    }
+    for entries in res.values_mut() {
+        entries.sort_by_key(|t| (t.timestamp, t.id));
     }
-    Ok(vec)
+    Ok(res)
+}
+
+// Rolls timeline entries up per commit, top-level directory and UTC hour. The directory is the
+// first path segment, so `cut_path` of `directory/` at depth 1 equals `cut_path` of the file path.
+const ROLLUP_INSERT_QUERY: &str = "
+    INSERT INTO timeline_rollups (repository_id, commit, directory, is_app, hour, time, lines_added, lines_deleted)
+    SELECT commits.repository_id,
+           commits.id,
+           split_part(regexp_replace(files.path, '^(\\./)+', ''), '/', 1),
+           files.path LIKE '%.app',
+           timeline.timestamp - timeline.timestamp % 3600,
+           sum(timeline.time),
+           sum(files.lines_added),
+           sum(files.lines_deleted)
+    FROM timeline
+        INNER JOIN files ON timeline.file = files.id
+        INNER JOIN commits ON files.commit = commits.id";
+
+/// Recomputes the hourly rollups of the given commits from their timeline entries.
+pub fn refresh_rollups(conn: &PgConnection, commits: &Vec<i32>) -> Result<usize, Error> {
+    diesel::delete(timeline_rollups::table.filter(timeline_rollups::commit.eq_any(commits)))
+        .execute(conn)?;
+    Ok(sql_query(format!("{} WHERE commits.id = ANY($1) GROUP BY 1, 2, 3, 4, 5", ROLLUP_INSERT_QUERY))
+        .bind::<sql_types::Array<sql_types::Integer>, _>(commits)
+        .execute(conn)?)
+}
+
+/// Drops and recomputes all hourly rollups.
+pub fn rebuild_rollups(conn: &PgConnection) -> Result<usize, Error> {
+    conn.transaction::<_, Error, _>(|| {
+        diesel::delete(timeline_rollups::table).execute(conn)?;
+        Ok(sql_query(format!("{} GROUP BY 1, 2, 3, 4, 5", ROLLUP_INSERT_QUERY))
+            .execute(conn)?)
+    })
+}
+
+pub fn fetch_timeline_rollups(
+    conn: &PgConnection,
+    group_name: &str,
+    start: i64,
+    end: i64,
+    branches: &Option<Vec<String>>,
+) -> Result<Vec<TimelineDWH>, Error> {
+    Ok(sql_query(format!("
+    {}
+    SELECT coalesce(users.username, commits.email)          AS user,
+           sum(timeline_rollups.time)::bigint               AS time,
+           timeline_rollups.hour                            AS timestamp,
+           sum(timeline_rollups.lines_added)::bigint        AS lines_added,
+           sum(timeline_rollups.lines_deleted)::bigint      AS lines_removed
+    FROM timeline_rollups
+        INNER JOIN commits ON timeline_rollups.commit = commits.id
+        INNER JOIN repositories ON timeline_rollups.repository_id = repositories.id
+        LEFT JOIN emails ON commits.email = emails.email
+        LEFT JOIN users ON emails.user = users.id
+    WHERE repositories.group IN (
+        SELECT DISTINCT group_repos_query.child
+        FROM    group_repos_query
+        UNION (
+            SELECT g.id
+            FROM groups g
+            WHERE g.name = $1))
+        AND timeline_rollups.hour >= $2
+        AND timeline_rollups.hour < $3
+        AND NOT commits.is_superseded
+        AND (NOT timeline_rollups.is_app OR (SELECT groups.include_apps FROM groups WHERE groups.name = $1))
+        {}
+    GROUP BY coalesce(users.username, commits.email), timeline_rollups.hour", GROUP_CHILDREN_QUERY, BRANCH_FILTER))
+        .bind::<sql_types::Text, _>(group_name)
+        .bind::<sql_types::BigInt, _>(start)
+        .bind::<sql_types::BigInt, _>(end)
+        .bind::<sql_types::Nullable<sql_types::Array<sql_types::Text>>, _>(branches)
+        .load(conn)?)
+}
+
+pub fn fetch_pathless_file_edit_rollups(
+    conn: &PgConnection,
+    group_name: &str,
+    start: i64,
+    end: i64,
+    branches: &Option<Vec<String>>,
+) -> Result<Vec<PathlessFileEditDWH>, Error> {
+    Ok(sql_query(format!("
+    {}
+    SELECT coalesce(users.username, commits.email)          AS user,
+           sum(timeline_rollups.time)::bigint               AS time,
+           sum(timeline_rollups.lines_added)::bigint        AS lines_added,
+           sum(timeline_rollups.lines_deleted)::bigint      AS lines_deleted,
+           timeline_rollups.hour                            AS timestamp
+    FROM timeline_rollups
+        INNER JOIN commits ON timeline_rollups.commit = commits.id
+        INNER JOIN repositories ON timeline_rollups.repository_id = repositories.id
+        LEFT JOIN emails ON commits.email = emails.email
+        LEFT JOIN users ON emails.user = users.id
+    WHERE repositories.group IN (
+        SELECT  group_repos_query.child
+        FROM    group_repos_query
+        UNION (
+            SELECT g.id
+            FROM groups g
+            WHERE g.name = $1))
+        AND commits.timestamp >= $2
+        AND commits.timestamp < $3
+        AND NOT commits.is_superseded
+        AND (NOT timeline_rollups.is_app OR (SELECT groups.include_apps FROM groups WHERE groups.name = $1))
+        {}
+    GROUP BY coalesce(users.username, commits.email), timeline_rollups.hour", GROUP_CHILDREN_QUERY, BRANCH_FILTER))
+        .bind::<sql_types::Text, _>(group_name)
+        .bind::<sql_types::BigInt, _>(start)
+        .bind::<sql_types::BigInt, _>(end)
+        .bind::<sql_types::Nullable<sql_types::Array<sql_types::Text>>, _>(branches)
+        .load(conn)?)
+}
+
+/// File edits rolled up to top-level directories, usable for a subdirectory timeline of depth 1.
+pub fn fetch_file_edit_rollups(
+    conn: &PgConnection,
+    group_name: &str,
+    start: i64,
+    end: i64,
+    branches: &Option<Vec<String>>,
+) -> Result<Vec<FileEditDWH>, Error> {
+    Ok(sql_query(format!("
+    {}
+    SELECT coalesce(users.username, commits.email)          AS user,
+           timeline_rollups.directory || '/'                AS path,
+           sum(timeline_rollups.time)::bigint               AS time,
+           sum(timeline_rollups.lines_added)::bigint        AS lines_added,
+           sum(timeline_rollups.lines_deleted)::bigint      AS lines_deleted,
+           timeline_rollups.hour                            AS timestamp,
+           commits.hash                                     AS commit_hash
+    FROM timeline_rollups
+        INNER JOIN commits ON timeline_rollups.commit = commits.id
+        INNER JOIN repositories ON timeline_rollups.repository_id = repositories.id
+        LEFT JOIN emails ON commits.email = emails.email
+        LEFT JOIN users ON emails.user = users.id
+    WHERE repositories.group IN (
+        SELECT  group_repos_query.child
+        FROM    group_repos_query
+        UNION (
+            SELECT g.id
+            FROM groups g
+            WHERE g.name = $1))
+        AND commits.timestamp >= $2
+        AND commits.timestamp < $3
+        AND NOT commits.is_superseded
+        AND (NOT timeline_rollups.is_app OR (SELECT groups.include_apps FROM groups WHERE groups.name = $1))
+        {}
+    GROUP BY coalesce(users.username, commits.email),
+             timeline_rollups.directory,
+             timeline_rollups.hour,
+             commits.hash", GROUP_CHILDREN_QUERY, BRANCH_FILTER))
+        .bind::<sql_types::Text, _>(group_name)
+        .bind::<sql_types::BigInt, _>(start)
+        .bind::<sql_types::BigInt, _>(end)
+        .bind::<sql_types::Nullable<sql_types::Array<sql_types::Text>>, _>(branches)
+        .load(conn)?)
+}
+
+pub fn find_all_by_files(conn: &PgConnection, files: &Vec<i32>) -> Result<Vec<Timeline>, Error> {
+    Ok(timeline::table
+        .filter(timeline::file.eq_any(files))
+        .order(timeline::timestamp)
+        .load::<Timeline>(conn)?)
 }
 
-pub fn fetch_timeline(conn: &PgConnection, group_name: &str, start: i64, end: i64) -> Vec<TimelineDWH> {
+pub fn fetch_timeline(
+    conn: &PgConnection,
+    group_name: &str,
+    start: i64,
+    end: i64,
+    branches: &Option<Vec<String>>,
+    languages: &Option<Vec<String>>,
+) -> Vec<TimelineDWH> {
     let day_timeline: Vec<TimelineDWH> = sql_query(format!("
     {}
     SELECT coalesce(users.username, commits.email) AS user,
@@ -65,22 +255,144 @@ pub fn fetch_timeline(conn: &PgConnection, group_name: &str, start: i64, end: i6
             FROM groups g
             WHERE g.name = $1))
         AND timeline.timestamp >= $2
-        AND timeline.timestamp < $3", GROUP_CHILDREN_QUERY))
+        AND timeline.timestamp < $3
+        AND NOT commits.is_superseded
+        {}
+        {}
+        {}", GROUP_CHILDREN_QUERY, BRANCH_FILTER, language_filter(), path_rules_filter(QUERIED_GROUP)))
         .bind::<sql_types::Text, _>(group_name)
         .bind::<sql_types::BigInt, _>(start)
         .bind::<sql_types::BigInt, _>(end)
+        .bind::<sql_types::Nullable<sql_types::Array<sql_types::Text>>, _>(branches)
+        .bind::<sql_types::Nullable<sql_types::Array<sql_types::Text>>, _>(languages)
         .load(conn)
         .expect("Error loading timeline for group");
     day_timeline
 }
 
+pub fn fetch_repository_timeline(
+    conn: &PgConnection,
+    repository_id: i32,
+    start: i64,
+    end: i64,
+    branches: &Option<Vec<String>>,
+    languages: &Option<Vec<String>>,
+) -> Result<Vec<TimelineDWH>, Error> {
+    let timeline: Vec<TimelineDWH> = sql_query(format!("
+    SELECT coalesce(users.username, commits.email) AS user,
+           timeline.time                           AS time,
+           timeline.timestamp                      AS timestamp,
+           files.lines_added                       AS lines_added,
+           files.lines_deleted                     AS lines_removed
+    FROM timeline
+        INNER JOIN files ON timeline.file = files.id
+        INNER JOIN commits ON files.commit = commits.id
+        LEFT JOIN emails ON commits.email = emails.email
+        LEFT JOIN users ON emails.user = users.id
+    WHERE commits.repository_id = $1
+        AND timeline.timestamp >= $2
+        AND timeline.timestamp < $3
+        AND NOT commits.is_superseded
+        {}
+        {}
+        {}", BRANCH_FILTER, language_filter(), path_rules_filter(REPOSITORY_GROUP)))
+        .bind::<sql_types::Integer, _>(repository_id)
+        .bind::<sql_types::BigInt, _>(start)
+        .bind::<sql_types::BigInt, _>(end)
+        .bind::<sql_types::Nullable<sql_types::Array<sql_types::Text>>, _>(branches)
+        .bind::<sql_types::Nullable<sql_types::Array<sql_types::Text>>, _>(languages)
+        .load(conn)?;
+    Ok(timeline)
+}
+
+/// Timeline of all commits whose email belongs to the user, in any repository.
+pub fn fetch_user_timeline(
+    conn: &PgConnection,
+    user_id: i32,
+    start: i64,
+    end: i64,
+    branches: &Option<Vec<String>>,
+    languages: &Option<Vec<String>>,
+) -> Result<Vec<TimelineDWH>, Error> {
+    let timeline: Vec<TimelineDWH> = sql_query(format!("
+    SELECT users.username                          AS user,
+           timeline.time                           AS time,
+           timeline.timestamp                      AS timestamp,
+           files.lines_added                       AS lines_added,
+           files.lines_deleted                     AS lines_removed
+    FROM timeline
+        INNER JOIN files ON timeline.file = files.id
+        INNER JOIN commits ON files.commit = commits.id
+        INNER JOIN emails ON commits.email = emails.email
+        INNER JOIN users ON emails.user = users.id
+    WHERE emails.user = $1
+        AND timeline.timestamp >= $2
+        AND timeline.timestamp < $3
+        AND NOT commits.is_superseded
+        {}
+        {}", BRANCH_FILTER, language_filter()))
+        .bind::<sql_types::Integer, _>(user_id)
+        .bind::<sql_types::BigInt, _>(start)
+        .bind::<sql_types::BigInt, _>(end)
+        .bind::<sql_types::Nullable<sql_types::Array<sql_types::Text>>, _>(branches)
+        .bind::<sql_types::Nullable<sql_types::Array<sql_types::Text>>, _>(languages)
+        .load(conn)?;
+    Ok(timeline)
+}
+
+/// App and code time of the group per user and timeline slot. App time is reported even if the
+/// group leaves it out of its stats, code time follows the path rules of the group.
+pub fn fetch_app_timeline(
+    conn: &PgConnection,
+    group_name: &str,
+    start: i64,
+    end: i64,
+    branches: &Option<Vec<String>>,
+) -> Result<Vec<AppTimeDWH>, Error> {
+    let timeline: Vec<AppTimeDWH> = sql_query(format!("
+    {}
+    SELECT coalesce(users.username, commits.email)        AS user,
+           substring(files.path FROM '([^/]+)\\.app$')    AS app,
+           timeline.timestamp                             AS timestamp,
+           sum(timeline.time)::bigint                     AS time
+    FROM timeline
+        INNER JOIN files ON timeline.file = files.id
+        INNER JOIN commits ON files.commit = commits.id
+        INNER JOIN repositories ON commits.repository_id = repositories.id
+        LEFT JOIN emails ON commits.email = emails.email
+        LEFT JOIN users ON emails.user = users.id
+    WHERE repositories.group IN (
+        SELECT DISTINCT group_repos_query.child
+        FROM    group_repos_query
+        UNION (
+            SELECT g.id
+            FROM groups g
+            WHERE g.name = $1))
+        AND timeline.timestamp >= $2
+        AND timeline.timestamp < $3
+        AND NOT commits.is_superseded
+        AND (files.path LIKE '%.app' OR ({}))
+        {}
+    GROUP BY 1, 2, 3", GROUP_CHILDREN_QUERY, code_path_rules(QUERIED_GROUP), BRANCH_FILTER))
+        .bind::<sql_types::Text, _>(group_name)
+        .bind::<sql_types::BigInt, _>(start)
+        .bind::<sql_types::BigInt, _>(end)
+        .bind::<sql_types::Nullable<sql_types::Array<sql_types::Text>>, _>(branches)
+        .load(conn)?;
+    Ok(timeline)
+}
+
 pub fn fetch_timeline_comparison(conn: &PgConnection, repos: &Vec<i32>, start: i64, end: i64) -> Vec<ComparisonDWH> {
-    let data: Vec<ComparisonDWH> = sql_query("
+    let data: Vec<ComparisonDWH> = sql_query(format!("
         SELECT coalesce(users.username, commits.email)        AS user,
                 repositories.id                               AS repo,
                 repositories.repo                             AS repo_name,
                 commits.hash                                  AS commit_hash,
-                commits.branch                                AS branch,
+                ARRAY(
+                    SELECT commit_branches.branch
+                    FROM commit_branches
+                    WHERE commit_branches.commit = commits.id
+                    ORDER BY commit_branches.branch)          AS branches,
                 timeline.timestamp                            AS timestamp,
                 coalesce(sum(timeline.time )::bigint, 0)      AS time,
                 coalesce(sum(files.lines_added)::bigint, 0)   AS lines_added,
@@ -94,13 +406,15 @@ pub fn fetch_timeline_comparison(conn: &PgConnection, repos: &Vec<i32>, start: i
         WHERE repositories.id = ANY ($1)
         AND commits.timestamp >= $2
         AND commits.timestamp < $3
+        AND NOT commits.is_superseded
+        {}
         GROUP BY files.path,
                 coalesce(users.username, commits.email),
                 repositories.id,
                 repositories.repo,
+                commits.id,
                 commits.hash,
-                commits.branch,
-                timeline.timestamp;")
+                timeline.timestamp;", path_rules_filter("repositories.group")))
         .bind::<sql_types::Array<sql_types::Integer>, _>(repos)
         .bind::<sql_types::BigInt, _>(start)
         .bind::<sql_types::BigInt, _>(end)
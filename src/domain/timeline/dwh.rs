@@ -1,4 +1,4 @@
-use diesel::sql_types::{BigInt, Text, Integer};
+use diesel::sql_types::{Array, BigInt, Text, Integer, Nullable};
 
 #[derive(QueryableByName, Debug)]
 pub struct TimelineDWH {
@@ -56,8 +56,8 @@ pub struct ComparisonDWH {
     pub repo_name: String,
     #[sql_type = "Text"]
     pub commit_hash: String,
-    #[sql_type = "Text"]
-    pub branch: String,
+    #[sql_type = "Array<Text>"]
+    pub branches: Vec<String>,
     #[sql_type = "BigInt"]
     pub timestamp: i64,
     #[sql_type = "BigInt"]
@@ -66,4 +66,18 @@ pub struct ComparisonDWH {
     pub lines_added: i64,
     #[sql_type = "BigInt"]
     pub lines_removed: i64,
-}
\ No newline at end of file
+}
+
+/// Time of a user in a timeline slot, `app` is the name of the app for app time and `None` for
+/// time spent editing files.
+#[derive(QueryableByName, Debug)]
+pub struct AppTimeDWH {
+    #[sql_type = "Text"]
+    pub user: String,
+    #[sql_type = "Nullable<Text>"]
+    pub app: Option<String>,
+    #[sql_type = "BigInt"]
+    pub timestamp: i64,
+    #[sql_type = "BigInt"]
+    pub time: i64,
+}
@@ -0,0 +1,120 @@
+use chrono::{Datelike, DateTime, Duration, NaiveDate, TimeZone};
+
+use crate::domain::timeline::helper::{add_local, from_local};
+
+// Keeps responses and the bucketing work of a single request bounded
+pub const MAX_INTERVALS: i64 = 10000;
+const MAX_CUSTOM_BOUNDARIES: usize = 1000;
+
+/// Length of the timeline intervals. `Hour`, `Month` and `Year` intervals start at the requested
+/// start, `Day` and `Week` intervals at local midnight of the day it falls on. `IsoWeek` and
+/// `Quarter` intervals are aligned to calendar weeks starting on Monday and to calendar quarters.
+/// `Custom` intervals lie between consecutive user supplied boundaries.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Granularity {
+    Minute,
+    QuarterHour,
+    Hour,
+    Day,
+    Week,
+    IsoWeek,
+    Month,
+    Quarter,
+    Year,
+    Custom(Vec<i64>),
+}
+
+impl Granularity {
+    /// Parses the `interval` parameter, `custom` takes the comma separated boundaries in
+    /// `buckets`, other intervals must come without them.
+    pub fn parse(interval: &str, buckets: Option<&str>) -> Result<Granularity, &'static str> {
+        let granularity = match &*interval.to_lowercase() {
+            "minute" => Granularity::Minute,
+            "15min" => Granularity::QuarterHour,
+            "hour" => Granularity::Hour,
+            "day" => Granularity::Day,
+            "week" => Granularity::Week,
+            "isoweek" => Granularity::IsoWeek,
+            "month" => Granularity::Month,
+            "quarter" => Granularity::Quarter,
+            "year" => Granularity::Year,
+            "custom" => {
+                let boundaries = buckets
+                    .ok_or("Custom interval requires buckets!")?
+                    .split(",")
+                    .map(|b| b.trim().parse::<i64>())
+                    .collect::<Result<Vec<i64>, _>>()
+                    .map_err(|_| "Invalid buckets!")?;
+                if boundaries.len() < 2 || boundaries.len() > MAX_CUSTOM_BOUNDARIES {
+                    return Err("Custom interval requires 2 to 1000 buckets!");
+                }
+                if boundaries.windows(2).any(|w| w[0] >= w[1]) {
+                    return Err("Buckets must be in ascending order!");
+                }
+                return Ok(Granularity::Custom(boundaries));
+            }
+            _ => return Err("Invalid interval!"),
+        };
+        if buckets.is_some() {
+            return Err("Buckets are only allowed with custom interval!");
+        }
+        Ok(granularity)
+    }
+
+    /// Length in seconds of intervals that are generated by adding a fixed duration, intervals of
+    /// a day or longer vary with daylight saving transitions and month lengths.
+    pub fn fixed_seconds(&self) -> Option<i64> {
+        match self {
+            Granularity::Minute => Some(60),
+            Granularity::QuarterHour => Some(15 * 60),
+            Granularity::Hour => Some(60 * 60),
+            _ => None,
+        }
+    }
+
+    /// Upper bound of the number of intervals between `start` and `end`.
+    pub fn count_intervals(&self, start: i64, end: i64) -> i64 {
+        match self {
+            Granularity::Custom(boundaries) => boundaries.len() as i64 - 1,
+            Granularity::Day => (end - start) / (23 * 60 * 60) + 2,
+            Granularity::Week | Granularity::IsoWeek => (end - start) / (7 * 24 * 60 * 60) + 2,
+            Granularity::Month | Granularity::Quarter | Granularity::Year => {
+                (end - start) / (28 * 24 * 60 * 60) + 2
+            }
+            _ => (end - start) / self.fixed_seconds().unwrap_or(1) + 1,
+        }
+    }
+
+    /// Start of the first interval holding `date_time_tz`.
+    pub fn first_start<Tz: TimeZone>(&self, date_time_tz: DateTime<Tz>) -> DateTime<Tz> {
+        let date = date_time_tz.naive_local().date();
+        match self {
+            Granularity::Day | Granularity::Week => from_local(&date_time_tz.timezone(), &date.and_hms(0, 0, 0)),
+            Granularity::IsoWeek => from_local(
+                &date_time_tz.timezone(),
+                &(date - Duration::days(date.weekday().num_days_from_monday() as i64)).and_hms(0, 0, 0),
+            ),
+            Granularity::Quarter => from_local(
+                &date_time_tz.timezone(),
+                &NaiveDate::from_ymd(date.year(), date.month0() / 3 * 3 + 1, 1).and_hms(0, 0, 0),
+            ),
+            _ => date_time_tz,
+        }
+    }
+
+    /// Start of the `n`th interval after the one starting at `anchor`. Intervals of a day or
+    /// longer follow the local calendar, so daily intervals starting at local midnight keep
+    /// starting at local midnight across daylight saving transitions.
+    pub fn nth_start<Tz: TimeZone>(&self, anchor: &DateTime<Tz>, n: i64) -> DateTime<Tz> {
+        if let Some(seconds) = self.fixed_seconds() {
+            return anchor.clone() + Duration::seconds(seconds * n);
+        }
+        match self {
+            Granularity::Day => add_local(anchor, 0, n),
+            Granularity::Week | Granularity::IsoWeek => add_local(anchor, 0, 7 * n),
+            Granularity::Month => add_local(anchor, n, 0),
+            Granularity::Quarter => add_local(anchor, 3 * n, 0),
+            _ => add_local(anchor, 12 * n, 0),
+        }
+    }
+}
@@ -1,73 +1,124 @@
 use std::default::Default;
 
-use chrono::{Datelike, DateTime, TimeZone};
+use chrono::{Datelike, DateTime, Duration, NaiveDate, NaiveDateTime, Offset, TimeZone, Timelike};
 
+use crate::domain::timeline::granularity::Granularity;
 use crate::domain::timeline::resources::Activity;
 
-pub trait DateTimeExt<Tz: TimeZone> {
-    fn next_month(&self) -> DateTime<Tz>;
-    fn next_year(&self) -> DateTime<Tz>;
+/// Resolves a local date and time in `timezone`. Local times skipped by a daylight saving
+/// transition move forward by the length of the gap, repeated ones resolve to the earlier instant.
+pub fn from_local<Tz: TimeZone>(timezone: &Tz, local: &NaiveDateTime) -> DateTime<Tz> {
+    if let Some(date_time_tz) = timezone.from_local_datetime(local).earliest() {
+        return date_time_tz;
+    }
+    // Gaps are at most a few hours long, the offset before the gap gives the instant after it
+    let offset = timezone.offset_from_utc_datetime(&(*local - Duration::days(1))).fix();
+    timezone.from_utc_datetime(&(*local - Duration::seconds(offset.local_minus_utc() as i64)))
 }
 
-impl<Tz: TimeZone> DateTimeExt<Tz> for DateTime<Tz> {
-    fn next_month(&self) -> DateTime<Tz> {
-        self.with_month(self.month() + 1)
-            .unwrap_or(self.with_year(self.year() + 1).unwrap().with_month(1).unwrap())
-    }
+/// Local date and time `months` months and `days` days after `anchor` in its timezone. The day of
+/// month is clamped to the length of the resulting month, e.g. one month after January 31st is
+/// February 28th or 29th.
+pub fn add_local<Tz: TimeZone>(anchor: &DateTime<Tz>, months: i64, days: i64) -> DateTime<Tz> {
+    let local = anchor.naive_local();
+    let month0 = local.year() as i64 * 12 + local.month0() as i64 + months;
+    let (year, month) = (month0.div_euclid(12) as i32, month0.rem_euclid(12) as u32 + 1);
+    let date = NaiveDate::from_ymd(year, month, local.day().min(days_in_month(year, month)))
+        + Duration::days(days);
+    from_local(&anchor.timezone(), &date.and_time(local.time()))
+}
 
-    fn next_year(&self) -> DateTime<Tz> {
-        self.with_year(self.year() + 1).unwrap()
-    }
+fn days_in_month(year: i32, month: u32) -> u32 {
+    let next = if month == 12 {
+        NaiveDate::from_ymd(year + 1, 1, 1)
+    } else {
+        NaiveDate::from_ymd(year, month + 1, 1)
+    };
+    (next - NaiveDate::from_ymd(year, month, 1)).num_days() as u32
 }
 
 pub fn generate_intervals<Tz: TimeZone, EntryT>(
     start_tz: DateTime<Tz>,
     end_tz: DateTime<Tz>,
-    interval: &str,
+    interval: &Granularity,
     entry_fn: fn(start: DateTime<Tz>, end: DateTime<Tz>) -> EntryT,
 ) -> Vec<EntryT> {
     let mut intervals = Vec::new();
-    let mut current_start_tz = start_tz.clone();
-    let mut current_end_tz = get_next_interval_start(start_tz, interval);
+    if let Granularity::Custom(boundaries) = interval {
+        let timezone = start_tz.timezone();
+        for bounds in boundaries.windows(2) {
+            intervals.push(entry_fn(
+                timezone.timestamp(bounds[0], 0),
+                timezone.timestamp(bounds[1], 0) + Duration::seconds(-1),
+            ));
+        }
+        return intervals;
+    }
 
+    // Every interval start is computed from the first one, so clamped month days and daylight
+    // saving transitions don't carry over to the following intervals
+    let anchor = interval.first_start(start_tz);
+    let mut current_start_tz = anchor.clone();
+    let mut n = 0;
     while end_tz.ge(&current_start_tz) {
+        n += 1;
+        let next_start_tz = interval.nth_start(&anchor, n);
         intervals.push(
-            entry_fn(current_start_tz.clone(), current_end_tz.clone() + chrono::Duration::seconds(-1))
+            entry_fn(current_start_tz, next_start_tz.clone() + Duration::seconds(-1))
         );
-        current_start_tz = get_next_interval_start(current_start_tz, interval);
-        current_end_tz = get_next_interval_start(current_end_tz, interval);
+        current_start_tz = next_start_tz;
     }
 
     intervals
 }
 
-fn get_next_interval_start<Tz: TimeZone>(
-    date_time_tz: DateTime<Tz>,
-    interval: &str
-) -> DateTime<Tz> {
-    let interval = &interval.to_lowercase();
-    if interval == "hour" || interval == "day" || interval == "week" {
-        return date_time_tz.clone() + get_interval_duration(interval);
+/// Index of the last interval starting at or before `timestamp`, `None` if it precedes them all.
+/// `bounds` are the `(start, end)` timestamps of intervals from `generate_intervals`.
+fn find_last_started(bounds: &[(i64, i64)], interval: &Granularity, timestamp: i64) -> Option<usize> {
+    let first = bounds.first()?.0;
+    if timestamp < first {
+        return None;
     }
-    if interval == "month" {
-        return date_time_tz.next_month();
+    let index = match interval.fixed_seconds() {
+        Some(length) => ((timestamp - first) / length) as usize,
+        None => match bounds.binary_search_by(|(start, _)| start.cmp(&timestamp)) {
+            Ok(index) => index,
+            Err(index) => index - 1,
+        },
+    };
+    Some(index.min(bounds.len() - 1))
+}
+
+/// Index of the interval holding `timestamp`, an interval holds `start <= timestamp < end`.
+pub fn find_interval(bounds: &[(i64, i64)], interval: &Granularity, timestamp: i64) -> Option<usize> {
+    find_last_started(bounds, interval, timestamp).filter(|index| timestamp < bounds[*index].1)
+}
+
+/// Index of the first interval ending after `timestamp`, i.e. the first interval a cumulative
+/// timeline counts it in. Equals `bounds.len()` when no interval does.
+pub fn find_first_ending_after(bounds: &[(i64, i64)], interval: &Granularity, timestamp: i64) -> usize {
+    match find_last_started(bounds, interval, timestamp) {
+        None => 0,
+        Some(index) if timestamp < bounds[index].1 => index,
+        Some(index) => index + 1,
     }
-    date_time_tz.next_year()
 }
 
-fn get_interval_duration(interval: &str) -> chrono::Duration {
-    return match interval {
-        "hour" => chrono::Duration::hours(1),
-        "day" => chrono::Duration::days(1),
-        _ => chrono::Duration::weeks(1)
+/// Id of the activity interval from `generate_activity_interval` a time point falls in.
+pub fn get_activity_id<Tz: TimeZone>(time_point: &DateTime<Tz>, interval: &Granularity) -> i32 {
+    match interval {
+        Granularity::Day => time_point.hour() as i32,
+        Granularity::Week => time_point.weekday().number_from_monday() as i32,
+        Granularity::Month => time_point.day0() as i32,
+        Granularity::Year => time_point.month0() as i32,
+        _ => 0,
     }
 }
 
-pub fn generate_activity_interval(interval: &str) -> Vec<Activity> {
+pub fn generate_activity_interval(interval: &Granularity) -> Vec<Activity> {
     let mut res: Vec<Activity> = vec![];
-    let interval= &*interval.to_lowercase();
     match interval {
-        "day" => {
+        Granularity::Day => {
             for i in 0..24 {
                 res.push(Activity {
                     id: i,
@@ -79,7 +130,7 @@ pub fn generate_activity_interval(interval: &str) -> Vec<Activity> {
                 })
             }
         },
-        "week" => {
+        Granularity::Week => {
             let days = vec!["Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday", "Sunday"];
             for i in 0..days.len() {
                 res.push(Activity {
@@ -92,7 +143,7 @@ pub fn generate_activity_interval(interval: &str) -> Vec<Activity> {
                 })
             }
         },
-        "month" => {
+        Granularity::Month => {
             for i in 0..31 {
                 res.push(Activity {
                     id: i,
@@ -104,7 +155,7 @@ pub fn generate_activity_interval(interval: &str) -> Vec<Activity> {
                 })
             }
         },
-        "year" => {
+        Granularity::Year => {
             for i in 0..12 {
                 res.push(Activity {
                     id: i,
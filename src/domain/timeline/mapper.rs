@@ -2,16 +2,22 @@ use std::collections::{HashMap, HashSet};
 use std::iter::FromIterator;
 use std::time::{Duration, UNIX_EPOCH};
 
-use chrono::{DateTime, Datelike, TimeZone, Timelike, Utc};
+use chrono::{DateTime, TimeZone, Utc};
 use chrono_tz::Tz;
 use itertools::Itertools;
 
-use crate::domain::timeline::dwh::{ComparisonDWH, FileEditDWH, PathlessFileEditDWH, TimelineDWH};
-use crate::domain::timeline::helper::{generate_activity_interval, generate_intervals};
+use crate::domain::timeline::dwh::{AppTimeDWH, ComparisonDWH, FileEditDWH, PathlessFileEditDWH, TimelineDWH};
+use crate::domain::timeline::granularity::Granularity;
+use crate::domain::timeline::helper::{
+    find_first_ending_after, find_interval, generate_activity_interval, generate_intervals,
+    get_activity_id,
+};
 use crate::domain::timeline::resources::{
-    ActivityJson, ComparisonJsonWrapper, ComparisonStatJson, ComparisonStatJsonEntry, Interval,
+    ActivityJson, AppInterval, AppTimelineJsonWrapper, ComparisonJsonWrapper, ComparisonStatJson,
+    ComparisonStatJsonEntry, HeatmapCell,
+    HeatmapJson, Interval,
     IntervalJson, SubdirLevelTimeline, SubdirLevelTimelineEntry, SubdirLevelTimelineJson,
-    TimelineComparisonEntry, TimelineComparisonJsonEntry
+    TimelineComparisonEntry, TimelineComparisonJsonEntry, UserAppTime,
 };
 
 pub fn map_timeline(
@@ -19,7 +25,7 @@ pub fn map_timeline(
     start: i64,
     end: i64,
     timezone: &str,
-    interval: &str,
+    interval: &Granularity,
     cumulative: bool,
 ) -> Vec<IntervalJson> {
     let tz: Tz = timezone.parse().unwrap();
@@ -31,17 +37,25 @@ pub fn map_timeline(
         time: 0,
         users: vec![],
     });
+    let bounds: Vec<(i64, i64)> = intervals.iter()
+        .map(|i| (i.start.timestamp(), i.end.timestamp()))
+        .collect();
     for item in data {
-        for i in 0..intervals.len() {
-            if (intervals[i].start.timestamp() <= item.timestamp || cumulative)
-                && item.timestamp < intervals[i].end.timestamp()
-            {
-                intervals[i].time += item.time;
-                if !intervals[i].users.contains(&item.user) {
-                    intervals[i].users.push(item.user.to_string());
-                }
-                if !cumulative {
-                    break;
+        if let Some(i) = find_bucket(&bounds, interval, item.timestamp, cumulative) {
+            intervals[i].time += item.time;
+            if !intervals[i].users.contains(&item.user) {
+                intervals[i].users.push(item.user.to_string());
+            }
+        }
+    }
+    if cumulative {
+        for i in 1..intervals.len() {
+            let (previous, current) = intervals.split_at_mut(i);
+            let (previous, current) = (&previous[i - 1], &mut current[0]);
+            current.time += previous.time;
+            for user in &previous.users {
+                if !current.users.contains(user) {
+                    current.users.push(user.to_string());
                 }
             }
         }
@@ -53,23 +67,16 @@ pub fn map_timeline(
 pub fn map_activity(
     data: Vec<PathlessFileEditDWH>,
     timezone: &str,
-    interval: &str,
+    interval: &Granularity,
     cumulative: bool,
 ) -> Vec<ActivityJson> {
     let tz: Tz = timezone.parse().unwrap();
-    let interval = &*interval.to_lowercase();
     let mut intervals = generate_activity_interval(interval);
 
     for item in data {
         let time_point = get_datetime_tz_from_seconds(item.timestamp, &tz);
+        let id = get_activity_id(&time_point, interval);
         for activity in intervals.iter_mut() {
-            let id = match interval {
-                "day" => time_point.hour() as i32,
-                "week" => time_point.weekday().number_from_monday() as i32,
-                "month" => time_point.day0() as i32,
-                "year" => time_point.month0() as i32,
-                _ => 0,
-            };
             if !cumulative && activity.id == id || cumulative && activity.id >= id {
                 activity.time += item.time;
                 activity.lines_added += item.lines_added;
@@ -87,7 +94,7 @@ pub fn map_subdir_level_timeline(
     start: i64,
     end: i64,
     timezone: &str,
-    interval: &str,
+    interval: &Granularity,
     cumulative: bool,
 ) -> Vec<SubdirLevelTimelineJson> {
     let tz: Tz = timezone.parse().unwrap();
@@ -99,41 +106,57 @@ pub fn map_subdir_level_timeline(
             end: e,
             directories: HashMap::new(),
         });
+    let bounds: Vec<(i64, i64)> = intervals.iter()
+        .map(|i| (i.start.timestamp(), i.end.timestamp()))
+        .collect();
     for item in data {
-        if item.path.ends_with(".app") {
-            continue;
+        if let Some(i) = find_bucket(&bounds, interval, item.timestamp, cumulative) {
+            let cut_path = cut_path(&item.path, depth);
+            let entry = intervals[i].directories.get_mut(&cut_path);
+            if entry.is_some() {
+                let entry = entry.unwrap();
+                entry.time += item.time;
+                entry.lines_added += item.lines_added;
+                entry.lines_removed += item.lines_deleted;
+                entry.commits.insert(item.commit_hash.clone());
+                entry.users.insert(item.user.to_string());
+            } else {
+                intervals[i].directories.insert(
+                    cut_path.clone(),
+                    SubdirLevelTimelineEntry {
+                        path: cut_path,
+                        time: item.time,
+                        commits: HashSet::from_iter(
+                            std::iter::repeat(item.commit_hash.clone()).take(1),
+                        ),
+                        lines_added: item.lines_added,
+                        lines_removed: item.lines_deleted,
+                        users: HashSet::from_iter(std::iter::repeat(item.user.clone()).take(1)),
+                    },
+                );
+            }
         }
-        for i in 0..intervals.len() {
-            if (intervals[i].start.timestamp() <= item.timestamp || cumulative)
-                && item.timestamp < intervals[i].end.timestamp()
-            {
-                let cut_path = cut_path(&item.path, depth);
-                let entry = intervals[i].directories.get_mut(&cut_path);
-                if entry.is_some() {
-                    let entry = entry.unwrap();
-                    entry.time += item.time;
-                    entry.lines_added += item.lines_added;
-                    entry.lines_removed += item.lines_deleted;
-                    entry.commits.insert(item.commit_hash.clone());
-                    entry.users.insert(item.user.to_string());
-                } else {
-                    intervals[i].directories.insert(
-                        cut_path.clone(),
-                        SubdirLevelTimelineEntry {
-                            path: cut_path,
-                            time: item.time,
-                            commits: HashSet::from_iter(
-                                std::iter::repeat(item.commit_hash.clone()).take(1),
-                            ),
-                            lines_added: item.lines_added,
-                            lines_removed: item.lines_deleted,
-                            users: HashSet::from_iter(std::iter::repeat(item.user.clone()).take(1)),
-                        },
-                    );
-                }
-                if !cumulative {
-                    break;
-                };
+    }
+    if cumulative {
+        for i in 1..intervals.len() {
+            let (previous, current) = intervals.split_at_mut(i);
+            let (previous, current) = (&previous[i - 1], &mut current[0]);
+            for (path, previous_entry) in &previous.directories {
+                let entry = current.directories
+                    .entry(path.clone())
+                    .or_insert_with(|| SubdirLevelTimelineEntry {
+                        path: path.clone(),
+                        time: 0,
+                        commits: HashSet::new(),
+                        lines_added: 0,
+                        lines_removed: 0,
+                        users: HashSet::new(),
+                    });
+                entry.time += previous_entry.time;
+                entry.lines_added += previous_entry.lines_added;
+                entry.lines_removed += previous_entry.lines_removed;
+                entry.commits.extend(previous_entry.commits.iter().cloned());
+                entry.users.extend(previous_entry.users.iter().cloned());
             }
         }
     }
@@ -141,12 +164,74 @@ pub fn map_subdir_level_timeline(
     intervals.into_iter().map(|x| x.attach()).collect()
 }
 
+/// App time per app and user over the intervals, and app and code time per user over the whole
+/// period.
+pub fn map_app_timeline(
+    data: Vec<AppTimeDWH>,
+    start: i64,
+    end: i64,
+    timezone: &str,
+    interval: &Granularity,
+    cumulative: bool,
+) -> AppTimelineJsonWrapper {
+    let tz: Tz = timezone.parse().unwrap();
+    let start_tz: DateTime<Tz> = get_datetime_tz_from_seconds(start, &tz);
+    let end_tz = get_datetime_tz_from_seconds(end, &tz);
+    let mut intervals = generate_intervals(start_tz, end_tz, interval, |s, e| AppInterval {
+        start: s,
+        end: e,
+        apps: HashMap::new(),
+    });
+    let bounds: Vec<(i64, i64)> = intervals.iter()
+        .map(|i| (i.start.timestamp(), i.end.timestamp()))
+        .collect();
+    let mut users: HashMap<String, UserAppTime> = HashMap::new();
+    for item in data {
+        let user = users.entry(item.user.clone()).or_default();
+        match item.app {
+            Some(app) => {
+                *user.apps.entry(app.clone()).or_insert(0) += item.time;
+                if let Some(i) = find_bucket(&bounds, interval, item.timestamp, cumulative) {
+                    *intervals[i].apps.entry(app).or_default().entry(item.user).or_insert(0) += item.time;
+                }
+            }
+            None => user.code_time += item.time,
+        }
+    }
+    if cumulative {
+        for i in 1..intervals.len() {
+            let (previous, current) = intervals.split_at_mut(i);
+            let (previous, current) = (&previous[i - 1], &mut current[0]);
+            for (app, app_users) in &previous.apps {
+                let entry = current.apps.entry(app.clone()).or_default();
+                for (user, time) in app_users {
+                    *entry.entry(user.clone()).or_insert(0) += time;
+                }
+            }
+        }
+    }
+
+    let apps: Vec<String> = users.values()
+        .flat_map(|u| u.apps.keys().cloned())
+        .sorted()
+        .dedup()
+        .collect();
+    AppTimelineJsonWrapper {
+        apps,
+        data: intervals.iter().map(|x| x.attach()).collect(),
+        users: users.iter()
+            .sorted_by(|a, b| a.0.cmp(b.0))
+            .map(|(user, time)| time.attach(user))
+            .collect(),
+    }
+}
+
 pub fn map_timeline_comparison(
     data: Vec<ComparisonDWH>,
     start: i64,
     end: i64,
     timezone: &str,
-    interval: &str,
+    interval: &Granularity,
     repos: &Vec<i32>,
     branches: &Vec<String>,
     users: &Vec<String>,
@@ -176,6 +261,9 @@ pub fn map_timeline_comparison(
             commits: Default::default(),
             users: Default::default(),
         });
+    let bounds: Vec<(i64, i64)> = general_intervals.iter()
+        .map(|i| (i.start.timestamp(), i.end.timestamp()))
+        .collect();
     let mut filtered_data: Vec<ComparisonDWH> = vec![];
     let mut repo_names: HashSet<String> = HashSet::default();
     let mut user_names: HashSet<String> = HashSet::default();
@@ -183,13 +271,13 @@ pub fn map_timeline_comparison(
     for item in &data {
         repo_names.insert(item.repo_name.clone());
         user_names.insert(item.user.clone());
-        branch_names.insert(item.branch.clone());
-        accumulate_data(&mut general_intervals, &item);
+        branch_names.extend(item.branches.iter().cloned());
+        accumulate_data(&mut general_intervals, &bounds, interval, &item);
         if repos.contains(&item.repo)
-            && branches.contains(&item.branch)
+            && item.branches.iter().any(|b| branches.contains(b))
             && users.contains(&item.user)
         {
-            accumulate_data(&mut filtered_intervals, &item);
+            accumulate_data(&mut filtered_intervals, &bounds, interval, &item);
             filtered_data.push(item.clone());
         }
     }
@@ -213,6 +301,48 @@ pub fn map_timeline_comparison(
     }
 }
 
+pub fn map_heatmap(
+    data: Vec<ComparisonDWH>,
+    timezone: &str,
+    repos: &Vec<i32>,
+    branches: &Vec<String>,
+    users: &Vec<String>,
+) -> HeatmapJson {
+    let tz: Tz = timezone.parse().unwrap();
+    let mut cells: Vec<Vec<HeatmapCell>> = (0..7)
+        .map(|_| (0..24).map(|_| HeatmapCell::default()).collect())
+        .collect();
+    for item in data {
+        if !(repos.is_empty() || repos.contains(&item.repo))
+            || !(branches.is_empty() || item.branches.iter().any(|b| branches.contains(b)))
+            || !(users.is_empty() || users.contains(&item.user))
+        {
+            continue;
+        }
+        let time_point = get_datetime_tz_from_seconds(item.timestamp, &tz);
+        let day = get_activity_id(&time_point, &Granularity::Week) as usize - 1;
+        let hour = get_activity_id(&time_point, &Granularity::Day) as usize;
+        let cell = &mut cells[day][hour];
+        cell.time += item.time;
+        cell.lines_added += item.lines_added;
+        cell.lines_removed += item.lines_removed;
+        cell.users.insert(item.user);
+    }
+
+    let matrix = |value: fn(&HeatmapCell) -> i64| -> Vec<Vec<i64>> {
+        cells.iter().map(|day| day.iter().map(value).collect()).collect()
+    };
+    HeatmapJson {
+        days: generate_activity_interval(&Granularity::Week).into_iter().map(|a| a.label).collect(),
+        time: cells.iter()
+            .map(|day| day.iter().map(|c| (c.time as f64 / 60.0 / 60.0 * 10.0).round() / 10.0).collect())
+            .collect(),
+        lines_added: matrix(|c| c.lines_added),
+        lines_removed: matrix(|c| c.lines_removed),
+        users: matrix(|c| c.users.len() as i64),
+    }
+}
+
 fn get_comparison_stats<F>(
     mut key_data: F,
     general_data: &Vec<ComparisonDWH>,
@@ -261,21 +391,28 @@ where
 
 fn accumulate_data<Tz: TimeZone>(
     filtered_intervals: &mut Vec<TimelineComparisonEntry<Tz>>,
+    bounds: &[(i64, i64)],
+    interval: &Granularity,
     item: &ComparisonDWH,
 ) {
-    for i in 0..filtered_intervals.len() {
-        if filtered_intervals[i].start.timestamp() <= item.timestamp
-            && item.timestamp < filtered_intervals[i].end.timestamp()
-        {
-            filtered_intervals[i].time += item.time;
-            filtered_intervals[i].lines_added += item.lines_added;
-            filtered_intervals[i].lines_removed += item.lines_removed;
-            filtered_intervals[i]
-                .commits
-                .insert(item.commit_hash.clone());
-            filtered_intervals[i].users.insert(item.user.clone());
-            break;
-        }
+    if let Some(i) = find_interval(bounds, interval, item.timestamp) {
+        filtered_intervals[i].time += item.time;
+        filtered_intervals[i].lines_added += item.lines_added;
+        filtered_intervals[i].lines_removed += item.lines_removed;
+        filtered_intervals[i]
+            .commits
+            .insert(item.commit_hash.clone());
+        filtered_intervals[i].users.insert(item.user.clone());
+    }
+}
+
+/// Interval an item is added to, cumulative timelines add it to the first interval ending after
+/// it and carry it over to the following intervals.
+fn find_bucket(bounds: &[(i64, i64)], interval: &Granularity, timestamp: i64, cumulative: bool) -> Option<usize> {
+    if cumulative {
+        Some(find_first_ending_after(bounds, interval, timestamp)).filter(|i| *i < bounds.len())
+    } else {
+        find_interval(bounds, interval, timestamp)
     }
 }
 
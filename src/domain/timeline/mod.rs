@@ -3,6 +3,7 @@ pub mod routes;
 pub mod model;
 pub mod mapper;
 pub mod helper;
+pub mod granularity;
 pub mod db;
 pub mod dwh;
 pub mod resources;
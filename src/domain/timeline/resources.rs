@@ -93,6 +93,56 @@ impl SubdirLevelTimelineEntry {
     }
 }
 
+#[derive(Debug)]
+pub struct AppInterval<Tz: TimeZone> {
+    pub start: DateTime<Tz>,
+    pub end: DateTime<Tz>,
+    /// Seconds per user per app.
+    pub apps: HashMap<String, HashMap<String, i64>>,
+}
+
+#[derive(Debug, Default)]
+pub struct UserAppTime {
+    pub code_time: i64,
+    pub apps: HashMap<String, i64>,
+}
+
+impl<Tz: TimeZone> AppInterval<Tz> {
+    pub fn attach(&self) -> AppIntervalJson {
+        let apps: HashMap<String, AppIntervalJsonEntry> = HashMap::from_iter(self.apps.iter()
+            .map(|(app, users)| (app.clone(), AppIntervalJsonEntry {
+                time: (users.values().sum::<i64>() as f64 / 60.0 / 60.0 * 10.0).round() / 10.0,
+                users: HashMap::from_iter(users.iter()
+                    .map(|(user, time)| (user.clone(), (*time as f64 / 60.0 / 60.0 * 10.0).round() / 10.0))),
+            })));
+        let time: i64 = self.apps.values().flat_map(|users| users.values()).sum();
+        AppIntervalJson {
+            start: format!("{:?}{}", self.start.naive_local(), self.start.offset().fix()),
+            end: format!("{:?}{}", self.end.naive_local(), self.end.offset().fix()),
+            time: (time as f64 / 60.0 / 60.0 * 10.0).round() / 10.0,
+            apps,
+        }
+    }
+}
+
+impl UserAppTime {
+    pub fn attach(&self, user: &str) -> UserAppTimeJson {
+        let app_time: i64 = self.apps.values().sum();
+        UserAppTimeJson {
+            user: user.to_string(),
+            app_time: (app_time as f64 / 60.0 / 60.0 * 10.0).round() / 10.0,
+            code_time: (self.code_time as f64 / 60.0 / 60.0 * 10.0).round() / 10.0,
+            app_code_ratio: if self.code_time > 0 {
+                Some((app_time as f64 / self.code_time as f64 * 100.0).round() / 100.0)
+            } else {
+                None
+            },
+            apps: HashMap::from_iter(self.apps.iter()
+                .map(|(app, time)| (app.clone(), (*time as f64 / 60.0 / 60.0 * 10.0).round() / 10.0))),
+        }
+    }
+}
+
 #[derive(Serialize, JsonSchema)]
 pub struct IntervalJson {
     pub start: String,
@@ -101,6 +151,62 @@ pub struct IntervalJson {
     pub users: i32,
 }
 
+#[derive(Debug, Default)]
+pub struct HeatmapCell {
+    pub time: i64,
+    pub lines_added: i64,
+    pub lines_removed: i64,
+    pub users: HashSet<String>,
+}
+
+/// Rows are days of the week starting from Monday, columns are hours of the day.
+#[derive(Serialize, JsonSchema)]
+pub struct HeatmapJson {
+    pub days: Vec<String>,
+    pub time: Vec<Vec<f64>>,
+    pub lines_added: Vec<Vec<i64>>,
+    pub lines_removed: Vec<Vec<i64>>,
+    pub users: Vec<Vec<i64>>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct AppIntervalJson {
+    pub start: String,
+    pub end: String,
+    pub time: f64,
+    pub apps: HashMap<String, AppIntervalJsonEntry>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct AppIntervalJsonEntry {
+    pub time: f64,
+    /// Hours per user.
+    pub users: HashMap<String, f64>,
+}
+
+/// App and code time of a user over the whole period, `app_code_ratio` is missing for users
+/// without code time.
+#[derive(Serialize, JsonSchema)]
+pub struct UserAppTimeJson {
+    pub user: String,
+    pub app_time: f64,
+    pub code_time: f64,
+    pub app_code_ratio: Option<f64>,
+    pub apps: HashMap<String, f64>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct AppTimelineJsonWrapper {
+    pub apps: Vec<String>,
+    pub data: Vec<AppIntervalJson>,
+    pub users: Vec<UserAppTimeJson>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct RollupRebuildJson {
+    pub rows: usize,
+}
+
 #[derive(Serialize, JsonSchema)]
 pub struct ActivityJson {
     pub label: String,
@@ -1,20 +1,23 @@
 use rocket::request::Form;
 use rocket_contrib::json::Json;
 use rocket_okapi::{openapi, JsonSchema};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use validator::Validate;
 
 use crate::domain::db::Conn;
+use crate::domain::group;
 use crate::domain::role::model::ADMIN;
 use crate::domain::timeline;
+use crate::domain::timeline::granularity::Granularity;
 use crate::domain::timeline::resources::{
-    ActivityJson, ComparisonJsonWrapper, IntervalJson, SubdirLevelTimelineJsonWrapper,
+    ActivityJson, AppTimelineJsonWrapper, ComparisonJsonWrapper, HeatmapJson, IntervalJson,
+    RollupRebuildJson, SubdirLevelTimelineJsonWrapper,
 };
 use crate::domain::user::model::AuthUser;
 use crate::errors::{Error, FieldValidator};
 use crate::security;
 
-#[derive(Deserialize, Validate, JsonSchema)]
+#[derive(Serialize, Deserialize, Validate, JsonSchema)]
 pub struct NewTimelineData {
     pub timestamp: Option<i64>,
     pub time: Option<i64>,
@@ -22,11 +25,17 @@ pub struct NewTimelineData {
 
 #[derive(FromForm, Default, Validate, Deserialize, JsonSchema)]
 pub struct TimelineParams {
-    start: Option<i64>,
-    end: Option<i64>,
-    interval: Option<String>,
-    timezone: Option<String>,
-    cumulative: Option<bool>,
+    pub start: Option<i64>,
+    pub end: Option<i64>,
+    pub interval: Option<String>,
+    pub timezone: Option<String>,
+    pub cumulative: Option<bool>,
+    /// Comma separated branches, commits reachable from any of them are included.
+    pub branch: Option<String>,
+    /// Comma separated interval boundaries of the `custom` interval.
+    pub buckets: Option<String>,
+    /// Comma separated languages, only time spent on files of them is included.
+    pub language: Option<String>,
 }
 
 #[derive(FromForm, Default, Validate, Deserialize, JsonSchema)]
@@ -39,6 +48,12 @@ pub struct SubdirTimelineParams {
     time_threshold: Option<f64>,
     lines_threshold: Option<i64>,
     cumulative: Option<bool>,
+    /// Comma separated branches, commits reachable from any of them are included.
+    branch: Option<String>,
+    /// Comma separated interval boundaries of the `custom` interval.
+    buckets: Option<String>,
+    /// Comma separated languages, only time spent on files of them is included.
+    language: Option<String>,
 }
 
 #[openapi]
@@ -54,12 +69,15 @@ pub fn get_timeline(
     }
     let params = params.into_inner();
     let mut validator = FieldValidator::validate(&params);
-    let start = validator.extract("start", params.start);
-    let end = validator.extract("end", params.end);
+    let (start, end) = group::service::default_period(&conn, &group_name, params.start, params.end);
+    let start = validator.extract("start", start);
+    let end = validator.extract("end", end);
     let interval = validator.extract("interval", params.interval);
     let timezone = validator.extract("timezone", params.timezone);
     let cumulative = params.cumulative.unwrap_or(false);
-    validator.validate_timeline_period(start, end, &interval);
+    let branches = split_branches(params.branch);
+    let languages = split_languages(params.language);
+    let interval = validator.validate_timeline_period(start, end, &interval, params.buckets.as_deref());
     validator.check()?;
 
     let timeline = timeline::service::get_timeline(
@@ -70,6 +88,8 @@ pub fn get_timeline(
         &timezone,
         &interval,
         cumulative,
+        &branches,
+        &languages,
     );
     Ok(Json(timeline))
 }
@@ -87,12 +107,15 @@ pub fn get_activity_timeline(
     }
     let params = params.into_inner();
     let mut validator = FieldValidator::validate(&params);
-    let start = validator.extract("start", params.start);
-    let end = validator.extract("end", params.end);
+    let (start, end) = group::service::default_period(&conn, &group_name, params.start, params.end);
+    let start = validator.extract("start", start);
+    let end = validator.extract("end", end);
     let interval = validator.extract("interval", params.interval);
     let timezone = validator.extract("timezone", params.timezone);
     let cumulative = params.cumulative.unwrap_or(false);
-    validator.validate_timeline_period(start, end, &interval);
+    let branches = split_branches(params.branch);
+    let languages = split_languages(params.language);
+    let interval = validator.validate_timeline_period(start, end, &interval, params.buckets.as_deref());
     validator.check()?;
 
     let timeline = timeline::service::get_activity_timeline(
@@ -103,6 +126,44 @@ pub fn get_activity_timeline(
         &timezone,
         &interval,
         cumulative,
+        &branches,
+        &languages,
+    )?;
+    Ok(Json(timeline))
+}
+
+#[openapi]
+#[get("/<group_name>/apps?<params..>")]
+pub fn get_app_timeline(
+    auth_user: AuthUser,
+    group_name: String,
+    params: Form<TimelineParams>,
+    conn: Conn,
+) -> Result<Json<AppTimelineJsonWrapper>, Error> {
+    if auth_user.require_role(&ADMIN).is_err() {
+        security::service::check_group_access(&conn, auth_user.user_id, &group_name)?;
+    }
+    let params = params.into_inner();
+    let mut validator = FieldValidator::validate(&params);
+    let (start, end) = group::service::default_period(&conn, &group_name, params.start, params.end);
+    let start = validator.extract("start", start);
+    let end = validator.extract("end", end);
+    let interval = validator.extract("interval", params.interval);
+    let timezone = validator.extract("timezone", params.timezone);
+    let cumulative = params.cumulative.unwrap_or(false);
+    let branches = split_branches(params.branch);
+    let interval = validator.validate_timeline_period(start, end, &interval, params.buckets.as_deref());
+    validator.check()?;
+
+    let timeline = timeline::service::get_app_timeline(
+        &conn,
+        &group_name,
+        start,
+        end,
+        &timezone,
+        &interval,
+        cumulative,
+        &branches,
     )?;
     Ok(Json(timeline))
 }
@@ -121,18 +182,23 @@ pub fn get_subdir_level_timeline(
     let params = params.into_inner();
 
     let mut validator = FieldValidator::validate(&params);
-    let start = validator.extract("start", params.start);
-    let end = validator.extract("end", params.end);
+    let (start, end) = group::service::default_period(&conn, &group_name, params.start, params.end);
+    let start = validator.extract("start", start);
+    let end = validator.extract("end", end);
     let interval = validator.extract("interval", params.interval);
     let timezone = validator.extract("timezone", params.timezone);
     let depth = validator.extract("depth", params.depth);
     let cumulative = params.cumulative.unwrap_or(false);
+    let branches = split_branches(params.branch);
+    let languages = split_languages(params.language);
 
-    let mut time_threshold_multiplier = (match &*interval {
-        "year" => 365.0,
-        "month" => 30.0,
-        "week" => 7.0,
-        "day" => 1.0,
+    let interval = validator.validate_timeline_period(start, end, &interval, params.buckets.as_deref());
+    let mut time_threshold_multiplier = (match interval {
+        Granularity::Year => 365.0,
+        Granularity::Quarter => 91.0,
+        Granularity::Month => 30.0,
+        Granularity::Week | Granularity::IsoWeek => 7.0,
+        Granularity::Day => 1.0,
         _ => 1.0,
     } as f64)
         .sqrt();
@@ -146,7 +212,6 @@ pub fn get_subdir_level_timeline(
         .lines_threshold
         .unwrap_or((((end - start) as f64).sqrt() * time_threshold_multiplier / 51.0) as i64);
     // TODO: validate depth?
-    validator.validate_timeline_period(start, end, &interval);
     validator.check()?;
 
     let timeline = timeline::service::get_subdir_level_timeline(
@@ -160,11 +225,31 @@ pub fn get_subdir_level_timeline(
         time_threshold,
         line_threshold,
         cumulative,
+        &branches,
+        &languages,
     )?;
 
     Ok(Json(timeline))
 }
 
+pub fn split_branches(branch: Option<String>) -> Option<Vec<String>> {
+    branch.map(|b| {
+        b.split(",")
+            .filter(|s| s.len() > 0)
+            .map(|s| s.to_string())
+            .collect()
+    })
+}
+
+pub fn split_languages(language: Option<String>) -> Option<Vec<String>> {
+    language.map(|l| {
+        l.split(",")
+            .map(|s| s.trim().to_lowercase())
+            .filter(|s| s.len() > 0)
+            .collect()
+    })
+}
+
 // TODO: Switch to vec in rocket 0.5.0
 #[derive(FromForm, Default, Validate, Deserialize, JsonSchema)]
 pub struct ComparisonParams {
@@ -176,6 +261,8 @@ pub struct ComparisonParams {
     end: Option<i64>,
     interval: Option<String>,
     timezone: Option<String>,
+    /// Comma separated interval boundaries of the `custom` interval.
+    buckets: Option<String>,
 }
 
 #[openapi]
@@ -215,7 +302,7 @@ pub fn get_timeline_comparison(
     let end = validator.extract("end", params.end);
     let interval = validator.extract("interval", params.interval);
     let timezone = validator.extract("timezone", params.timezone);
-    validator.validate_timeline_period(start, end, &interval);
+    let interval = validator.validate_timeline_period(start, end, &interval, params.buckets.as_deref());
     validator.check()?;
 
     if auth_user.require_role(&ADMIN).is_err() {
@@ -229,3 +316,72 @@ pub fn get_timeline_comparison(
     )?;
     Ok(Json(timeline))
 }
+
+#[derive(FromForm, Default, Validate, Deserialize, JsonSchema)]
+pub struct HeatmapParams {
+    groups: Option<String>,
+    /// Comma separated repository ids, all repositories of the groups if omitted.
+    repo: Option<String>,
+    /// Comma separated branches, all branches if omitted.
+    branch: Option<String>,
+    /// Comma separated users, all users if omitted.
+    user: Option<String>,
+    start: Option<i64>,
+    end: Option<i64>,
+    timezone: Option<String>,
+}
+
+#[openapi]
+#[get("/comparison/heatmap?<params..>")]
+pub fn get_heatmap(
+    auth_user: AuthUser,
+    params: Form<HeatmapParams>,
+    conn: Conn,
+) -> Result<Json<HeatmapJson>, Error> {
+    let params = params.into_inner();
+    let mut validator = FieldValidator::validate(&params);
+    let groups: Vec<String> = validator
+        .extract("groups", params.groups)
+        .split(",")
+        .map(|s| s.to_string())
+        .collect();
+    let repos = params
+        .repo
+        .unwrap_or("".to_string())
+        .split(",")
+        .filter(|s| s.len() > 0)
+        .map(|r| r.parse::<i32>().unwrap_or(-1))
+        .collect();
+    let branches = split_branches(params.branch).unwrap_or_default();
+    let users = params
+        .user
+        .unwrap_or("".to_string())
+        .split(",")
+        .filter(|s| s.len() > 0)
+        .map(|u| u.to_string())
+        .collect();
+    let start = validator.extract("start", params.start);
+    let end = validator.extract("end", params.end);
+    let timezone = validator.extract("timezone", params.timezone);
+    validator.validate_period(start, end);
+    validator.check()?;
+
+    if auth_user.require_role(&ADMIN).is_err() {
+        for group_name in &groups {
+            security::service::check_group_access(&conn, auth_user.user_id, &group_name)?;
+        }
+    }
+
+    let heatmap = timeline::service::get_heatmap(
+        &conn, &groups, &repos, &branches, &users, start, end, &timezone,
+    )?;
+    Ok(Json(heatmap))
+}
+
+#[openapi]
+#[post("/timeline/rollups/rebuild")]
+pub fn rebuild_timeline_rollups(auth_user: AuthUser, conn: Conn) -> Result<Json<RollupRebuildJson>, Error> {
+    auth_user.require_role(&ADMIN)?;
+    let rows = timeline::db::rebuild_rollups(&conn)?;
+    Ok(Json(RollupRebuildJson { rows }))
+}
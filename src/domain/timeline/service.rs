@@ -1,16 +1,25 @@
 use std::collections::HashMap;
 
+use chrono::Offset;
+use chrono_tz::Tz;
 use diesel::PgConnection;
 
-use crate::domain::file::db::{fetch_file_edits, fetch_pathless_file_edits};
+use crate::domain::file::db::{fetch_file_edits, fetch_pathless_file_edits, fetch_user_pathless_file_edits};
+use crate::domain::group_path_rule;
 use crate::domain::repository;
-use crate::domain::timeline::db::{fetch_timeline, fetch_timeline_comparison};
+use crate::domain::timeline::granularity::Granularity;
+use crate::domain::timeline::db::{
+    fetch_app_timeline, fetch_file_edit_rollups, fetch_pathless_file_edit_rollups,
+    fetch_repository_timeline, fetch_timeline, fetch_timeline_comparison, fetch_timeline_rollups,
+    fetch_user_timeline,
+};
 use crate::domain::timeline::mapper::{
-    map_activity, map_subdir_level_timeline, map_timeline, map_timeline_comparison,
+    get_datetime_tz_from_seconds, map_activity, map_app_timeline, map_heatmap,
+    map_subdir_level_timeline, map_timeline, map_timeline_comparison,
 };
 use crate::domain::timeline::resources::{
-    ActivityJson, ComparisonJsonWrapper, IntervalJson, SubdirLevelTimelineJson,
-    SubdirLevelTimelineJsonEntry, SubdirLevelTimelineJsonWrapper,
+    ActivityJson, AppTimelineJsonWrapper, ComparisonJsonWrapper, HeatmapJson, IntervalJson,
+    SubdirLevelTimelineJson, SubdirLevelTimelineJsonEntry, SubdirLevelTimelineJsonWrapper,
 };
 use crate::errors::Error;
 
@@ -20,26 +29,98 @@ pub fn get_timeline(
     start: i64,
     end: i64,
     timezone: &str,
-    interval: &str,
+    interval: &Granularity,
     cumulative: bool,
+    branches: &Option<Vec<String>>,
+    languages: &Option<Vec<String>>,
 ) -> Vec<IntervalJson> {
-    let timeline = fetch_timeline(conn, group_name, start, end);
+    let timeline = if !paths_needed(conn, group_name, languages) && rollups_apply(timezone, interval, start, end, &[start, end]) {
+        fetch_timeline_rollups(conn, group_name, start, end, branches)
+            .expect("Error loading timeline rollups for group")
+    } else {
+        fetch_timeline(conn, group_name, start, end, branches, languages)
+    };
     map_timeline(timeline, start, end, timezone, interval, cumulative)
 }
 
+pub fn get_repository_timeline(
+    conn: &PgConnection,
+    repository_id: i32,
+    start: i64,
+    end: i64,
+    timezone: &str,
+    interval: &Granularity,
+    cumulative: bool,
+    branches: &Option<Vec<String>>,
+    languages: &Option<Vec<String>>,
+) -> Result<Vec<IntervalJson>, Error> {
+    let timeline = fetch_repository_timeline(conn, repository_id, start, end, branches, languages)?;
+    Ok(map_timeline(timeline, start, end, timezone, interval, cumulative))
+}
+
+pub fn get_user_timeline(
+    conn: &PgConnection,
+    user_id: i32,
+    start: i64,
+    end: i64,
+    timezone: &str,
+    interval: &Granularity,
+    cumulative: bool,
+    branches: &Option<Vec<String>>,
+    languages: &Option<Vec<String>>,
+) -> Result<Vec<IntervalJson>, Error> {
+    let timeline = fetch_user_timeline(conn, user_id, start, end, branches, languages)?;
+    Ok(map_timeline(timeline, start, end, timezone, interval, cumulative))
+}
+
 pub fn get_activity_timeline(
     conn: &PgConnection,
     group_name: &str,
     start: i64,
     end: i64,
     timezone: &str,
-    interval: &str,
+    interval: &Granularity,
     cumulative: bool,
+    branches: &Option<Vec<String>>,
+    languages: &Option<Vec<String>>,
 ) -> Result<Vec<ActivityJson>, Error> {
-    let data = fetch_pathless_file_edits(conn, group_name, start, end)?;
+    let data = if !paths_needed(conn, group_name, languages) && rollups_apply(timezone, &Granularity::Hour, start, end, &[]) {
+        fetch_pathless_file_edit_rollups(conn, group_name, start, end, branches)?
+    } else {
+        fetch_pathless_file_edits(conn, group_name, start, end, branches, languages)?
+    };
     Ok(map_activity(data, timezone, interval, cumulative))
 }
 
+pub fn get_user_activity_timeline(
+    conn: &PgConnection,
+    user_id: i32,
+    start: i64,
+    end: i64,
+    timezone: &str,
+    interval: &Granularity,
+    cumulative: bool,
+    branches: &Option<Vec<String>>,
+    languages: &Option<Vec<String>>,
+) -> Result<Vec<ActivityJson>, Error> {
+    let data = fetch_user_pathless_file_edits(conn, user_id, start, end, branches, languages)?;
+    Ok(map_activity(data, timezone, interval, cumulative))
+}
+
+pub fn get_app_timeline(
+    conn: &PgConnection,
+    group_name: &str,
+    start: i64,
+    end: i64,
+    timezone: &str,
+    interval: &Granularity,
+    cumulative: bool,
+    branches: &Option<Vec<String>>,
+) -> Result<AppTimelineJsonWrapper, Error> {
+    let data = fetch_app_timeline(conn, group_name, start, end, branches)?;
+    Ok(map_app_timeline(data, start, end, timezone, interval, cumulative))
+}
+
 pub fn get_subdir_level_timeline(
     conn: &PgConnection,
     group_name: &str,
@@ -47,12 +128,18 @@ pub fn get_subdir_level_timeline(
     start: i64,
     end: i64,
     timezone: &str,
-    interval: &str,
+    interval: &Granularity,
     time_threshold: f64,
     lines_threshold: i64,
     cumulative: bool,
+    branches: &Option<Vec<String>>,
+    languages: &Option<Vec<String>>,
 ) -> Result<SubdirLevelTimelineJsonWrapper, Error> {
-    let file_edits_data = fetch_file_edits(conn, group_name, start, end)?;
+    let file_edits_data = if depth == 1 && !paths_needed(conn, group_name, languages) && rollups_apply(timezone, interval, start, end, &[start]) {
+        fetch_file_edit_rollups(conn, group_name, start, end, branches)?
+    } else {
+        fetch_file_edits(conn, group_name, start, end, branches, languages)?
+    };
     let mut users: Vec<&String> = file_edits_data.iter().map(|e| &e.user).collect();
     users.sort();
     users.dedup();
@@ -131,8 +218,32 @@ pub fn get_timeline_comparison(
     start: i64,
     end: i64,
     timezone: &str,
-    interval: &str,
+    interval: &Granularity,
 ) -> Result<ComparisonJsonWrapper, Error> {
+    let repositories = find_group_repositories(conn, group_names);
+    let raw_data = fetch_timeline_comparison(&conn, &repositories, start, end);
+    let data = map_timeline_comparison(
+        raw_data, start, end, timezone, interval, repos, branches, users,
+    );
+    Ok(data)
+}
+
+pub fn get_heatmap(
+    conn: &PgConnection,
+    group_names: &Vec<String>,
+    repos: &Vec<i32>,
+    branches: &Vec<String>,
+    users: &Vec<String>,
+    start: i64,
+    end: i64,
+    timezone: &str,
+) -> Result<HeatmapJson, Error> {
+    let repositories = find_group_repositories(conn, group_names);
+    let raw_data = fetch_timeline_comparison(&conn, &repositories, start, end);
+    Ok(map_heatmap(raw_data, timezone, repos, branches, users))
+}
+
+fn find_group_repositories(conn: &PgConnection, group_names: &Vec<String>) -> Vec<i32> {
     let mut repositories: Vec<i32> = group_names
         .iter()
         .flat_map(|g| repository::db::find_all_repository_ids_in_group(&conn, g).unwrap_or(vec![]))
@@ -141,10 +252,34 @@ pub fn get_timeline_comparison(
 
     repositories.sort();
     repositories.dedup();
+    repositories
+}
 
-    let raw_data = fetch_timeline_comparison(&conn, &repositories, start, end);
-    let data = map_timeline_comparison(
-        raw_data, start, end, timezone, interval, repos, branches, users,
-    );
-    Ok(data)
+// Rollups don't keep file paths, so language filters and path rules read the timeline itself
+fn paths_needed(conn: &PgConnection, group_name: &str, languages: &Option<Vec<String>>) -> bool {
+    languages.is_some() || group_path_rule::db::has_rules(conn, group_name)
+}
+
+/// Hourly rollups give the same buckets as raw timeline entries only when every bucket boundary
+/// falls on a whole UTC hour, i.e. the timezone offset is a whole number of hours over the period,
+/// intervals are whole hours long and the boundaries taken from the request (`aligned` and custom
+/// buckets) are whole hours themselves.
+fn rollups_apply(timezone: &str, interval: &Granularity, start: i64, end: i64, aligned: &[i64]) -> bool {
+    let tz: Tz = match timezone.parse() {
+        Ok(tz) => tz,
+        Err(_) => return false,
+    };
+    let boundaries: &[i64] = match interval {
+        Granularity::Custom(boundaries) => boundaries,
+        _ => &[],
+    };
+    if interval.fixed_seconds().map_or(false, |seconds| seconds % 3600 != 0) {
+        return false;
+    }
+    let whole_hour_offset = |seconds: i64| {
+        get_datetime_tz_from_seconds(seconds, &tz).offset().fix().local_minus_utc() % 3600 == 0
+    };
+    whole_hour_offset(start)
+        && whole_hour_offset(end)
+        && aligned.iter().chain(boundaries).all(|seconds| seconds % 3600 == 0)
 }
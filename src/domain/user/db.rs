@@ -1,10 +1,11 @@
 use std::fmt::{Display, Formatter};
 use std::fmt;
 
-use diesel::Insertable;
+use diesel::{Insertable, sql_query, sql_types};
 use diesel::prelude::*;
 use diesel::result::DatabaseErrorKind;
 
+use crate::domain::group::dwh::{GroupFileStats, GroupUserStats};
 use crate::errors::Error;
 use crate::schema::roles;
 use crate::schema::user_role_members;
@@ -104,3 +105,56 @@ pub fn find_all(conn: &PgConnection) -> Vec<UserDWH> {
         .expect("Cannot load users");
     users
 }
+
+/// Totals of all commits whose email belongs to the user, in any repository.
+pub fn fetch_user_stats(conn: &PgConnection, user_id: i32, start: i64, end: i64) -> Result<Vec<GroupUserStats>, Error> {
+    let stats: Vec<GroupUserStats> = sql_query("
+        SELECT users.username                                 AS name,
+            coalesce(sum(files.time)::bigint, 0)              AS total_time,
+            coalesce(sum(files.lines_added)::bigint, 0)       AS lines_added,
+            coalesce(sum(files.lines_deleted)::bigint, 0)     AS lines_removed,
+            coalesce(count(DISTINCT commits.hash)::bigint, 0) AS commits
+        FROM commits
+            LEFT JOIN files ON files.commit = commits.id
+            INNER JOIN emails ON commits.email = emails.email
+            INNER JOIN users ON emails.user = users.id
+        WHERE emails.user = $1
+            AND commits.timestamp >= $2
+            AND commits.timestamp < $3
+            AND NOT commits.is_superseded
+        GROUP BY users.username;")
+        .bind::<sql_types::Integer, _>(user_id)
+        .bind::<sql_types::BigInt, _>(start)
+        .bind::<sql_types::BigInt, _>(end)
+        .load(conn)?;
+
+    Ok(stats)
+}
+
+/// File totals of the user, paths are prefixed with the repository name as the same path may
+/// exist in several repositories.
+pub fn fetch_user_file_stats(conn: &PgConnection, user_id: i32, start: i64, end: i64) -> Result<Vec<GroupFileStats>, Error> {
+    let stats: Vec<GroupFileStats> = sql_query("
+        SELECT repositories.repo || '/' || regexp_replace(files.path, '^(\\./|/)+', '') AS path,
+            coalesce(sum(files.time)::bigint, 0)              AS total_time,
+            coalesce(sum(files.lines_added)::bigint, 0)       AS lines_added,
+            coalesce(sum(files.lines_deleted)::bigint, 0)     AS lines_removed,
+            coalesce(count(DISTINCT commits.hash)::bigint, 0) AS commits,
+            users.username                                    AS user
+        FROM commits
+            INNER JOIN repositories ON commits.repository_id = repositories.id
+            INNER JOIN files ON files.commit = commits.id
+            INNER JOIN emails ON commits.email = emails.email
+            INNER JOIN users ON emails.user = users.id
+        WHERE emails.user = $1
+            AND commits.timestamp >= $2
+            AND commits.timestamp < $3
+            AND NOT commits.is_superseded
+        GROUP BY 1, users.username;")
+        .bind::<sql_types::Integer, _>(user_id)
+        .bind::<sql_types::BigInt, _>(start)
+        .bind::<sql_types::BigInt, _>(end)
+        .load(conn)?;
+
+    Ok(stats)
+}
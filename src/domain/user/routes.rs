@@ -1,9 +1,15 @@
+use rocket::request::Form;
 use rocket_contrib::json::Json;
 
 use crate::domain::db::Conn;
-use crate::errors::Error;
+use crate::errors::{Error, FieldValidator};
+use crate::domain::group::resource::GroupStatsJson;
+use crate::domain::group::routes::GroupStatsParams;
 use crate::domain::role;
 use crate::domain::role::model::ADMIN;
+use crate::domain::timeline;
+use crate::domain::timeline::resources::{ActivityJson, IntervalJson};
+use crate::domain::timeline::routes::{split_branches, split_languages, TimelineParams};
 use crate::domain::user;
 use crate::domain::user::model::{AuthUser, UserJson};
 use crate::domain::user::resource::UserIdResponse;
@@ -34,3 +40,87 @@ pub fn get_user(auth_user: AuthUser, id: i32, conn: Conn) -> Result<Json<UserJso
             .collect());
     Ok(Json(user))
 }
+
+/// Timeline of the caller's own commits in all repositories, regardless of group access.
+#[openapi]
+#[get("/me/timeline?<params..>")]
+pub fn get_my_timeline(
+    auth_user: AuthUser,
+    params: Form<TimelineParams>,
+    conn: Conn,
+) -> Result<Json<Vec<IntervalJson>>, Error> {
+    let params = params.into_inner();
+    let mut validator = FieldValidator::validate(&params);
+    let start = validator.extract("start", params.start);
+    let end = validator.extract("end", params.end);
+    let interval = validator.extract("interval", params.interval);
+    let timezone = validator.extract("timezone", params.timezone);
+    let cumulative = params.cumulative.unwrap_or(false);
+    let branches = split_branches(params.branch);
+    let languages = split_languages(params.language);
+    let interval = validator.validate_timeline_period(start, end, &interval, params.buckets.as_deref());
+    validator.check()?;
+
+    let timeline = timeline::service::get_user_timeline(
+        &conn,
+        auth_user.user_id,
+        start,
+        end,
+        &timezone,
+        &interval,
+        cumulative,
+        &branches,
+        &languages,
+    )?;
+    Ok(Json(timeline))
+}
+
+/// Activity of the caller's own commits in all repositories, regardless of group access.
+#[openapi]
+#[get("/me/activity?<params..>")]
+pub fn get_my_activity(
+    auth_user: AuthUser,
+    params: Form<TimelineParams>,
+    conn: Conn,
+) -> Result<Json<Vec<ActivityJson>>, Error> {
+    let params = params.into_inner();
+    let mut validator = FieldValidator::validate(&params);
+    let start = validator.extract("start", params.start);
+    let end = validator.extract("end", params.end);
+    let interval = validator.extract("interval", params.interval);
+    let timezone = validator.extract("timezone", params.timezone);
+    let cumulative = params.cumulative.unwrap_or(false);
+    let branches = split_branches(params.branch);
+    let languages = split_languages(params.language);
+    let interval = validator.validate_timeline_period(start, end, &interval, params.buckets.as_deref());
+    validator.check()?;
+
+    let timeline = timeline::service::get_user_activity_timeline(
+        &conn,
+        auth_user.user_id,
+        start,
+        end,
+        &timezone,
+        &interval,
+        cumulative,
+        &branches,
+        &languages,
+    )?;
+    Ok(Json(timeline))
+}
+
+/// Stats of the caller's own commits in all repositories, file paths start with the repository.
+#[openapi]
+#[get("/me/stats?<params..>")]
+pub fn get_my_stats(
+    auth_user: AuthUser,
+    params: Form<GroupStatsParams>,
+    conn: Conn,
+) -> Result<Json<GroupStatsJson>, Error> {
+    let period = params.into_inner();
+    let start = period.start.unwrap_or(0);
+    let end = period.end.unwrap_or(std::i64::MAX);
+    let depth = period.depth.unwrap_or(1);
+    let stats = user::service::get_user_stats(&conn, auth_user.user_id, start, end, depth)?;
+    Ok(Json(stats))
+}
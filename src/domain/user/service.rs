@@ -1,6 +1,9 @@
 use diesel::PgConnection;
+use crate::domain::group::mapper::{map_group_file_stats, map_group_user_stats};
+use crate::domain::group::resource::GroupStatsJson;
 use crate::domain::user::model::UserJson;
 use crate::domain::user;
+use crate::errors::Error;
 
 pub fn find_all(conn: &PgConnection) -> Vec<UserJson> {
     let user_dwhs = user::db::find_all(conn);
@@ -23,4 +26,18 @@ pub fn find_all(conn: &PgConnection) -> Vec<UserJson> {
         }
     }
     user_jsons
-}
\ No newline at end of file
+}
+pub fn get_user_stats(
+    conn: &PgConnection,
+    user_id: i32,
+    start: i64,
+    end: i64,
+    depth: i32,
+) -> Result<GroupStatsJson, Error> {
+    let user_stats = user::db::fetch_user_stats(conn, user_id, start, end)?;
+    let file_stats = user::db::fetch_user_file_stats(conn, user_id, start, end)?;
+    Ok(GroupStatsJson {
+        users: map_group_user_stats(&user_stats),
+        files: map_group_file_stats(&file_stats, depth),
+    })
+}
@@ -1,3 +1,5 @@
+use std::collections::BTreeMap;
+
 use rocket::{Request, response};
 use rocket::http::Status;
 use rocket::response::{Responder, status};
@@ -8,13 +10,18 @@ use rocket_okapi::gen::OpenApiGenerator;
 use okapi::openapi3::Responses;
 use rocket_okapi::OpenApiError;
 
+use crate::common::glob::glob_to_regex;
+use crate::domain::timeline::granularity::{Granularity, MAX_INTERVALS};
+
 #[derive(Debug)]
 pub enum Error {
     ValidationError(ValidationErrors),
+    PayloadValidationError(BTreeMap<String, Vec<String>>),
     DatabaseError(diesel::result::Error),
     AuthorizationError(&'static str),
     HttpError(reqwest::Error),
     BadRequest(&'static str),
+    PayloadTooLarge,
     Custom(&'static str),
 }
 
@@ -36,6 +43,12 @@ impl<'a> Responder<'a> for Error {
                     Json(json!({ "errors": errors })),
                 ).respond_to(req)
             }
+            Error::PayloadValidationError(errors) => {
+                status::Custom(
+                    Status::UnprocessableEntity,
+                    Json(json!({ "errors": errors })),
+                ).respond_to(req)
+            }
             Error::DatabaseError(err) => {
                 error!("{}", err);
                 status::Custom(
@@ -61,6 +74,7 @@ impl<'a> Responder<'a> for Error {
                     Option::from(Json(json!({ "error": msg })))
                 ).respond_to(req)
             }
+            Error::PayloadTooLarge => Err(Status::PayloadTooLarge),
             Error::Custom(msg) => {
                 status::Custom(
                     Status::InternalServerError,
@@ -158,12 +172,7 @@ impl FieldValidator {
         })
     }
 
-    pub fn validate_timeline_period(
-        &mut self,
-        start: i64,
-        end :i64,
-        interval: &str)
-    {
+    pub fn validate_period(&mut self, start: i64, end: i64) {
         if start < 0 || start > end {
             self.errors
                 .add("period", ValidationError::new("Invalid period!"));
@@ -173,11 +182,98 @@ impl FieldValidator {
             self.errors
                 .add("period", ValidationError::new("Too long period!"));
         }
+    }
+
+    /// Validates a group's period, which unlike a queried period may span several years.
+    pub fn validate_group_period(&mut self, start: i64, end: i64) {
+        if start > end {
+            self.errors
+                .add("period", ValidationError::new("Invalid period!"));
+        }
+    }
+
+    /// Validates the period and interval of a timeline, `buckets` are the boundaries of a
+    /// custom interval.
+    pub fn validate_timeline_period(
+        &mut self,
+        start: i64,
+        end :i64,
+        interval: &str,
+        buckets: Option<&str>) -> Granularity
+    {
+        self.validate_period(start, end);
+
+        match Granularity::parse(interval, buckets) {
+            Ok(granularity) => {
+                if granularity.count_intervals(start, end) > MAX_INTERVALS {
+                    self.errors
+                        .add("interval", ValidationError::new("Too many intervals!"));
+                }
+                granularity
+            }
+            Err(message) => {
+                self.errors
+                    .add("interval", ValidationError::new(message));
+                Granularity::Day
+            }
+        }
+    }
 
-        let interval = &*interval.to_lowercase();
-        if !(interval == "hour" || interval == "day" || interval == "week" || interval == "month" || interval == "year")  {
+    pub fn validate_idle_gap(&mut self, idle_gap: i64) {
+        if idle_gap <= 0 || idle_gap > 24 * 60 * 60 {
             self.errors
-                .add("interval", ValidationError::new("Invalid interval!"));
+                .add("idle_gap", ValidationError::new("Idle gap must be between 1 second and 24 hours!"));
+        }
+    }
+
+    /// Validates a path rule glob, returns its regex.
+    pub fn validate_glob(&mut self, glob: &str) -> String {
+        glob_to_regex(glob).unwrap_or_else(|message| {
+            self.errors
+                .add("pattern", ValidationError::new(message));
+            String::new()
+        })
+    }
+}
+
+/// Collects validation errors of nested payloads, keyed by their path in the payload,
+/// e.g. `commits[3].files[900].time_total`.
+#[derive(Default)]
+pub struct PayloadValidator {
+    errors: BTreeMap<String, Vec<String>>,
+}
+
+impl PayloadValidator {
+    pub fn validate<T: Validate>(&mut self, prefix: &str, model: &T) {
+        use validator::ValidationErrorsKind::Field;
+        if let Err(errors) = model.validate() {
+            for (field, field_errors) in errors.into_errors() {
+                if let Field(field_errors) = field_errors {
+                    for field_error in field_errors {
+                        self.add(&format!("{}.{}", prefix, field), &field_error.code);
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn require<T>(&mut self, prefix: &str, field_name: &str, field: &Option<T>) {
+        if field.is_none() {
+            self.add(&format!("{}.{}", prefix, field_name), "can't be blank");
+        }
+    }
+
+    pub fn add(&mut self, path: &str, code: &str) {
+        self.errors.entry(path.to_string())
+            .or_insert_with(Vec::new)
+            .push(code.to_string());
+    }
+
+    pub fn check(self) -> Result<(), Error> {
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::PayloadValidationError(self.errors))
         }
     }
 }
\ No newline at end of file
@@ -41,6 +41,14 @@ fn not_found() -> JsonValue {
     })
 }
 
+#[catch(413)]
+fn payload_too_large() -> JsonValue {
+    json!({
+        "status": "error",
+        "reason": "Payload is too large."
+    })
+}
+
 fn cors_fairing() -> Cors {
     Cors::from_options(&Default::default()).expect("Cors fairing cannot be created")
 }
@@ -81,26 +89,60 @@ pub fn rocket() -> rocket::Rocket {
                 domain::user::routes::get_user_id,
                 domain::user::routes::get_user,
                 domain::user::routes::get_users,
+                domain::user::routes::get_my_timeline,
+                domain::user::routes::get_my_activity,
+                domain::user::routes::get_my_stats,
                 domain::commit::routes::get_commit_hash,
+                domain::commit::routes::get_repository_commits,
+                domain::commit::routes::get_repository_commit,
                 domain::repository::routes::post_repository,
                 domain::repository::routes::put_repository,
+                domain::repository::routes::post_repository_notes,
+                domain::repository::routes::put_repository_notes,
                 domain::repository::routes::delete_repository,
+                domain::repository::routes::get_repositories,
+                domain::repository::routes::get_repository,
+                domain::repository::routes::get_repository_stats,
+                domain::repository::routes::get_repository_timeline,
+                domain::ingest::routes::get_ingest_job,
                 domain::group::routes::post_group_parents,
                 domain::group::routes::post_group_children,
+                domain::group::routes::post_group,
+                domain::group::routes::put_group,
+                domain::group::routes::put_group_metadata,
+                domain::group::routes::delete_group,
+                domain::group::routes::put_group_parents,
+                domain::group::routes::delete_group_parent,
+                domain::group::routes::delete_group_child,
                 domain::group::routes::get_groups,
                 domain::group::routes::get_group_stats,
                 domain::group::routes::get_group_export,
+                domain::branch::routes::get_group_branches,
+                domain::language::routes::get_group_languages,
+                domain::language::routes::get_language_mappings,
+                domain::language::routes::post_language_mappings,
+                domain::language::routes::delete_language_mapping,
                 domain::group::routes::get_groups_with_access,
                 domain::group::routes::get_groups_without_access,
+                domain::group::routes::get_group_audit,
+                domain::group::routes::get_group_tree,
                 domain::timeline::routes::get_timeline,
                 domain::timeline::routes::get_activity_timeline,
                 domain::timeline::routes::get_subdir_level_timeline,
+                domain::timeline::routes::get_app_timeline,
                 domain::timeline::routes::get_timeline_comparison,
+                domain::timeline::routes::get_heatmap,
+                domain::timeline::routes::rebuild_timeline_rollups,
+                domain::session::routes::get_sessions,
                 domain::role::routes::add_role_to_user,
                 domain::role::routes::delete_role_from_user,
                 domain::group_access::routes::post_group_accesses,
                 domain::group_access::routes::delete_group_accesses,
                 domain::group_access::routes::toggle_recursive_access,
+                domain::group_path_rule::routes::get_group_path_rules,
+                domain::group_path_rule::routes::post_group_path_rules,
+                domain::group_path_rule::routes::delete_group_path_rule,
+                domain::group_path_rule::routes::put_group_include_apps,
                 vcs::routes::get_accessible_repositories,
                 vcs::routes::post_start_tracking_repository,
             ],
@@ -108,6 +150,7 @@ pub fn rocket() -> rocket::Rocket {
         .mount("/services/gtm/api/swagger", make_swagger_ui(&get_docs()))
         .attach(domain::db::Conn::fairing())
         .attach(setup::migrate_database())
+        .attach(domain::ingest::worker::start())
         .attach(cors_fairing())
         .attach(security::config::manage())
         .attach(OAuth2::<security::oauth::GitHub>::fairing("github"))
@@ -115,5 +158,5 @@ pub fn rocket() -> rocket::Rocket {
         .attach(OAuth2::<security::oauth::GitLabTalTech>::fairing("gitlab-taltech"))
         .attach(OAuth2::<security::oauth::Microsoft>::fairing("microsoft"))
         .attach(OAuth2::<security::oauth::Bitbucket>::fairing("bitbucket"))
-        .register(catchers![not_found])
+        .register(catchers![not_found, payload_too_large])
 }
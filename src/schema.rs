@@ -1,3 +1,11 @@
+table! {
+    commit_branches (commit, branch) {
+        commit -> Int4,
+        branch -> Text,
+        added_at -> Timestamptz,
+    }
+}
+
 table! {
     commits (id) {
         id -> Int4,
@@ -8,6 +16,9 @@ table! {
         branch -> Text,
         timestamp -> Int8,
         git_user_name -> Text,
+        is_superseded -> Bool,
+        superseded_by -> Nullable<Int4>,
+        note_digest -> Nullable<Text>,
     }
 }
 
@@ -46,11 +57,65 @@ table! {
     }
 }
 
+table! {
+    group_path_rules (id) {
+        id -> Int4,
+        group -> Int4,
+        pattern -> Text,
+        regex -> Text,
+        include -> Bool,
+    }
+}
+
 table! {
     groups (id) {
         id -> Int4,
         name -> Text,
         added_at -> Timestamptz,
+        include_apps -> Bool,
+        display_name -> Nullable<Text>,
+        description -> Nullable<Text>,
+        tags -> Array<Text>,
+        period_start -> Nullable<Timestamptz>,
+        period_end -> Nullable<Timestamptz>,
+    }
+}
+
+table! {
+    ingest_job_batches (job, seq) {
+        job -> Int4,
+        seq -> Int4,
+        payload -> Text,
+    }
+}
+
+table! {
+    ingest_jobs (id) {
+        id -> Int4,
+        sync_client -> Int4,
+        kind -> Text,
+        status -> Text,
+        payload -> Nullable<Text>,
+        commits_total -> Int4,
+        inserted -> Int4,
+        updated -> Int4,
+        skipped -> Int4,
+        repository_id -> Nullable<Int4>,
+        errors -> Nullable<Text>,
+        added_at -> Timestamptz,
+        started_at -> Nullable<Timestamptz>,
+        finished_at -> Nullable<Timestamptz>,
+        commits_processed -> Int4,
+        attempts -> Int4,
+        heartbeat_at -> Nullable<Timestamptz>,
+    }
+}
+
+table! {
+    language_mappings (id) {
+        id -> Int4,
+        extension -> Text,
+        language -> Text,
     }
 }
 
@@ -117,6 +182,19 @@ table! {
     }
 }
 
+table! {
+    timeline_rollups (commit, directory, is_app, hour) {
+        repository_id -> Int4,
+        commit -> Int4,
+        directory -> Text,
+        is_app -> Bool,
+        hour -> Int8,
+        time -> Int8,
+        lines_added -> Int8,
+        lines_deleted -> Int8,
+    }
+}
+
 table! {
     tokens (id) {
         id -> Int4,
@@ -141,28 +219,40 @@ table! {
     }
 }
 
+joinable!(commit_branches -> commits (commit));
 joinable!(commits -> repositories (repository_id));
 joinable!(emails -> users (user));
 joinable!(files -> commits (commit));
 joinable!(group_accesses -> groups (group));
 joinable!(group_accesses -> users (user));
+joinable!(group_path_rules -> groups (group));
+joinable!(ingest_job_batches -> ingest_jobs (job));
+joinable!(ingest_jobs -> repositories (repository_id));
+joinable!(ingest_jobs -> sync_clients (sync_client));
 joinable!(logins -> login_types (login_type));
 joinable!(logins -> users (user));
 joinable!(repositories -> groups (group));
 joinable!(repositories -> sync_clients (sync_client));
 joinable!(sync_clients -> sync_client_type (sync_client_type));
 joinable!(timeline -> files (file));
+joinable!(timeline_rollups -> commits (commit));
+joinable!(timeline_rollups -> repositories (repository_id));
 joinable!(tokens -> users (user));
 joinable!(user_role_members -> roles (role));
 joinable!(user_role_members -> users (user));
 
 allow_tables_to_appear_in_same_query!(
+    commit_branches,
     commits,
     emails,
     files,
     group_accesses,
     group_group_members,
+    group_path_rules,
     groups,
+    ingest_job_batches,
+    ingest_jobs,
+    language_mappings,
     login_types,
     logins,
     repositories,
@@ -170,6 +260,7 @@ allow_tables_to_appear_in_same_query!(
     sync_client_type,
     sync_clients,
     timeline,
+    timeline_rollups,
     tokens,
     user_role_members,
     users,
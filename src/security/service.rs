@@ -91,6 +91,15 @@ pub fn check_group_access(conn: &PgConnection, user: i32, group: &str) -> Result
     Ok(())
 }
 
+/// Like `check_group_access`, but only accepts accesses that cover the whole subtree of the group.
+pub fn check_recursive_group_access(conn: &PgConnection, user: i32, group: &str) -> Result<(), Error> {
+    let accesses = group_access::service::get_recursive_group_access_count(conn, user, group)?;
+    if accesses <= 0 {
+        return Err(Error::AuthorizationError("No recursive group access!"));
+    }
+    Ok(())
+}
+
 fn crypt_password(password: &str) -> String {
     scrypt_simple(password, &ScryptParams::new(10, 8, 1)).expect("hash error")
 }
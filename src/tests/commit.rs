@@ -2,7 +2,7 @@ use rocket::http::{ContentType, Status};
 use rocket::local::Client;
 use serde_json::{json, Value};
 
-use crate::tests::common::{api_key_header, create_sync_client_api_key, random_string, setup, teardown, teardown_api_key};
+use crate::tests::common::{api_key_header, bearer_header, create_sync_client_api_key, get_admin_jwt, random_string, setup, teardown, teardown_api_key};
 
 #[test]
 fn test_get_last_commit_hash() {
@@ -68,4 +68,102 @@ fn test_get_last_commit_hash() {
 
     teardown_api_key(&jwt, &api_key);
     teardown(&jwt);
-}
\ No newline at end of file
+}
+#[test]
+fn test_get_repository_commits() {
+    let jwt = setup();
+    let admin_jwt = get_admin_jwt();
+    let api_key = create_sync_client_api_key(&jwt, 2);
+
+    let client = Client::new(gtm_api::rocket()).unwrap();
+
+    let user = random_string(16);
+    let provider = random_string(10);
+    let repo = random_string(10);
+
+    let timeline = vec![json!({
+        "timestamp": 3600,
+        "time": 100,
+    })];
+
+    let files = vec![json!({
+        "path": "/test/a/b/c",
+        "status": "m",
+        "time_total": 100,
+        "added_lines": 50,
+        "deleted_lines": 10,
+        "timeline": &timeline,
+    })];
+
+    let commits: Vec<Value> = (0..3).map(|i| json!({
+        "author": format!("author-{} <author{}@test.test>", i % 2, i % 2),
+        "branch": "test-branch",
+        "message": format!("fix 100% of bug #{}", i),
+        "hash": format!("hash-{}", i),
+        "time": 1000 + i,
+        "files": &files
+    })).collect();
+
+    let mut response = client.post("/services/gtm/api/repositories")
+        .header(api_key_header(&api_key))
+        .header(ContentType::JSON)
+        .body(json!({
+            "repository": {
+                "user": &user,
+                "provider": &provider,
+                "repo": &repo,
+                "commits": &commits,
+            }
+        }).to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let body_json: Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+    let repository_id = body_json["id"].as_i64().unwrap();
+
+    let mut response = client.get(
+        format!("/services/gtm/api/repositories/{}/commits?page_size=2", repository_id))
+        .header(bearer_header(&admin_jwt))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let body_json: Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+    assert_eq!(body_json["total"], 3);
+    let page: Vec<&str> = body_json["commits"].as_array().unwrap().iter()
+        .map(|c| c["hash"].as_str().unwrap())
+        .collect();
+    assert_eq!(page, vec!["hash-2", "hash-1"]);
+    let file = &body_json["commits"][0]["files"][0];
+    assert_eq!(file["time"], 100);
+    assert_eq!(file["linesAdded"], 50);
+    assert_eq!(file["timeline"][0]["timestamp"], 3600);
+
+    let mut response = client.get(
+        format!("/services/gtm/api/repositories/{}/commits?author=author1&message=100%25", repository_id))
+        .header(bearer_header(&admin_jwt))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let body_json: Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+    assert_eq!(body_json["total"], 1);
+    assert_eq!(body_json["commits"][0]["hash"], "hash-1");
+
+    let mut response = client.get(
+        format!("/services/gtm/api/repositories/{}/commits/hash-0", repository_id))
+        .header(bearer_header(&admin_jwt))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let body_json: Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+    assert_eq!(body_json["gitUserName"], "author-0");
+    assert_eq!(body_json["files"].as_array().unwrap().len(), 1);
+
+    let response = client.get(
+        format!("/services/gtm/api/repositories/{}/commits", repository_id))
+        .header(bearer_header(&jwt))
+        .dispatch();
+    assert_eq!(response.status(), Status::Unauthorized);
+
+    teardown_api_key(&jwt, &api_key);
+    teardown(&jwt);
+}
@@ -203,3 +203,708 @@ fn test_group_access_delete() {
     teardown_api_key(&jwt, &api_key);
     teardown(&jwt);
 }
+
+#[test]
+fn test_group_lifecycle() {
+    let jwt = setup();
+    let admin_jwt = get_admin_jwt();
+    let api_key = create_sync_client_api_key(&jwt, 2);
+
+    let client = Client::new(gtm_api::rocket()).unwrap();
+
+    let user = random_string(16);
+    let provider = random_string(10);
+    let repo = random_string(10);
+    let repo_group = format!("{}-{}-{}", provider, user, repo);
+    let course = random_string(10);
+    let renamed_course = random_string(10);
+    let other_course = random_string(10);
+
+    let response = client.post("/services/gtm/api/groups")
+        .header(bearer_header(&jwt))
+        .header(ContentType::JSON)
+        .body(json!({ "name": &course }).to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::Unauthorized);
+
+    for name in &[&course, &other_course] {
+        let mut response = client.post("/services/gtm/api/groups")
+            .header(bearer_header(&admin_jwt))
+            .header(ContentType::JSON)
+            .body(json!({ "name": name }).to_string())
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.content_type(), Some(ContentType::JSON));
+
+        let body_json: Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+        assert_eq!(body_json["name"].as_str(), Some(name.as_str()));
+    }
+
+    let response = client.post("/services/gtm/api/groups")
+        .header(bearer_header(&admin_jwt))
+        .header(ContentType::JSON)
+        .body(json!({ "name": &course }).to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+
+    let timeline = vec![json!({
+        "timestamp": 100,
+        "time": 100,
+    })];
+
+    let files = vec![json!({
+        "path": "/test/a/b/c",
+        "status": "m",
+        "time_total": 100,
+        "added_lines": 50,
+        "deleted_lines": 10,
+        "timeline": &timeline,
+    })];
+
+    let commits = vec![json!({
+        "author": "test-author <test@test.test>",
+        "branch": "test-branch",
+        "message": "test-message",
+        "hash": random_string(16),
+        "time": 1000,
+        "files": &files
+    })];
+
+    let response = client.post("/services/gtm/api/repositories")
+        .header(api_key_header(&api_key))
+        .header(ContentType::JSON)
+        .body(json!({
+            "repository": {
+                "user": &user,
+                "provider": &provider,
+                "repo": &repo,
+                "commits": &commits,
+            }
+        }).to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let response = client.post(format!("/services/gtm/api/groups/{}/children", course))
+        .header(bearer_header(&admin_jwt))
+        .header(ContentType::JSON)
+        .body(json!({ "children": [&repo_group] }).to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let response = client.put(format!("/services/gtm/api/groups/{}", repo_group))
+        .header(bearer_header(&admin_jwt))
+        .header(ContentType::JSON)
+        .body(json!({ "name": &renamed_course }).to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+
+    let response = client.put(format!("/services/gtm/api/groups/{}", course))
+        .header(bearer_header(&admin_jwt))
+        .header(ContentType::JSON)
+        .body(json!({ "name": &renamed_course }).to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let user_count = |group: &str| {
+        let mut response = client.get(
+            format!("/services/gtm/api/groups/{}/stats?start={}&end={}&depth={}",
+                    group, 0, 60 * 60 * 24 * 7, 2))
+            .header(bearer_header(&admin_jwt))
+            .header(ContentType::JSON)
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+
+        let body_json: Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+        body_json["users"].as_array().unwrap().len()
+    };
+
+    assert_eq!(user_count(&renamed_course), 1);
+
+    let mut response = client.put(format!("/services/gtm/api/groups/{}/parents", repo_group))
+        .header(bearer_header(&admin_jwt))
+        .header(ContentType::JSON)
+        .body(json!({ "parents": [&other_course] }).to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let body_json: Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+    let parents = body_json.as_array().unwrap();
+    assert_eq!(parents.len(), 1);
+    assert_eq!(parents[0]["name"].as_str(), Some(other_course.as_str()));
+
+    assert_eq!(user_count(&renamed_course), 0);
+    assert_eq!(user_count(&other_course), 1);
+
+    for expected in &["1", "0"] {
+        let mut response = client.delete(
+            format!("/services/gtm/api/groups/{}/children/{}", other_course, repo_group))
+            .header(bearer_header(&admin_jwt))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.body_string(), Some(expected.to_string()));
+    }
+
+    assert_eq!(user_count(&other_course), 0);
+
+    for name in &[&renamed_course, &other_course] {
+        let mut response = client.delete(format!("/services/gtm/api/groups/{}", name))
+            .header(bearer_header(&admin_jwt))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.body_string(), Some("1".to_string()));
+    }
+
+    let response = client.delete(format!("/services/gtm/api/groups/{}", repo_group))
+        .header(bearer_header(&admin_jwt))
+        .dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+
+    let mut response = client.delete(format!("/services/gtm/api/groups/{}?force=true", repo_group))
+        .header(bearer_header(&admin_jwt))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.body_string(), Some("1".to_string()));
+
+    let response = client.delete(format!("/services/gtm/api/groups/{}", repo_group))
+        .header(bearer_header(&admin_jwt))
+        .dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+
+    teardown_api_key(&jwt, &api_key);
+    teardown(&jwt);
+}
+
+#[test]
+fn test_group_relation_cycles() {
+    let jwt = setup();
+    let admin_jwt = get_admin_jwt();
+
+    let client = Client::new(gtm_api::rocket()).unwrap();
+
+    let names: Vec<String> = (0..4).map(|_| random_string(10)).collect();
+    for name in &names {
+        let response = client.post("/services/gtm/api/groups")
+            .header(bearer_header(&admin_jwt))
+            .header(ContentType::JSON)
+            .body(json!({ "name": name }).to_string())
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+    }
+
+    for (child, parent) in &[(&names[1], &names[0]), (&names[2], &names[1])] {
+        let response = client.post(format!("/services/gtm/api/groups/{}/parents", child))
+            .header(bearer_header(&admin_jwt))
+            .header(ContentType::JSON)
+            .body(json!({ "parents": [parent] }).to_string())
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+    }
+
+    let response = client.post(format!("/services/gtm/api/groups/{}/parents", names[0]))
+        .header(bearer_header(&admin_jwt))
+        .header(ContentType::JSON)
+        .body(json!({ "parents": [&names[2]] }).to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+
+    let response = client.post(format!("/services/gtm/api/groups/{}/children", names[0]))
+        .header(bearer_header(&admin_jwt))
+        .header(ContentType::JSON)
+        .body(json!({ "children": [&names[0]] }).to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+
+    let response = client.put(format!("/services/gtm/api/groups/{}/parents", names[0]))
+        .header(bearer_header(&admin_jwt))
+        .header(ContentType::JSON)
+        .body(json!({ "parents": [&names[1]] }).to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+
+    let response = client.get("/services/gtm/api/groups/audit")
+        .header(bearer_header(&jwt))
+        .dispatch();
+    assert_eq!(response.status(), Status::Unauthorized);
+
+    let mut response = client.get("/services/gtm/api/groups/audit")
+        .header(bearer_header(&admin_jwt))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.content_type(), Some(ContentType::JSON));
+
+    let body_json: Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+    let cycles = body_json["cycles"].as_array().unwrap();
+    let orphans: Vec<&str> = body_json["orphans"].as_array().unwrap()
+        .iter()
+        .map(|g| g["name"].as_str().unwrap())
+        .collect();
+
+    assert!(cycles.iter().all(|c| c.as_array().unwrap().iter()
+        .all(|name| !names.iter().any(|n| Some(n.as_str()) == name.as_str()))));
+    assert!(orphans.contains(&names[3].as_str()));
+    assert!(!orphans.contains(&names[0].as_str()));
+
+    for name in &names {
+        let response = client.delete(format!("/services/gtm/api/groups/{}", name))
+            .header(bearer_header(&admin_jwt))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+    }
+
+    teardown(&jwt);
+}
+
+#[test]
+fn test_group_relation_authorization() {
+    let jwt = setup();
+    let admin_jwt = get_admin_jwt();
+
+    let client = Client::new(gtm_api::rocket()).unwrap();
+
+    let other_course = random_string(10);
+    let course = random_string(10);
+    let sub_course = random_string(10);
+    let missing = random_string(10);
+
+    let mut response = client.post("/services/gtm/api/groups")
+        .header(bearer_header(&admin_jwt))
+        .header(ContentType::JSON)
+        .body(json!({ "name": &other_course }).to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let other_course_id = serde_json::from_str::<Value>(&response.body_string().unwrap()).unwrap()["id"]
+        .as_i64()
+        .unwrap();
+
+    let response = client.post(format!("/services/gtm/api/groups/{}/children", other_course))
+        .header(ContentType::JSON)
+        .body(json!({ "children": [&missing] }).to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::Unauthorized);
+
+    let response = client.post(format!("/services/gtm/api/groups/{}/children", other_course))
+        .header(bearer_header(&jwt))
+        .header(ContentType::JSON)
+        .body(json!({ "children": [&missing] }).to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::Unauthorized);
+
+    let mut response = client.get("/services/gtm/api/user")
+        .header(bearer_header(&jwt))
+        .header(ContentType::JSON)
+        .dispatch();
+    let user_id = serde_json::from_str::<Value>(&response.body_string().unwrap()).unwrap()["user_id"]
+        .as_i64()
+        .unwrap();
+
+    let response = client.post("/services/gtm/api/roles")
+        .header(bearer_header(&admin_jwt))
+        .header(ContentType::JSON)
+        .body(json!({ "user": user_id, "role": 2 }).to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let mut response = client.get("/services/gtm/api/auth/token")
+        .header(bearer_header(&jwt))
+        .header(ContentType::JSON)
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let lecturer_jwt = serde_json::from_str::<Value>(&response.body_string().unwrap()).unwrap()["jwt"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    for name in &[&course, &sub_course] {
+        let response = client.post("/services/gtm/api/groups")
+            .header(bearer_header(&lecturer_jwt))
+            .header(ContentType::JSON)
+            .body(json!({ "name": name }).to_string())
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+    }
+
+    let response = client.post(format!("/services/gtm/api/groups/{}/children", course))
+        .header(bearer_header(&lecturer_jwt))
+        .header(ContentType::JSON)
+        .body(json!({ "children": [&sub_course] }).to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let response = client.post(format!("/services/gtm/api/groups/{}/children", course))
+        .header(bearer_header(&lecturer_jwt))
+        .header(ContentType::JSON)
+        .body(json!({ "children": [&other_course] }).to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::Unauthorized);
+
+    let response = client.post(format!("/services/gtm/api/groups/{}/parents", sub_course))
+        .header(bearer_header(&lecturer_jwt))
+        .header(ContentType::JSON)
+        .body(json!({ "parents": [&other_course] }).to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::Unauthorized);
+
+    let response = client.put(format!("/services/gtm/api/groups/{}/parents", other_course))
+        .header(bearer_header(&lecturer_jwt))
+        .header(ContentType::JSON)
+        .body(json!({ "parents": [&course] }).to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::Unauthorized);
+
+    let response = client.post(format!("/services/gtm/api/groups/{}/children", course))
+        .header(bearer_header(&lecturer_jwt))
+        .header(ContentType::JSON)
+        .body(json!({ "children": [&missing] }).to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+
+    let mut response = client.get("/services/gtm/api/groups")
+        .header(bearer_header(&admin_jwt))
+        .header(ContentType::JSON)
+        .dispatch();
+    let groups: Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+    assert!(!groups.as_array().unwrap().iter().any(|g| g["name"].as_str() == Some(missing.as_str())));
+
+    let response = client.post("/services/gtm/api/group_accesses")
+        .header(bearer_header(&admin_jwt))
+        .header(ContentType::JSON)
+        .body(json!([
+            {
+                "user": user_id,
+                "group": other_course_id,
+                "access_level_recursive": false,
+            }
+        ]).to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let response = client.post(format!("/services/gtm/api/groups/{}/children", course))
+        .header(bearer_header(&lecturer_jwt))
+        .header(ContentType::JSON)
+        .body(json!({ "children": [&other_course] }).to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::Unauthorized);
+
+    for name in &[&course, &sub_course, &other_course] {
+        let response = client.delete(format!("/services/gtm/api/groups/{}", name))
+            .header(bearer_header(&admin_jwt))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+    }
+
+    teardown(&jwt);
+}
+
+#[test]
+fn test_group_metadata() {
+    let jwt = setup();
+    let admin_jwt = get_admin_jwt();
+    let api_key = create_sync_client_api_key(&jwt, 2);
+
+    let client = Client::new(gtm_api::rocket()).unwrap();
+
+    let user = random_string(16);
+    let provider = random_string(10);
+    let repo = random_string(10);
+    let course = random_string(10);
+    let tag = random_string(10).to_lowercase();
+
+    let response = client.post("/services/gtm/api/groups")
+        .header(bearer_header(&admin_jwt))
+        .header(ContentType::JSON)
+        .body(json!({ "name": &course }).to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let timeline = vec![json!({
+        "timestamp": 100,
+        "time": 100,
+    })];
+
+    let files = vec![json!({
+        "path": "/test/a/b/c",
+        "status": "m",
+        "time_total": 100,
+        "added_lines": 50,
+        "deleted_lines": 10,
+        "timeline": &timeline,
+    })];
+
+    let commits = vec![json!({
+        "author": "test-author <test@test.test>",
+        "branch": "test-branch",
+        "message": "test-message",
+        "hash": random_string(16),
+        "time": 1000,
+        "files": &files
+    })];
+
+    let response = client.post("/services/gtm/api/repositories")
+        .header(api_key_header(&api_key))
+        .header(ContentType::JSON)
+        .body(json!({
+            "repository": {
+                "user": &user,
+                "provider": &provider,
+                "repo": &repo,
+                "commits": &commits,
+            }
+        }).to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let response = client.post(format!("/services/gtm/api/groups/{}/children", course))
+        .header(bearer_header(&admin_jwt))
+        .header(ContentType::JSON)
+        .body(json!({ "children": [format!("{}-{}-{}", provider, user, repo)] }).to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let response = client.put(format!("/services/gtm/api/groups/{}/metadata", course))
+        .header(bearer_header(&jwt))
+        .header(ContentType::JSON)
+        .body(json!({ "displayName": "Software Engineering" }).to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::Unauthorized);
+
+    let response = client.put(format!("/services/gtm/api/groups/{}/metadata", course))
+        .header(bearer_header(&admin_jwt))
+        .header(ContentType::JSON)
+        .body(json!({ "periodStart": 2000, "periodEnd": 1000 }).to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::UnprocessableEntity);
+
+    let mut response = client.put(format!("/services/gtm/api/groups/{}/metadata", course))
+        .header(bearer_header(&admin_jwt))
+        .header(ContentType::JSON)
+        .body(json!({
+            "displayName": " Software Engineering ",
+            "description": "Spring course",
+            "tags": [format!(" {} ", tag.to_uppercase()), "spring", &tag, ""],
+            "periodStart": 0,
+            "periodEnd": 60 * 60 * 24 * 7,
+        }).to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.content_type(), Some(ContentType::JSON));
+
+    let body_json: Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+    assert_eq!(body_json["displayName"].as_str(), Some("Software Engineering"));
+    assert_eq!(body_json["description"].as_str(), Some("Spring course"));
+    let mut tags = vec![tag.clone(), "spring".to_string()];
+    tags.sort();
+    assert_eq!(body_json["tags"], json!(tags));
+    assert_eq!(body_json["periodEnd"].as_i64(), Some(60 * 60 * 24 * 7));
+
+    let group_names = |tag: &str| {
+        let mut response = client.get(format!("/services/gtm/api/groups?tag={}", tag))
+            .header(bearer_header(&admin_jwt))
+            .header(ContentType::JSON)
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+
+        let body_json: Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+        body_json.as_array().unwrap()
+            .iter()
+            .map(|g| g["name"].as_str().unwrap().to_string())
+            .collect::<Vec<String>>()
+    };
+
+    assert_eq!(group_names(&tag.to_uppercase()), vec![course.clone()]);
+    assert!(!group_names(&random_string(10)).contains(&course));
+
+    let user_count = || {
+        let mut response = client.get(format!("/services/gtm/api/groups/{}/stats", course))
+            .header(bearer_header(&admin_jwt))
+            .header(ContentType::JSON)
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+
+        let body_json: Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+        body_json["users"].as_array().unwrap().len()
+    };
+
+    assert_eq!(user_count(), 1);
+
+    let response = client.get(format!("/services/gtm/api/{}/timeline?interval=day&timezone=UTC", course))
+        .header(bearer_header(&admin_jwt))
+        .header(ContentType::JSON)
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    // Stats default to the group's period, the commit at 1000 is only counted when inside it
+    let set_period = |start: i64, end: i64| {
+        let response = client.put(format!("/services/gtm/api/groups/{}/metadata", course))
+            .header(bearer_header(&admin_jwt))
+            .header(ContentType::JSON)
+            .body(json!({ "periodStart": start, "periodEnd": end }).to_string())
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+    };
+
+    set_period(2000, 3000);
+    assert_eq!(user_count(), 0);
+
+    set_period(1000, 2000);
+    assert_eq!(user_count(), 1);
+
+    // A period of a multi-year project is accepted
+    set_period(0, 60 * 60 * 24 * 365 * 3);
+    assert_eq!(user_count(), 1);
+
+    // An empty object clears all metadata
+    let mut response = client.put(format!("/services/gtm/api/groups/{}/metadata", course))
+        .header(bearer_header(&admin_jwt))
+        .header(ContentType::JSON)
+        .body(json!({}).to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let body_json: Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+    assert!(body_json["displayName"].is_null());
+    assert!(body_json["periodStart"].is_null());
+    assert_eq!(body_json["tags"], json!([]));
+    assert_eq!(user_count(), 1);
+
+    let response = client.delete(format!("/services/gtm/api/groups/{}", course))
+        .header(bearer_header(&admin_jwt))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    teardown_api_key(&jwt, &api_key);
+    teardown(&jwt);
+}
+
+#[test]
+fn test_group_tree() {
+    let jwt = setup();
+    let admin_jwt = get_admin_jwt();
+    let api_key = create_sync_client_api_key(&jwt, 2);
+
+    let client = Client::new(gtm_api::rocket()).unwrap();
+
+    let user = random_string(16);
+    let provider = random_string(10);
+    let repo = random_string(10);
+    let repo_group = format!("{}-{}-{}", provider, user, repo);
+    let course = random_string(10);
+    let sub_course = random_string(10);
+    let other_course = random_string(10);
+
+    let mut response = client.post("/services/gtm/api/groups")
+        .header(bearer_header(&admin_jwt))
+        .header(ContentType::JSON)
+        .body(json!({ "name": &course }).to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let course_id = serde_json::from_str::<Value>(&response.body_string().unwrap()).unwrap()["id"]
+        .as_i64()
+        .unwrap();
+
+    let response = client.post("/services/gtm/api/groups")
+        .header(bearer_header(&admin_jwt))
+        .header(ContentType::JSON)
+        .body(json!({ "name": &other_course }).to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let response = client.post("/services/gtm/api/repositories")
+        .header(api_key_header(&api_key))
+        .header(ContentType::JSON)
+        .body(json!({
+            "repository": {
+                "user": &user,
+                "provider": &provider,
+                "repo": &repo,
+                "commits": [],
+            }
+        }).to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    for (parent, child) in &[(&course, &sub_course), (&other_course, &sub_course), (&sub_course, &repo_group)] {
+        let response = client.post(format!("/services/gtm/api/groups/{}/children", parent))
+            .header(bearer_header(&admin_jwt))
+            .header(ContentType::JSON)
+            .body(json!({ "children": [child] }).to_string())
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+    }
+
+    let tree = |token: &str| {
+        let mut response = client.get("/services/gtm/api/groups/tree")
+            .header(bearer_header(token))
+            .header(ContentType::JSON)
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.content_type(), Some(ContentType::JSON));
+
+        let body_json: Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+        body_json.as_array().unwrap().clone()
+    };
+
+    let admin_tree = tree(&admin_jwt);
+    let root = admin_tree.iter().find(|g| g["name"].as_str() == Some(course.as_str()));
+    assert!(root.is_some());
+    let root = root.unwrap();
+    assert_eq!(root["access"].as_str(), Some("admin"));
+    assert_eq!(root["repositories"].as_i64(), Some(1));
+    assert_eq!(root["children"][0]["name"].as_str(), Some(sub_course.as_str()));
+    assert!(!admin_tree.iter().any(|g| g["name"].as_str() == Some(sub_course.as_str())));
+
+    // A group with several parents appears with its whole subtree below each of them
+    let other_root = admin_tree.iter().find(|g| g["name"].as_str() == Some(other_course.as_str())).unwrap();
+    assert_eq!(other_root["repositories"].as_i64(), Some(1));
+    assert_eq!(other_root["children"], root["children"]);
+    assert_eq!(other_root["children"][0]["children"][0]["name"].as_str(), Some(repo_group.as_str()));
+
+    assert!(!tree(&jwt).iter().any(|g| g["name"].as_str() == Some(course.as_str())));
+
+    let mut response = client.get("/services/gtm/api/user")
+        .header(bearer_header(&jwt))
+        .header(ContentType::JSON)
+        .dispatch();
+    let user_id = serde_json::from_str::<Value>(&response.body_string().unwrap()).unwrap()["user_id"]
+        .as_i64()
+        .unwrap();
+
+    let response = client.post("/services/gtm/api/group_accesses")
+        .header(bearer_header(&admin_jwt))
+        .header(ContentType::JSON)
+        .body(json!([
+            {
+                "user": user_id,
+                "group": course_id,
+                "access_level_recursive": true,
+            }
+        ]).to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let user_tree = tree(&jwt);
+    assert_eq!(user_tree.len(), 1);
+    let root = &user_tree[0];
+    assert_eq!(root["name"].as_str(), Some(course.as_str()));
+    assert_eq!(root["access"].as_str(), Some("recursive"));
+    assert_eq!(root["repositories"].as_i64(), Some(1));
+
+    let sub = &root["children"][0];
+    assert_eq!(sub["name"].as_str(), Some(sub_course.as_str()));
+    assert_eq!(sub["access"].as_str(), Some("inherited"));
+    assert_eq!(sub["children"][0]["name"].as_str(), Some(repo_group.as_str()));
+    assert_eq!(sub["children"][0]["repositories"].as_i64(), Some(1));
+
+    for name in &[&course, &other_course, &sub_course] {
+        let response = client.delete(format!("/services/gtm/api/groups/{}", name))
+            .header(bearer_header(&admin_jwt))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+    }
+    let response = client.delete(format!("/services/gtm/api/groups/{}?force=true", repo_group))
+        .header(bearer_header(&admin_jwt))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    teardown_api_key(&jwt, &api_key);
+    teardown(&jwt);
+}
@@ -0,0 +1,177 @@
+use rocket::http::{ContentType, Status};
+use rocket::local::Client;
+use serde_json::{json, Value};
+
+use crate::tests::common::{api_key_header, bearer_header, create_sync_client_api_key, get_admin_jwt, random_string, setup, teardown, teardown_api_key};
+
+fn get_timeline_time(client: &Client, jwt: &str, group: &str) -> f64 {
+    let mut response = client.get(
+        format!("/services/gtm/api/{}/timeline?start={}&end={}&interval={}&timezone={}",
+                group, 0, 60 * 60 * 24 * 7, "day", "UTC"))
+        .header(bearer_header(jwt))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let body_json: Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+    body_json.as_array().unwrap().iter()
+        .map(|i| i["time"].as_f64().unwrap())
+        .sum()
+}
+
+fn get_subdir_paths(client: &Client, jwt: &str, group: &str) -> Vec<String> {
+    let mut response = client.get(
+        format!("/services/gtm/api/{}/subdirs-timeline?start={}&end={}&interval={}&timezone={}&depth={}&time_threshold={}&lines_threshold={}",
+                group, 0, 60 * 60 * 24 * 7, "day", "UTC", 1, 0, 0))
+        .header(bearer_header(jwt))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let body_json: Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+    body_json["paths"].as_array().unwrap().iter()
+        .map(|p| p.as_str().unwrap().to_string())
+        .collect()
+}
+
+fn get_stats_paths(client: &Client, jwt: &str, group: &str) -> Vec<String> {
+    let mut response = client.get(
+        format!("/services/gtm/api/groups/{}/stats?start={}&end={}&depth={}",
+                group, 0, 60 * 60 * 24 * 7, 1))
+        .header(bearer_header(jwt))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let body_json: Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+    let mut paths: Vec<String> = body_json["files"].as_array().unwrap().iter()
+        .map(|f| f["path"].as_str().unwrap().to_string())
+        .collect();
+    paths.sort();
+    paths
+}
+
+#[test]
+fn test_group_path_rules() {
+    let jwt = setup();
+    let admin_jwt = get_admin_jwt();
+    let api_key = create_sync_client_api_key(&jwt, 2);
+
+    let client = Client::new(gtm_api::rocket()).unwrap();
+
+    let user = random_string(16);
+    let provider = random_string(10);
+    let repo = random_string(10);
+    let group = format!("{}-{}-{}", provider, user, repo);
+
+    let files: Vec<Value> = vec![
+        ("src/main.rs", 1800),
+        ("package-lock.json", 600),
+        ("target/debug/build.log", 300),
+        ("./vendor/lib/index.js", 300),
+        ("terminal.app", 900),
+    ].into_iter()
+        .map(|(path, time)| json!({
+            "path": path,
+            "status": "m",
+            "time_total": time,
+            "added_lines": 10,
+            "deleted_lines": 2,
+            "timeline": vec![json!({ "timestamp": 3600, "time": time })],
+        }))
+        .collect();
+
+    let response = client.post("/services/gtm/api/repositories")
+        .header(api_key_header(&api_key))
+        .header(ContentType::JSON)
+        .body(json!({
+            "repository": {
+                "user": &user,
+                "provider": &provider,
+                "repo": &repo,
+                "commits": vec![json!({
+                    "author": "test-author <test@test.test>",
+                    "branch": "test-branch",
+                    "message": "test-message",
+                    "hash": random_string(16),
+                    "time": 1000,
+                    "files": &files,
+                })],
+            }
+        }).to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    assert_eq!(get_timeline_time(&client, &admin_jwt, &group), 1.1);
+    // Without rules the subdirectory timeline is served from rollups, apps included
+    assert_eq!(get_subdir_paths(&client, &admin_jwt, &group),
+               vec!["/package-lock.json", "/src", "/target", "/terminal.app", "/vendor", "other"]);
+
+    let rules = json!([
+        { "pattern": "package-lock.json", "include": false },
+        { "pattern": "target/", "include": false },
+        { "pattern": "/vendor/**", "include": false },
+    ]);
+    let response = client.post(format!("/services/gtm/api/groups/{}/path-rules", group))
+        .header(bearer_header(&jwt))
+        .header(ContentType::JSON)
+        .body(rules.to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::Unauthorized);
+
+    let response = client.post(format!("/services/gtm/api/groups/{}/path-rules", group))
+        .header(bearer_header(&admin_jwt))
+        .header(ContentType::JSON)
+        .body(json!([{ "pattern": "/", "include": false }]).to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::UnprocessableEntity);
+
+    let mut response = client.post(format!("/services/gtm/api/groups/{}/path-rules", group))
+        .header(bearer_header(&admin_jwt))
+        .header(ContentType::JSON)
+        .body(rules.to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let body_json: Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+    assert_eq!(body_json["includeApps"], true);
+    assert_eq!(body_json["rules"].as_array().unwrap().len(), 3);
+
+    // Source and app time remain
+    assert_eq!(get_timeline_time(&client, &admin_jwt, &group), 0.8);
+    assert_eq!(get_stats_paths(&client, &admin_jwt, &group), vec!["/src", "/terminal.app"]);
+    assert_eq!(get_subdir_paths(&client, &admin_jwt, &group), vec!["/src", "/terminal.app", "other"]);
+
+    let response = client.put(format!("/services/gtm/api/groups/{}/path-rules/apps", group))
+        .header(bearer_header(&admin_jwt))
+        .header(ContentType::JSON)
+        .body(json!({ "include_apps": false }).to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    assert_eq!(get_timeline_time(&client, &admin_jwt, &group), 0.5);
+    assert_eq!(get_stats_paths(&client, &admin_jwt, &group), vec!["/src"]);
+    assert_eq!(get_subdir_paths(&client, &admin_jwt, &group), vec!["/src", "other"]);
+
+    // An include rule matching nothing keeps nothing
+    let mut response = client.post(format!("/services/gtm/api/groups/{}/path-rules", group))
+        .header(bearer_header(&admin_jwt))
+        .header(ContentType::JSON)
+        .body(json!([{ "pattern": "*.md", "include": true }]).to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let body_json: Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+    assert_eq!(body_json["includeApps"], false);
+    let include_rule = body_json["rules"].as_array().unwrap().iter()
+        .find(|r| r["include"] == true)
+        .unwrap()["id"].as_i64().unwrap();
+
+    assert_eq!(get_timeline_time(&client, &admin_jwt, &group), 0.0);
+
+    let mut response = client.delete(format!("/services/gtm/api/groups/{}/path-rules/{}", group, include_rule))
+        .header(bearer_header(&admin_jwt))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.body_string().unwrap(), "1");
+
+    assert_eq!(get_timeline_time(&client, &admin_jwt, &group), 0.5);
+
+    teardown_api_key(&jwt, &api_key);
+    teardown(&jwt);
+}
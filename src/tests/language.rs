@@ -0,0 +1,127 @@
+use rocket::http::{ContentType, Status};
+use rocket::local::Client;
+use serde_json::{json, Value};
+
+use crate::tests::common::{api_key_header, bearer_header, create_sync_client_api_key, get_admin_jwt, random_string, setup, teardown, teardown_api_key};
+
+#[test]
+fn test_group_languages() {
+    let jwt = setup();
+    let admin_jwt = get_admin_jwt();
+    let api_key = create_sync_client_api_key(&jwt, 2);
+
+    let client = Client::new(gtm_api::rocket()).unwrap();
+
+    let user = random_string(16);
+    let provider = random_string(10);
+    let repo = random_string(10);
+    let extension = random_string(12).to_lowercase();
+
+    let response = client.post("/services/gtm/api/languages")
+        .header(bearer_header(&jwt))
+        .header(ContentType::JSON)
+        .body(json!([{ "extension": &extension, "language": "Testlang" }]).to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::Unauthorized);
+
+    let mut response = client.post("/services/gtm/api/languages")
+        .header(bearer_header(&admin_jwt))
+        .header(ContentType::JSON)
+        .body(json!([{ "extension": format!(".{}", extension.to_uppercase()), "language": "Testlang" }]).to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let body_json: Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+    assert!(body_json.as_array().unwrap().iter()
+        .any(|m| m["extension"] == json!(extension) && m["language"] == "Testlang"));
+
+    let custom_path = format!("src/data.{}", extension);
+    let files = vec![
+        ("src/main.rs", 1800),
+        ("tests/api.rs", 900),
+        ("src/util_test.rs", 300),
+        ("README.md", 600),
+        (&*custom_path, 1200),
+        ("Makefile", 60),
+    ];
+    let files: Vec<Value> = files.into_iter()
+        .map(|(path, time)| json!({
+            "path": path,
+            "status": "m",
+            "time_total": time,
+            "added_lines": 10,
+            "deleted_lines": 2,
+            "timeline": vec![json!({ "timestamp": 3600, "time": time })],
+        }))
+        .collect();
+
+    let response = client.post("/services/gtm/api/repositories")
+        .header(api_key_header(&api_key))
+        .header(ContentType::JSON)
+        .body(json!({
+            "repository": {
+                "user": &user,
+                "provider": &provider,
+                "repo": &repo,
+                "commits": vec![json!({
+                    "author": "test-author <test@test.test>",
+                    "branch": "test-branch",
+                    "message": "test-message",
+                    "hash": random_string(16),
+                    "time": 1000,
+                    "files": &files,
+                })],
+            }
+        }).to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let mut response = client.get(
+        format!("/services/gtm/api/groups/{}-{}-{}/languages?start={}&end={}",
+                provider, user, repo, 0, 60 * 60 * 24 * 7))
+        .header(bearer_header(&admin_jwt))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.content_type(), Some(ContentType::JSON));
+
+    let body_json: Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+    let mut stats: Vec<(String, bool, f64, i64)> = body_json.as_array().unwrap().iter()
+        .map(|s| (
+            s["language"].as_str().unwrap().to_string(),
+            s["is_test"].as_bool().unwrap(),
+            s["total_time"].as_f64().unwrap(),
+            s["files"].as_i64().unwrap(),
+        ))
+        .collect();
+    stats.sort_by(|a, b| a.0.cmp(&b.0).then(a.1.cmp(&b.1)));
+    assert_eq!(stats, vec![
+        ("Markdown".to_string(), false, 0.2, 1),
+        ("Other".to_string(), false, 0.0, 1),
+        ("Rust".to_string(), false, 0.5, 1),
+        ("Rust".to_string(), true, 0.3, 2),
+        ("Testlang".to_string(), false, 0.3, 1),
+    ]);
+
+    for (language, hours) in vec![("rust", 0.8), ("Rust,markdown", 1.0), ("testlang", 0.3), ("cobol", 0.0)] {
+        let mut response = client.get(
+            format!("/services/gtm/api/{}-{}-{}/timeline?start={}&end={}&interval={}&timezone={}&language={}",
+                    provider, user, repo, 0, 60 * 60 * 24 * 7, "day", "UTC", language))
+            .header(bearer_header(&admin_jwt))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+
+        let body_json: Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+        let total: f64 = body_json.as_array().unwrap().iter()
+            .map(|i| i["time"].as_f64().unwrap())
+            .sum();
+        assert!((total - hours).abs() < 0.01, "language {}: {}", language, total);
+    }
+
+    let mut response = client.delete(format!("/services/gtm/api/languages/{}", extension))
+        .header(bearer_header(&admin_jwt))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.body_string().unwrap(), "1");
+
+    teardown_api_key(&jwt, &api_key);
+    teardown(&jwt);
+}
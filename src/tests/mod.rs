@@ -6,5 +6,8 @@ pub mod commit;
 pub mod timeline;
 pub mod group_access;
 pub mod group;
+pub mod session;
+pub mod language;
+pub mod group_path_rule;
 
 mod common;
\ No newline at end of file
@@ -1,9 +1,23 @@
-use rocket::http::{ContentType, Status};
+use std::io::Write;
+
+use flate2::Compression;
+use flate2::write::GzEncoder;
+
+use rocket::http::{ContentType, Header, Status};
 use rocket::local::Client;
 use serde_json::{json, Value};
 
 use crate::tests::common::{api_key_header, create_sync_client_api_key, random_string, setup, teardown, teardown_api_key, bearer_header, get_admin_jwt};
 
+fn get_commit(client: &Client, jwt: &str, repository_id: i64, hash: &str) -> Value {
+    let mut response = client.get(
+        format!("/services/gtm/api/repositories/{}/commits/{}", repository_id, hash))
+        .header(bearer_header(jwt))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    serde_json::from_str(&response.body_string().unwrap()).unwrap()
+}
+
 #[test]
 fn test_create_repository() {
     let jwt = setup();
@@ -250,4 +264,991 @@ fn test_delete_repository_no_access() {
 
     teardown_api_key(&jwt, &api_key);
     teardown(&jwt);
-}
\ No newline at end of file
+}
+#[test]
+fn test_update_repository_skips_known_commits() {
+    let jwt = setup();
+    let admin_jwt = get_admin_jwt();
+    let api_key = create_sync_client_api_key(&jwt, 2);
+
+    let client = Client::new(gtm_api::rocket()).unwrap();
+
+    let user = random_string(16);
+    let provider = random_string(10);
+    let repo = random_string(10);
+    let hash = random_string(16);
+
+    let timeline = vec![json!({
+        "timestamp": 123456789,
+        "time": 123,
+    })];
+
+    let files = vec![json!({
+        "path": "/test/a/b/c",
+        "status": "m",
+        "time_total": 123,
+        "added_lines": 123,
+        "deleted_lines": 12,
+        "timeline": &timeline,
+    })];
+
+    let commits = vec![json!({
+        "author": "test-author <test@test.test>",
+        "branch": "test-branch",
+        "message": "test-message",
+        "hash": &hash,
+        "time": 123456789,
+        "files": &files
+    })];
+
+    let mut response = client.post("/services/gtm/api/repositories")
+        .header(api_key_header(&api_key))
+        .header(ContentType::JSON)
+        .body(json!({
+            "repository": {
+                "user": &user,
+                "provider": &provider,
+                "repo": &repo,
+                "commits": &commits,
+            }
+        }).to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.content_type(), Some(ContentType::JSON));
+
+    let body_json: Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+    assert_eq!(body_json["ingestion"]["inserted"], json!([&hash]));
+    let repository_id = body_json["id"].as_i64().unwrap();
+
+    let mut response = client.put("/services/gtm/api/repositories")
+        .header(api_key_header(&api_key))
+        .header(ContentType::JSON)
+        .body(json!({
+            "repository": {
+                "user": &user,
+                "provider": &provider,
+                "repo": &repo,
+                "commits": &commits,
+            }
+        }).to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.content_type(), Some(ContentType::JSON));
+
+    let body_json: Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+    assert_eq!(body_json["ingestion"]["inserted"], json!([]));
+    assert_eq!(body_json["ingestion"]["skipped"], json!([&hash]));
+    assert_eq!(body_json["commitsTotal"], 1);
+
+    let files = vec![json!({
+        "path": "/test/a/b/c",
+        "status": "m",
+        "time_total": 246,
+        "added_lines": 123,
+        "deleted_lines": 12,
+        "timeline": &timeline,
+    })];
+
+    let commits = vec![json!({
+        "author": "test-author <test@test.test>",
+        "branch": "test-branch",
+        "message": "test-message",
+        "hash": &hash,
+        "time": 123456789,
+        "files": &files
+    })];
+
+    let mut response = client.put("/services/gtm/api/repositories")
+        .header(api_key_header(&api_key))
+        .header(ContentType::JSON)
+        .body(json!({
+            "repository": {
+                "user": &user,
+                "provider": &provider,
+                "repo": &repo,
+                "commits": &commits,
+            }
+        }).to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.content_type(), Some(ContentType::JSON));
+
+    let body_json: Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+    assert_eq!(body_json["ingestion"]["updated"], json!([&hash]));
+    let commit = get_commit(&client, &admin_jwt, repository_id, &hash);
+    assert_eq!(commit["files"][0]["time"], json!(246));
+
+    // Same file count and total time, but a different path and line counts
+    let files = vec![json!({
+        "path": "/test/a/b/d",
+        "status": "m",
+        "time_total": 246,
+        "added_lines": 100,
+        "deleted_lines": 35,
+        "timeline": &timeline,
+    })];
+
+    let commits = vec![json!({
+        "author": "test-author <test@test.test>",
+        "branch": "test-branch",
+        "message": "test-message",
+        "hash": &hash,
+        "time": 123456789,
+        "files": &files
+    })];
+
+    let mut response = client.put("/services/gtm/api/repositories")
+        .header(api_key_header(&api_key))
+        .header(ContentType::JSON)
+        .body(json!({
+            "repository": {
+                "user": &user,
+                "provider": &provider,
+                "repo": &repo,
+                "commits": &commits,
+            }
+        }).to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.content_type(), Some(ContentType::JSON));
+
+    let body_json: Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+    assert_eq!(body_json["ingestion"]["updated"], json!([&hash]));
+    let commit = get_commit(&client, &admin_jwt, repository_id, &hash);
+    assert_eq!(commit["files"][0]["path"], json!("/test/a/b/d"));
+    assert_eq!(commit["files"][0]["linesAdded"], json!(100));
+
+    // Only the author was corrected
+    let commits = vec![json!({
+        "author": "test-author <author@test.test>",
+        "branch": "test-branch",
+        "message": "test-message",
+        "hash": &hash,
+        "time": 123456789,
+        "files": &files
+    })];
+
+    let mut response = client.put("/services/gtm/api/repositories")
+        .header(api_key_header(&api_key))
+        .header(ContentType::JSON)
+        .body(json!({
+            "repository": {
+                "user": &user,
+                "provider": &provider,
+                "repo": &repo,
+                "commits": &commits,
+            }
+        }).to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let body_json: Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+    assert_eq!(body_json["ingestion"]["updated"], json!([&hash]));
+    assert_eq!(body_json["ingestion"]["skipped"], json!([]));
+    assert_eq!(get_commit(&client, &admin_jwt, repository_id, &hash)["email"], "author@test.test");
+
+    teardown_api_key(&jwt, &api_key);
+    teardown(&jwt);
+}
+
+#[test]
+fn test_create_repository_attaches_files_to_their_commits() {
+    let jwt = setup();
+    let admin_jwt = get_admin_jwt();
+    let api_key = create_sync_client_api_key(&jwt, 2);
+
+    let client = Client::new(gtm_api::rocket()).unwrap();
+
+    let user = random_string(16);
+    let provider = random_string(10);
+    let repo = random_string(10);
+
+    let (commit_count, file_count) = (50, 4);
+    let hashes: Vec<String> = (0..commit_count).map(|_| random_string(16)).collect();
+    let commits: Vec<Value> = hashes.iter().enumerate().map(|(c, hash)| {
+        let files: Vec<Value> = (0..file_count).map(|f| json!({
+            "path": format!("commit{}/file{}.rs", c, f),
+            "status": "m",
+            "time_total": 60 * (c * file_count + f + 1),
+            "added_lines": c,
+            "deleted_lines": f,
+            "timeline": [{
+                "timestamp": 1600000000 + c * 3600,
+                "time": 60 * (c * file_count + f + 1),
+            }],
+        })).collect();
+        json!({
+            "author": "test-author <test@test.test>",
+            "branch": "test-branch",
+            "message": "test-message",
+            "hash": hash,
+            "time": 1600000000 + c * 3600,
+            "files": files,
+        })
+    }).collect();
+
+    let mut response = client.post("/services/gtm/api/repositories")
+        .header(api_key_header(&api_key))
+        .header(ContentType::JSON)
+        .body(json!({
+            "repository": {
+                "user": &user,
+                "provider": &provider,
+                "repo": &repo,
+                "commits": &commits,
+            }
+        }).to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let body_json: Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+    assert_eq!(body_json["commitsTotal"], commit_count);
+    let repository_id = body_json["id"].as_i64().unwrap();
+    for (c, hash) in hashes.iter().enumerate() {
+        let commit = get_commit(&client, &admin_jwt, repository_id, hash);
+        let files = commit["files"].as_array().unwrap();
+        assert_eq!(files.len(), file_count);
+        for file in files {
+            let path = file["path"].as_str().unwrap();
+            assert!(path.starts_with(&format!("commit{}/", c)));
+            assert_eq!(file["linesAdded"], json!(c));
+            assert_eq!(file["timeline"].as_array().unwrap().len(), 1);
+            assert_eq!(file["timeline"][0]["time"], file["time"]);
+        }
+    }
+
+    teardown_api_key(&jwt, &api_key);
+    teardown(&jwt);
+}
+
+#[test]
+fn test_create_repository_in_batches() {
+    let jwt = setup();
+    let api_key = create_sync_client_api_key(&jwt, 2);
+
+    let client = Client::new(gtm_api::rocket()).unwrap();
+
+    let user = random_string(16);
+    let provider = random_string(10);
+    let repo = random_string(10);
+
+    // More commits than fit into one batch, the last of them in a second one.
+    let commits: Vec<Value> = (0..510).map(|c| json!({
+        "author": "test-author <test@test.test>",
+        "branch": "test-branch",
+        "message": "test-message",
+        "hash": random_string(16),
+        "time": 1600000000 + c * 60,
+        "files": Vec::<Value>::new(),
+    })).collect();
+    // json! sorts keys, the sync client sends the repository before its commits.
+    let body = |commits: &[Value]| format!(
+        r#"{{"repository":{{"user":"{}","provider":"{}","repo":"{}","commits":{}}}}}"#,
+        user, provider, repo, Value::from(commits.to_vec()),
+    );
+
+    let mut malformed = commits.clone();
+    malformed[505]["author"] = json!("test-author");
+    let mut response = client.post("/services/gtm/api/repositories")
+        .header(api_key_header(&api_key))
+        .header(ContentType::JSON)
+        .body(body(&malformed))
+        .dispatch();
+    assert_eq!(response.status(), Status::UnprocessableEntity);
+
+    let body_json: Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+    let errors = body_json["errors"].as_object().unwrap();
+    assert!(errors.contains_key("commits[505].author"));
+    assert_eq!(errors.len(), 1);
+
+    // The batch before the malformed one is stored
+    let mut response = client.put("/services/gtm/api/repositories")
+        .header(api_key_header(&api_key))
+        .header(ContentType::JSON)
+        .body(body(&commits[..500]))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let body_json: Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+    assert_eq!(body_json["ingestion"]["skipped"].as_array().unwrap().len(), 500);
+
+    let mut response = client.post("/services/gtm/api/repositories")
+        .header(api_key_header(&api_key))
+        .header(ContentType::JSON)
+        .body(body(&commits))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let body_json: Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+    assert_eq!(body_json["commitsTotal"], 510);
+    assert_eq!(body_json["ingestion"]["inserted"].as_array().unwrap().len(), 510);
+
+    let mut response = client.put("/services/gtm/api/repositories?async=true")
+        .header(api_key_header(&api_key))
+        .header(ContentType::JSON)
+        .body(body(&commits))
+        .dispatch();
+    assert_eq!(response.status(), Status::Accepted);
+
+    let body_json: Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+    assert_eq!(body_json["kind"], "update");
+    assert_eq!(body_json["commitsTotal"], 510);
+
+    teardown_api_key(&jwt, &api_key);
+    teardown(&jwt);
+}
+
+#[test]
+fn test_create_repository_with_malformed_commits() {
+    let jwt = setup();
+    let api_key = create_sync_client_api_key(&jwt, 2);
+
+    let client = Client::new(gtm_api::rocket()).unwrap();
+
+    let user = random_string(16);
+    let provider = random_string(10);
+    let repo = random_string(10);
+
+    let timeline = vec![json!({
+        "timestamp": 123456789,
+        "time": 123,
+    })];
+
+    let files = vec![
+        json!({
+            "path": "/test/a/b/c",
+            "status": "m",
+            "time_total": 123,
+            "added_lines": 123,
+            "deleted_lines": 12,
+            "timeline": &timeline,
+        }),
+        json!({
+            "path": "/test/a/b/d",
+            "status": "m",
+            "added_lines": 123,
+            "deleted_lines": 12,
+            "timeline": &timeline,
+        }),
+    ];
+
+    let commits = vec![
+        json!({
+            "author": "test-author <test@test.test>",
+            "branch": "test-branch",
+            "message": "test-message",
+            "hash": random_string(16),
+            "time": 123456789,
+            "files": &files
+        }),
+        json!({
+            "author": "test-author",
+            "branch": "test-branch",
+            "message": "test-message",
+            "hash": random_string(16),
+            "time": 123456789,
+            "files": Vec::<Value>::new(),
+        }),
+    ];
+
+    let mut response = client.post("/services/gtm/api/repositories")
+        .header(api_key_header(&api_key))
+        .header(ContentType::JSON)
+        .body(json!({
+            "repository": {
+                "user": &user,
+                "provider": &provider,
+                "repo": &repo,
+                "commits": &commits,
+            }
+        }).to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::UnprocessableEntity);
+    assert_eq!(response.content_type(), Some(ContentType::JSON));
+
+    let body_json: Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+    let errors = body_json["errors"].as_object().unwrap();
+    assert!(errors.contains_key("commits[0].files[1].time_total"));
+    assert!(errors.contains_key("commits[1].author"));
+    assert_eq!(errors.len(), 2);
+
+    let response = client.put("/services/gtm/api/repositories")
+        .header(api_key_header(&api_key))
+        .header(ContentType::JSON)
+        .body(json!({
+            "repository": {
+                "user": &user,
+                "provider": &provider,
+                "repo": &repo,
+                "commits": Vec::<Value>::new(),
+            }
+        }).to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+
+    teardown_api_key(&jwt, &api_key);
+    teardown(&jwt);
+}
+
+#[test]
+fn test_create_repository_async() {
+    let jwt = setup();
+    let api_key = create_sync_client_api_key(&jwt, 2);
+
+    let client = Client::new(gtm_api::rocket()).unwrap();
+
+    let commits = vec![json!({
+        "author": "test-author <test@test.test>",
+        "branch": "test-branch",
+        "message": "test-message",
+        "hash": random_string(16),
+        "time": 123456789,
+        "files": Vec::<Value>::new(),
+    })];
+
+    let mut response = client.post("/services/gtm/api/repositories?async=true")
+        .header(api_key_header(&api_key))
+        .header(ContentType::JSON)
+        .body(json!({
+            "repository": {
+                "user": random_string(16),
+                "provider": random_string(10),
+                "repo": random_string(10),
+                "commits": &commits,
+            }
+        }).to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::Accepted);
+    assert_eq!(response.content_type(), Some(ContentType::JSON));
+
+    let body_json: Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+    let job_id = body_json["id"].as_i64().unwrap();
+    assert_eq!(body_json["kind"], "create");
+    assert_eq!(body_json["commitsTotal"], 1);
+
+    let mut response = client.get(format!("/services/gtm/api/ingest/jobs/{}", job_id))
+        .header(api_key_header(&api_key))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let body_json: Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+    assert_eq!(body_json["id"].as_i64().unwrap(), job_id);
+    assert_eq!(body_json["status"], "queued");
+    assert_eq!(body_json["commitsProcessed"], 0);
+
+    teardown_api_key(&jwt, &api_key);
+    teardown(&jwt);
+}
+
+#[test]
+fn test_create_repository_compressed() {
+    let jwt = setup();
+    let api_key = create_sync_client_api_key(&jwt, 2);
+
+    let client = Client::new(gtm_api::rocket()).unwrap();
+
+    let commits = vec![json!({
+        "author": "test-author <test@test.test>",
+        "branch": "test-branch",
+        "message": "test-message",
+        "hash": random_string(16),
+        "time": 123456789,
+        "files": Vec::<Value>::new(),
+    })];
+    let body = json!({
+        "repository": {
+            "user": random_string(16),
+            "provider": random_string(10),
+            "repo": random_string(10),
+            "commits": &commits,
+        }
+    }).to_string();
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(body.as_bytes()).unwrap();
+    let response = client.post("/services/gtm/api/repositories")
+        .header(api_key_header(&api_key))
+        .header(ContentType::JSON)
+        .header(Header::new("Content-Encoding", "gzip"))
+        .body(encoder.finish().unwrap())
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let response = client.post("/services/gtm/api/repositories")
+        .header(api_key_header(&api_key))
+        .header(ContentType::JSON)
+        .header(Header::new("Content-Encoding", "zstd"))
+        .body(zstd::encode_all(body.as_bytes(), 0).unwrap())
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let response = client.post("/services/gtm/api/repositories")
+        .header(api_key_header(&api_key))
+        .header(ContentType::JSON)
+        .header(Header::new("Content-Encoding", "br"))
+        .body(body)
+        .dispatch();
+    assert_eq!(response.status(), Status::UnsupportedMediaType);
+
+    teardown_api_key(&jwt, &api_key);
+    teardown(&jwt);
+}
+
+#[test]
+fn test_create_repository_decoded_payload_too_large() {
+    let jwt = setup();
+    let api_key = create_sync_client_api_key(&jwt, 2);
+
+    let client = Client::new(gtm_api::rocket()).unwrap();
+
+    // Whitespace is valid JSON, so the body only fails on the decoded size limit.
+    let padding = vec![b' '; 1024 * 1024];
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::fast());
+    for _ in 0..300 {
+        encoder.write_all(&padding).unwrap();
+    }
+    encoder.write_all(b"{}").unwrap();
+
+    let mut response = client.post("/services/gtm/api/repositories")
+        .header(api_key_header(&api_key))
+        .header(ContentType::JSON)
+        .header(Header::new("Content-Encoding", "gzip"))
+        .body(encoder.finish().unwrap())
+        .dispatch();
+    assert_eq!(response.status(), Status::PayloadTooLarge);
+
+    let body_json: Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+    assert_eq!(body_json["reason"], "Payload is too large.");
+
+    teardown_api_key(&jwt, &api_key);
+    teardown(&jwt);
+}
+
+#[test]
+fn test_create_repository_from_gtm_notes() {
+    let jwt = setup();
+    let admin_jwt = get_admin_jwt();
+    let api_key = create_sync_client_api_key(&jwt, 2);
+
+    let client = Client::new(gtm_api::rocket()).unwrap();
+
+    let user = random_string(16);
+    let provider = random_string(10);
+    let repo = random_string(10);
+
+    // Two blocks, as left behind by an amended commit.
+    let note = "[ver:2,total:480]\n\
+                src/main.rs:420,1585861200:300,1585864800:120,m\n\
+                README.md:60,1585861200:60,r\n\
+                [ver:2,total:30]\n\
+                src/main.rs:30,1585864800:30,m\n";
+    let numstat = "12\t3\tsrc/main.rs\n-\t-\tlogo.png\n";
+    let hash = random_string(16);
+
+    let commits = vec![json!({
+        "author": "test-author <test@test.test>",
+        "branch": "test-branch",
+        "message": "test-message",
+        "hash": &hash,
+        "time": 1585868400,
+        "note": note,
+        "numstat": numstat,
+    })];
+
+    let mut response = client.post("/services/gtm/api/repositories/notes")
+        .header(api_key_header(&api_key))
+        .header(ContentType::JSON)
+        .body(json!({
+            "repository": {
+                "user": &user,
+                "provider": &provider,
+                "repo": &repo,
+                "commits": &commits,
+            }
+        }).to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let body_json: Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+    let commit = get_commit(&client, &admin_jwt, body_json["id"].as_i64().unwrap(), &hash);
+    let files = commit["files"].as_array().unwrap();
+    assert_eq!(files.len(), 2);
+
+    let main = files.iter().find(|f| f["path"] == "src/main.rs").unwrap();
+    assert_eq!(main["status"], "m");
+    assert_eq!(main["time"], 450);
+    assert_eq!(main["linesAdded"], 12);
+    assert_eq!(main["linesDeleted"], 3);
+    let mut timeline: Vec<(i64, i64)> = main["timeline"].as_array().unwrap().iter()
+        .map(|t| (t["timestamp"].as_i64().unwrap(), t["time"].as_i64().unwrap()))
+        .collect();
+    timeline.sort();
+    assert_eq!(timeline, vec![(1585861200, 300), (1585864800, 150)]);
+
+    let readme = files.iter().find(|f| f["path"] == "README.md").unwrap();
+    assert_eq!(readme["status"], "r");
+    assert_eq!(readme["time"], 60);
+    assert_eq!(readme["linesAdded"], 0);
+
+    let mut response = client.put("/services/gtm/api/repositories/notes")
+        .header(api_key_header(&api_key))
+        .header(ContentType::JSON)
+        .body(json!({
+            "repository": {
+                "user": &user,
+                "provider": &provider,
+                "repo": &repo,
+                "commits": vec![json!({
+                    "author": "test-author <test@test.test>",
+                    "branch": "test-branch",
+                    "message": "test-message",
+                    "hash": random_string(16),
+                    "time": 1585868400,
+                    "note": "[ver:2,total:100]\nsrc/main.rs:90,1585861200:90,m\n",
+                })],
+            }
+        }).to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::UnprocessableEntity);
+
+    let body_json: Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+    assert!(body_json["errors"].as_object().unwrap().contains_key("commits[0].note"));
+
+    teardown_api_key(&jwt, &api_key);
+    teardown(&jwt);
+}
+
+#[test]
+fn test_gtm_notes_round_trip() {
+    let jwt = setup();
+    let admin_jwt = get_admin_jwt();
+    let api_key = create_sync_client_api_key(&jwt, 2);
+
+    let client = Client::new(gtm_api::rocket()).unwrap();
+
+    let user = random_string(16);
+    let provider = random_string(10);
+    let repo = random_string(10);
+
+    // Notes as `git notes --ref=gtm-data show` prints them, with the files they should give:
+    // path, status, time, lines added, lines deleted and the hourly timeline.
+    let notes = vec![
+        (
+            random_string(16),
+            1585866000,
+            "[ver:2,total:2520]\n\
+             src/app/main.go:2160,1585861200:1440,1585864800:720,m\n\
+             docs/README.md:360,1585864800:360,a\n",
+            "40\t2\tsrc/app/main.go\n15\t0\tdocs/README.md\n",
+            vec![
+                ("src/app/main.go", "m", 2160, 40, 2, vec![(1585861200, 1440), (1585864800, 720)]),
+                ("docs/README.md", "a", 360, 15, 0, vec![(1585864800, 360)]),
+            ],
+        ),
+        (
+            random_string(16),
+            1585870000,
+            "[ver:2,total:720]\n\
+             src/app/main.go:360,1585868400:360,m\n\
+             config/app.yml:360,1585868400:360,d\n",
+            "5\t5\tsrc/app/main.go\n0\t12\tconfig/app.yml\n",
+            vec![
+                ("src/app/main.go", "m", 360, 5, 5, vec![(1585868400, 360)]),
+                ("config/app.yml", "d", 360, 0, 12, vec![(1585868400, 360)]),
+            ],
+        ),
+    ];
+
+    let commits: Vec<Value> = notes.iter()
+        .map(|(hash, time, note, numstat, _)| json!({
+            "author": "test-author <test@test.test>",
+            "branch": "test-branch",
+            "message": format!("commit {}", hash),
+            "hash": hash,
+            "time": time,
+            "note": note,
+            "numstat": numstat,
+        }))
+        .collect();
+
+    let mut response = client.post("/services/gtm/api/repositories/notes")
+        .header(api_key_header(&api_key))
+        .header(ContentType::JSON)
+        .body(json!({
+            "repository": {
+                "user": &user,
+                "provider": &provider,
+                "repo": &repo,
+                "commits": &commits,
+            }
+        }).to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let body_json: Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+    assert_eq!(body_json["commitsTotal"], 2);
+    let repository_id = body_json["id"].as_i64().unwrap();
+
+    for (hash, time, _, _, expected_files) in &notes {
+        let commit = get_commit(&client, &admin_jwt, repository_id, hash);
+        assert_eq!(commit["hash"].as_str(), Some(hash.as_str()));
+        assert_eq!(commit["message"], json!(format!("commit {}", hash)));
+        assert_eq!(commit["email"], "test@test.test");
+        assert_eq!(commit["gitUserName"], "test-author");
+        assert_eq!(commit["time"], json!(time));
+
+        let files = commit["files"].as_array().unwrap();
+        assert_eq!(files.len(), expected_files.len());
+        for (path, status, time, added, deleted, timeline) in expected_files {
+            let file = files.iter().find(|f| f["path"] == *path).unwrap();
+            assert_eq!(file["status"], *status, "{}", path);
+            assert_eq!(file["time"], *time, "{}", path);
+            assert_eq!(file["linesAdded"], *added, "{}", path);
+            assert_eq!(file["linesDeleted"], *deleted, "{}", path);
+            let mut stored: Vec<(i64, i64)> = file["timeline"].as_array().unwrap().iter()
+                .map(|t| (t["timestamp"].as_i64().unwrap(), t["time"].as_i64().unwrap()))
+                .collect();
+            stored.sort();
+            assert_eq!(&stored, timeline, "{}", path);
+        }
+    }
+
+    // The hourly timeline of the repository adds up the note timelines of both commits
+    let mut response = client.get(
+        format!("/services/gtm/api/repositories/{}/timeline?start={}&end={}&interval={}&timezone={}",
+                repository_id, 1585861200, 1585861200 + 3 * 3600, "hour", "UTC"))
+        .header(bearer_header(&admin_jwt))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let body_json: Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+    let times: Vec<f64> = body_json.as_array().unwrap().iter()
+        .map(|i| i["time"].as_f64().unwrap())
+        .collect();
+    assert_eq!(times, vec![0.4, 0.3, 0.2]);
+
+    teardown_api_key(&jwt, &api_key);
+    teardown(&jwt);
+}
+
+#[test]
+fn test_update_repository_supersedes_rebased_commits() {
+    let jwt = setup();
+    let admin_jwt = get_admin_jwt();
+    let api_key = create_sync_client_api_key(&jwt, 2);
+
+    let client = Client::new(gtm_api::rocket()).unwrap();
+
+    let user = random_string(16);
+    let provider = random_string(10);
+    let repo = random_string(10);
+
+    let timeline = vec![json!({
+        "timestamp": 3600,
+        "time": 100,
+    })];
+
+    let files = vec![json!({
+        "path": "/test/a/b/c",
+        "status": "m",
+        "time_total": 100,
+        "added_lines": 50,
+        "deleted_lines": 10,
+        "timeline": &timeline,
+    })];
+
+    let commit = |hash: &str, message: &str| json!({
+        "author": "test-author <test@test.test>",
+        "branch": "test-branch",
+        "message": message,
+        "hash": hash,
+        "time": 1000,
+        "files": &files
+    });
+
+    let old_hash = random_string(16);
+    let amended_hash = random_string(16);
+    let rebased_hash = random_string(16);
+
+    let response = client.post("/services/gtm/api/repositories")
+        .header(api_key_header(&api_key))
+        .header(ContentType::JSON)
+        .body(json!({
+            "repository": {
+                "user": &user,
+                "provider": &provider,
+                "repo": &repo,
+                "commits": vec![commit(&old_hash, "test-message")],
+            }
+        }).to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    // Amended commit carries the same gtm time, so the old one is superseded on ingestion.
+    let mut response = client.put("/services/gtm/api/repositories")
+        .header(api_key_header(&api_key))
+        .header(ContentType::JSON)
+        .body(json!({
+            "repository": {
+                "user": &user,
+                "provider": &provider,
+                "repo": &repo,
+                "commits": vec![commit(&amended_hash, "amended-message")],
+            }
+        }).to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let body_json: Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+    assert_eq!(body_json["ingestion"]["superseded"], json!([&old_hash]));
+
+    // Hashes reported as removed are superseded even without an equivalent commit.
+    let mut response = client.put("/services/gtm/api/repositories")
+        .header(api_key_header(&api_key))
+        .header(ContentType::JSON)
+        .body(json!({
+            "repository": {
+                "user": &user,
+                "provider": &provider,
+                "repo": &repo,
+                "commits": vec![json!({
+                    "author": "test-author <test@test.test>",
+                    "branch": "test-branch",
+                    "message": "other-message",
+                    "hash": &rebased_hash,
+                    "time": 2000,
+                    "files": &files
+                })],
+                "removed_hashes": vec![&amended_hash],
+            }
+        }).to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let body_json: Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+    assert_eq!(body_json["ingestion"]["superseded"], json!([&amended_hash]));
+
+    let mut response = client.get(
+        format!("/services/gtm/api/groups/{}-{}-{}/stats?start={}&end={}&depth={}",
+                provider, user, repo, 0, 60 * 60 * 24 * 7, 2))
+        .header(bearer_header(&admin_jwt))
+        .header(ContentType::JSON)
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let body_json: Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+    let users = body_json["users"].as_array().unwrap();
+    assert_eq!(users.len(), 1);
+    assert_eq!(users[0]["commits"], 1);
+
+    teardown_api_key(&jwt, &api_key);
+    teardown(&jwt);
+}
+
+#[test]
+fn test_get_repository_stats_and_timeline() {
+    let jwt = setup();
+    let admin_jwt = get_admin_jwt();
+    let api_key = create_sync_client_api_key(&jwt, 2);
+
+    let client = Client::new(gtm_api::rocket()).unwrap();
+
+    let user = random_string(16);
+    let provider = random_string(10);
+    let repo = random_string(10);
+
+    let timeline = vec![json!({
+        "timestamp": 100,
+        "time": 100,
+    })];
+
+    let files = vec![json!({
+        "path": "/test/a/b/c",
+        "status": "m",
+        "time_total": 100,
+        "added_lines": 50,
+        "deleted_lines": 10,
+        "timeline": &timeline,
+    })];
+
+    let commits = vec![json!({
+        "author": "test-author <test@test.test>",
+        "branch": "test-branch",
+        "message": "test-message",
+        "hash": random_string(16),
+        "time": 1000,
+        "files": &files
+    })];
+
+    let mut response = client.post("/services/gtm/api/repositories")
+        .header(api_key_header(&api_key))
+        .header(ContentType::JSON)
+        .body(json!({
+            "repository": {
+                "user": &user,
+                "provider": &provider,
+                "repo": &repo,
+                "commits": &commits,
+            }
+        }).to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let body_json: Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+    let repository_id = body_json["id"].as_i64().unwrap();
+
+    let mut response = client.get("/services/gtm/api/repositories")
+        .header(bearer_header(&admin_jwt))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let body_json: Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+    assert!(body_json.as_array().unwrap().iter().any(|r| r["id"].as_i64() == Some(repository_id)));
+
+    let mut response = client.get(format!("/services/gtm/api/repositories/{}", repository_id))
+        .header(bearer_header(&admin_jwt))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let body_json: Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+    assert_eq!(body_json["repo"], json!(&repo));
+    assert_eq!(body_json["group"], json!(format!("{}-{}-{}", provider, user, repo)));
+
+    let mut response = client.get(
+        format!("/services/gtm/api/repositories/{}/stats?start={}&end={}&depth={}",
+                repository_id, 0, 60 * 60 * 24 * 7, 2))
+        .header(bearer_header(&admin_jwt))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let body_json: Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+    assert_eq!(body_json["users"].as_array().unwrap().len(), 1);
+    assert_eq!(body_json["files"].as_array().unwrap().len(), 1);
+
+    let mut response = client.get(
+        format!("/services/gtm/api/repositories/{}/timeline?start={}&end={}&interval={}&timezone={}",
+                repository_id, 0, 60 * 60 * 24 * 7, "day", "Europe/Tallinn"))
+        .header(bearer_header(&admin_jwt))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let body_json: Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+    assert_eq!(body_json.as_array().unwrap().len(), 8);
+
+    // The regular user has no access to the repository's group.
+    let mut response = client.get("/services/gtm/api/repositories")
+        .header(bearer_header(&jwt))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let body_json: Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+    assert!(!body_json.as_array().unwrap().iter().any(|r| r["id"].as_i64() == Some(repository_id)));
+
+    let response = client.get(format!("/services/gtm/api/repositories/{}/stats", repository_id))
+        .header(bearer_header(&jwt))
+        .dispatch();
+    assert_eq!(response.status(), Status::Unauthorized);
+
+    teardown_api_key(&jwt, &api_key);
+    teardown(&jwt);
+}
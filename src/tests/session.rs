@@ -0,0 +1,127 @@
+use rocket::http::{ContentType, Status};
+use rocket::local::Client;
+use serde_json::{json, Value};
+
+use crate::tests::common::{api_key_header, bearer_header, create_sync_client_api_key, get_admin_jwt, random_string, setup, teardown, teardown_api_key};
+
+#[test]
+fn test_sessions() {
+    let jwt = setup();
+    let admin_jwt = get_admin_jwt();
+    let api_key = create_sync_client_api_key(&jwt, 2);
+
+    let client = Client::new(gtm_api::rocket()).unwrap();
+
+    let user = random_string(16);
+    let provider = random_string(10);
+    let repo = random_string(10);
+
+    // 2020-09-13 12:00:00 UTC, work spanning 12:00-14:00 and a separate slot at 15:00
+    let start = 1599998400;
+    let files = vec![
+        json!({
+            "path": "src/a.rs",
+            "status": "m",
+            "time_total": 3600,
+            "added_lines": 5,
+            "deleted_lines": 1,
+            "timeline": vec![
+                json!({ "timestamp": start, "time": 1800 }),
+                json!({ "timestamp": start + 3600, "time": 1200 }),
+                json!({ "timestamp": start + 3 * 3600, "time": 600 }),
+            ],
+        }),
+        json!({
+            "path": "src/b.rs",
+            "status": "m",
+            "time_total": 30,
+            "added_lines": 5,
+            "deleted_lines": 1,
+            "timeline": vec![json!({ "timestamp": start, "time": 30 })],
+        }),
+    ];
+
+    let commits = vec![json!({
+        "author": "test-author <test@test.test>",
+        "branch": "test-branch",
+        "message": "test-message",
+        "hash": random_string(16),
+        "time": start + 4 * 3600,
+        "files": &files
+    })];
+
+    let response = client.post("/services/gtm/api/repositories")
+        .header(api_key_header(&api_key))
+        .header(ContentType::JSON)
+        .body(json!({
+            "repository": {
+                "user": &user,
+                "provider": &provider,
+                "repo": &repo,
+                "commits": &commits,
+            }
+        }).to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let url = |idle_gap: &str| format!(
+        "/services/gtm/api/{}-{}-{}/sessions?start={}&end={}&interval={}&timezone={}{}",
+        provider, user, repo, start - 3600, start + 3600 * 24, "day", "UTC", idle_gap);
+
+    let mut response = client.get(url(""))
+        .header(bearer_header(&admin_jwt))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.content_type(), Some(ContentType::JSON));
+
+    let body_json: Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+    assert_eq!(body_json["idleGap"], 900);
+    let group = &body_json["group"];
+    // Consecutive hours belong to one session, the gap is measured from the end of the hour
+    assert_eq!(group["sessions"], 2);
+    assert_eq!(group["averageLength"], 2700.0);
+    assert_eq!(group["longestSession"], 4800);
+    assert_eq!(group["time"], 3630);
+    assert_eq!(group["startDistribution"].as_array().unwrap().len(), 24);
+    assert_eq!(group["startDistribution"][12]["sessions"], 1);
+    assert_eq!(group["startDistribution"][15]["sessions"], 1);
+    let users = body_json["users"].as_array().unwrap();
+    assert_eq!(users.len(), 1);
+    assert_eq!(users[0]["sessions"], 2);
+
+    let mut response = client.get(url("&idle_gap=3600"))
+        .header(bearer_header(&admin_jwt))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let body_json: Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+    assert_eq!(body_json["group"]["sessions"], 1);
+    assert_eq!(body_json["group"]["longestSession"], 3 * 3600 + 600);
+
+    // Without start and end the group's period applies
+    let response = client.put(format!("/services/gtm/api/groups/{}-{}-{}/metadata", provider, user, repo))
+        .header(bearer_header(&admin_jwt))
+        .header(ContentType::JSON)
+        .body(json!({ "periodStart": start + 2 * 3600, "periodEnd": start + 24 * 3600 }).to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let mut response = client.get(format!(
+        "/services/gtm/api/{}-{}-{}/sessions?interval={}&timezone={}",
+        provider, user, repo, "day", "UTC"))
+        .header(bearer_header(&admin_jwt))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let body_json: Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+    assert_eq!(body_json["group"]["sessions"], 1);
+    assert_eq!(body_json["group"]["time"], 600);
+
+    let response = client.get(url("&idle_gap=0"))
+        .header(bearer_header(&admin_jwt))
+        .dispatch();
+    assert_eq!(response.status(), Status::UnprocessableEntity);
+
+    teardown_api_key(&jwt, &api_key);
+    teardown(&jwt);
+}
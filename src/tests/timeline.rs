@@ -1,3 +1,7 @@
+use std::collections::HashSet;
+
+use chrono::{Datelike, DateTime, Duration, NaiveDate, NaiveTime, TimeZone, Timelike};
+use chrono_tz::Tz;
 use rocket::http::{ContentType, Status};
 use rocket::local::Client;
 use serde_json::{json, Value};
@@ -221,3 +225,681 @@ fn test_subdirs_timeline() {
     teardown_api_key(&jwt, &api_key);
     teardown(&jwt);
 }
+
+#[test]
+fn test_timeline_branches() {
+    let jwt = setup();
+    let admin_jwt = get_admin_jwt();
+    let api_key = create_sync_client_api_key(&jwt, 2);
+
+    let client = Client::new(gtm_api::rocket()).unwrap();
+
+    let user = random_string(16);
+    let provider = random_string(10);
+    let repo = random_string(10);
+
+    let timeline = vec![json!({
+        "timestamp": 100,
+        "time": 100,
+    })];
+
+    let files = vec![json!({
+        "path": "/test/a/b/c",
+        "status": "m",
+        "time_total": 100,
+        "added_lines": 50,
+        "deleted_lines": 10,
+        "timeline": &timeline,
+    })];
+
+    let hash = random_string(16);
+    let commit = |branch: &str| json!({
+        "author": "test-author <test@test.test>",
+        "branch": branch,
+        "message": "test-message",
+        "hash": &hash,
+        "time": 1000,
+        "files": &files
+    });
+
+    let response = client.post("/services/gtm/api/repositories")
+        .header(api_key_header(&api_key))
+        .header(ContentType::JSON)
+        .body(json!({
+            "repository": {
+                "user": &user,
+                "provider": &provider,
+                "repo": &repo,
+                "commits": vec![commit("feature/x")],
+            }
+        }).to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    // Same commit after merging the feature branch into main.
+    let response = client.put("/services/gtm/api/repositories")
+        .header(api_key_header(&api_key))
+        .header(ContentType::JSON)
+        .body(json!({
+            "repository": {
+                "user": &user,
+                "provider": &provider,
+                "repo": &repo,
+                "commits": vec![commit("main")],
+            }
+        }).to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let mut response = client.get(
+        format!("/services/gtm/api/groups/{}-{}-{}/branches", provider, user, repo))
+        .header(bearer_header(&admin_jwt))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let body_json: Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+    let mut branches: Vec<&str> = body_json.as_array().unwrap().iter()
+        .map(|b| {
+            assert_eq!(b["commits"], 1);
+            assert_eq!(b["lastActivity"], 1000);
+            b["name"].as_str().unwrap()
+        })
+        .collect();
+    branches.sort();
+    assert_eq!(branches, vec!["feature/x", "main"]);
+
+    for (branch, users) in vec![("main", 1), ("feature/x,other", 1), ("other", 0)] {
+        let mut response = client.get(
+            format!("/services/gtm/api/{}-{}-{}/timeline?start={}&end={}&interval={}&timezone={}&branch={}",
+                    provider, user, repo, 0, 60 * 60 * 24 * 7, "day", "UTC", branch))
+            .header(bearer_header(&admin_jwt))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+
+        let body_json: Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+        let total: i64 = body_json.as_array().unwrap().iter()
+            .map(|i| i["users"].as_i64().unwrap())
+            .sum();
+        assert_eq!(total, users, "branch {}", branch);
+    }
+
+    teardown_api_key(&jwt, &api_key);
+    teardown(&jwt);
+}
+
+#[test]
+fn test_timeline_rollups() {
+    let jwt = setup();
+    let admin_jwt = get_admin_jwt();
+    let api_key = create_sync_client_api_key(&jwt, 2);
+
+    let client = Client::new(gtm_api::rocket()).unwrap();
+
+    let user = random_string(16);
+    let provider = random_string(10);
+    let repo = random_string(10);
+
+    let timeline = vec![
+        json!({ "timestamp": 60 * 60 * 5 + 100, "time": 1800 }),
+        json!({ "timestamp": 60 * 60 * 5 + 1000, "time": 1800 }),
+        json!({ "timestamp": 60 * 60 * 30 + 200, "time": 1800 }),
+    ];
+
+    let files = vec![json!({
+        "path": "/test/a/b/c",
+        "status": "m",
+        "time_total": 5400,
+        "added_lines": 50,
+        "deleted_lines": 10,
+        "timeline": &timeline,
+    })];
+
+    let commits = vec![json!({
+        "author": "test-author <test@test.test>",
+        "branch": "test-branch",
+        "message": "test-message",
+        "hash": random_string(16),
+        "time": 1000,
+        "files": &files
+    })];
+
+    let response = client.post("/services/gtm/api/repositories")
+        .header(api_key_header(&api_key))
+        .header(ContentType::JSON)
+        .body(json!({
+            "repository": {
+                "user": &user,
+                "provider": &provider,
+                "repo": &repo,
+                "commits": &commits,
+            }
+        }).to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let response = client.post("/services/gtm/api/timeline/rollups/rebuild")
+        .header(bearer_header(&jwt))
+        .dispatch();
+    assert_eq!(response.status(), Status::Unauthorized);
+
+    let mut response = client.post("/services/gtm/api/timeline/rollups/rebuild")
+        .header(bearer_header(&admin_jwt))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let body_json: Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+    assert!(body_json["rows"].as_i64().unwrap() >= 2);
+
+    // Hour aligned periods are served from rollups, the others from raw timeline entries.
+    let mut results = vec![];
+    for (start, end) in vec![(0, 60 * 60 * 24 * 7), (1, 60 * 60 * 24 * 7 + 1)] {
+        let mut response = client.get(
+            format!("/services/gtm/api/{}-{}-{}/timeline?start={}&end={}&interval={}&timezone={}",
+                    provider, user, repo, start, end, "day", "UTC"))
+            .header(bearer_header(&admin_jwt))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+
+        let body_json: Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+        let times: Vec<f64> = body_json.as_array().unwrap().iter()
+            .map(|i| i["time"].as_f64().unwrap())
+            .collect();
+        results.push(times);
+    }
+    assert_eq!(results[0][0], 1.0);
+    assert_eq!(results[0][1], 0.5);
+    assert_eq!(results[0], results[1]);
+
+    teardown_api_key(&jwt, &api_key);
+    teardown(&jwt);
+}
+
+fn post_timeline_entries(client: &Client, api_key: &str, user: &str, provider: &str, repo: &str, entries: &Vec<(i64, i64, usize)>) {
+    let commits: Vec<Value> = (0..3).map(|author| {
+        let timeline: Vec<Value> = entries.iter()
+            .filter(|(_, _, a)| *a == author)
+            .map(|(timestamp, time, _)| json!({ "timestamp": timestamp, "time": time }))
+            .collect();
+        let time_total: i64 = entries.iter()
+            .filter(|(_, _, a)| *a == author)
+            .map(|(_, time, _)| time)
+            .sum();
+        json!({
+            "author": format!("test-author-{} <test{}@test.test>", author, author),
+            "branch": "test-branch",
+            "message": "test-message",
+            "hash": random_string(16),
+            "time": 1600000000,
+            "files": vec![json!({
+                "path": format!("src/dir{}/file.rs", author),
+                "status": "m",
+                "time_total": time_total,
+                "added_lines": 10,
+                "deleted_lines": 2,
+                "timeline": timeline,
+            })],
+        })
+    }).collect();
+
+    let response = client.post("/services/gtm/api/repositories")
+        .header(api_key_header(api_key))
+        .header(ContentType::JSON)
+        .body(json!({
+            "repository": {
+                "user": user,
+                "provider": provider,
+                "repo": repo,
+                "commits": &commits,
+            }
+        }).to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+}
+
+#[test]
+fn test_timeline_bucketing() {
+    let jwt = setup();
+    let admin_jwt = get_admin_jwt();
+    let api_key = create_sync_client_api_key(&jwt, 2);
+
+    let client = Client::new(gtm_api::rocket()).unwrap();
+
+    let user = random_string(16);
+    let provider = random_string(10);
+    let repo = random_string(10);
+
+    // Not hour aligned, so the raw timeline is bucketed rather than rollups.
+    let (start, end) = (1600001000, 1600001000 + 60 * 60 * 24 * 100);
+    let mut entries: Vec<(i64, i64, usize)> = (0..300)
+        .map(|k| (start + (k * 7919 * 37) % (end - start), 60 + k % 500, (k % 3) as usize))
+        .collect();
+    // Items right at the start and in the last second of hourly and daily intervals.
+    for k in 1..40 {
+        entries.push((start + k * 3600, 120, (k % 3) as usize));
+        entries.push((start + k * 3600 - 1, 180, (k % 3) as usize));
+        entries.push((start + k * 86400 - 1, 240, (k % 3) as usize));
+    }
+    post_timeline_entries(&client, &api_key, &user, &provider, &repo, &entries);
+
+    for (interval, timezone) in vec![("hour", "Asia/Kolkata"), ("day", "Europe/Tallinn"), ("week", "UTC"), ("month", "Europe/Tallinn"), ("year", "Asia/Kolkata")] {
+        for cumulative in vec![false, true] {
+            let mut response = client.get(
+                format!("/services/gtm/api/{}-{}-{}/timeline?start={}&end={}&interval={}&timezone={}&cumulative={}",
+                        provider, user, repo, start, end, interval, timezone, cumulative))
+                .header(bearer_header(&admin_jwt))
+                .dispatch();
+            assert_eq!(response.status(), Status::Ok);
+
+            let body_json: Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+            for bucket in body_json.as_array().unwrap() {
+                let bucket_start = DateTime::parse_from_rfc3339(bucket["start"].as_str().unwrap()).unwrap().timestamp();
+                let bucket_end = DateTime::parse_from_rfc3339(bucket["end"].as_str().unwrap()).unwrap().timestamp();
+                let contained: Vec<&(i64, i64, usize)> = entries.iter()
+                    .filter(|(t, _, _)| start <= *t && *t < end)
+                    .filter(|(t, _, _)| (bucket_start <= *t || cumulative) && *t < bucket_end)
+                    .collect();
+                let time: i64 = contained.iter().map(|(_, time, _)| time).sum();
+                let users: HashSet<usize> = contained.iter().map(|(_, _, author)| *author).collect();
+                assert_eq!(bucket["time"].as_f64().unwrap(), (time as f64 / 60.0 / 60.0 * 10.0).round() / 10.0,
+                           "{} {} cumulative={}", interval, bucket["start"], cumulative);
+                assert_eq!(bucket["users"].as_u64().unwrap() as usize, users.len(),
+                           "{} {} cumulative={}", interval, bucket["start"], cumulative);
+            }
+        }
+    }
+
+    teardown_api_key(&jwt, &api_key);
+    teardown(&jwt);
+}
+
+#[test]
+fn test_hourly_rollups_match_raw_timeline() {
+    let jwt = setup();
+    let admin_jwt = get_admin_jwt();
+    let api_key = create_sync_client_api_key(&jwt, 2);
+
+    let client = Client::new(gtm_api::rocket()).unwrap();
+
+    let user = random_string(16);
+    let provider = random_string(10);
+    let repo = random_string(10);
+
+    // Hour aligned and across the end of daylight saving time in Tallinn, so rollups apply.
+    let (start, end) = (1600002000, 1600002000 + 60 * 60 * 24 * 50);
+    let entries: Vec<(i64, i64, usize)> = (0..3000)
+        .map(|k| (start + (k * 7919 * 37) % (end - start), 60 + k % 300, (k % 3) as usize))
+        .collect();
+    post_timeline_entries(&client, &api_key, &user, &provider, &repo, &entries);
+
+    // Every file is Rust, filtering by language gives the same data from the raw timeline.
+    let fetch = |path: &str, interval: &str, timezone: &str, cumulative: bool, filter: &str| -> Value {
+        let mut response = client.get(
+            format!("/services/gtm/api/{}-{}-{}/{}?start={}&end={}&interval={}&timezone={}&cumulative={}&depth=1{}",
+                    provider, user, repo, path, start, end, interval, timezone, cumulative, filter))
+            .header(bearer_header(&admin_jwt))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        serde_json::from_str(&response.body_string().unwrap()).unwrap()
+    };
+
+    for path in vec!["timeline", "subdirs-timeline"] {
+        for (interval, timezone) in vec![("hour", "Europe/Tallinn"), ("day", "Europe/Tallinn"), ("week", "UTC")] {
+            for cumulative in vec![false, true] {
+                let rollups = fetch(path, interval, timezone, cumulative, "");
+                let raw = fetch(path, interval, timezone, cumulative, "&language=rust");
+                assert_eq!(rollups, raw, "{} {} {} cumulative={}", path, interval, timezone, cumulative);
+            }
+        }
+    }
+
+    teardown_api_key(&jwt, &api_key);
+    teardown(&jwt);
+}
+
+#[test]
+fn test_heatmap() {
+    let jwt = setup();
+    let admin_jwt = get_admin_jwt();
+    let api_key = create_sync_client_api_key(&jwt, 2);
+
+    let client = Client::new(gtm_api::rocket()).unwrap();
+
+    let user = random_string(16);
+    let provider = random_string(10);
+    let repo = random_string(10);
+    let (author_a, author_b) = (format!("{}@test.test", random_string(10)), format!("{}@test.test", random_string(10)));
+
+    // Sunday 2020-09-13 12:00 and 14:00 UTC, 15:00 and 17:00 in Tallinn.
+    let commit = |author: &str, branch: &str, timestamp: i64, time: i64| json!({
+        "author": format!("test-author <{}>", author),
+        "branch": branch,
+        "message": "test-message",
+        "hash": random_string(16),
+        "time": timestamp,
+        "files": vec![json!({
+            "path": "src/a.rs",
+            "status": "m",
+            "time_total": time,
+            "added_lines": 10,
+            "deleted_lines": 2,
+            "timeline": vec![json!({ "timestamp": timestamp, "time": time })],
+        })],
+    });
+
+    let response = client.post("/services/gtm/api/repositories")
+        .header(api_key_header(&api_key))
+        .header(ContentType::JSON)
+        .body(json!({
+            "repository": {
+                "user": &user,
+                "provider": &provider,
+                "repo": &repo,
+                "commits": vec![
+                    commit(&author_a, "main", 1599998400, 3600),
+                    commit(&author_b, "dev", 1600005600, 1800),
+                ],
+            }
+        }).to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let heatmap = |filter: &str| -> Value {
+        let mut response = client.get(
+            format!("/services/gtm/api/comparison/heatmap?groups={}-{}-{}&start={}&end={}&timezone={}{}",
+                    provider, user, repo, 1599900000, 1600100000, "Europe/Tallinn", filter))
+            .header(bearer_header(&admin_jwt))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        serde_json::from_str(&response.body_string().unwrap()).unwrap()
+    };
+
+    let body_json = heatmap("");
+    assert_eq!(body_json["days"].as_array().unwrap().len(), 7);
+    assert_eq!(body_json["days"][6], "Sunday");
+    assert_eq!(body_json["time"].as_array().unwrap().len(), 7);
+    assert_eq!(body_json["time"][6].as_array().unwrap().len(), 24);
+    assert_eq!(body_json["time"][6][15], 1.0);
+    assert_eq!(body_json["time"][6][17], 0.5);
+    assert_eq!(body_json["users"][6][15], 1);
+    assert_eq!(body_json["lines_added"][6][17], 10);
+
+    let body_json = heatmap("&branch=dev");
+    assert_eq!(body_json["time"][6][15], 0.0);
+    assert_eq!(body_json["time"][6][17], 0.5);
+
+    let body_json = heatmap(&format!("&user={}", author_a));
+    assert_eq!(body_json["time"][6][15], 1.0);
+    assert_eq!(body_json["users"][6][17], 0);
+
+    teardown_api_key(&jwt, &api_key);
+    teardown(&jwt);
+}
+
+#[test]
+fn test_timeline_granularities() {
+    let jwt = setup();
+    let admin_jwt = get_admin_jwt();
+    let api_key = create_sync_client_api_key(&jwt, 2);
+
+    let client = Client::new(gtm_api::rocket()).unwrap();
+
+    let user = random_string(16);
+    let provider = random_string(10);
+    let repo = random_string(10);
+
+    // Sunday 2020-09-13 12:43:20 UTC
+    let start = 1600001000;
+    let entries = vec![(start + 10, 1800, 0), (start + 3700, 1800, 1), (start + 3800, 1800, 1)];
+    post_timeline_entries(&client, &api_key, &user, &provider, &repo, &entries);
+
+    let timeline = |end: i64, interval: &str| -> (Status, Value) {
+        let mut response = client.get(
+            format!("/services/gtm/api/{}-{}-{}/timeline?start={}&end={}&timezone={}&interval={}",
+                    provider, user, repo, start, end, "UTC", interval))
+            .header(bearer_header(&admin_jwt))
+            .dispatch();
+        let status = response.status();
+        (status, serde_json::from_str(&response.body_string().unwrap_or_default()).unwrap_or(Value::Null))
+    };
+
+    let (status, body_json) = timeline(start + 60 * 60 * 24 * 30, "isoweek");
+    assert_eq!(status, Status::Ok);
+    let weeks = body_json.as_array().unwrap();
+    assert_eq!(weeks[0]["start"], "2020-09-07T00:00:00+00:00");
+    assert_eq!(weeks[0]["end"], "2020-09-13T23:59:59+00:00");
+    assert_eq!(weeks[1]["start"], "2020-09-14T00:00:00+00:00");
+    assert_eq!(weeks[0]["time"], 1.5);
+
+    let (status, body_json) = timeline(start + 60 * 60 * 24 * 150, "quarter");
+    assert_eq!(status, Status::Ok);
+    let quarters = body_json.as_array().unwrap();
+    assert_eq!(quarters.len(), 3);
+    assert_eq!(quarters[0]["start"], "2020-07-01T00:00:00+00:00");
+    assert_eq!(quarters[1]["start"], "2020-10-01T00:00:00+00:00");
+    assert_eq!(quarters[2]["start"], "2021-01-01T00:00:00+00:00");
+
+    let (status, body_json) = timeline(start + 7200, "15min");
+    assert_eq!(status, Status::Ok);
+    let quarter_hours = body_json.as_array().unwrap();
+    assert_eq!(quarter_hours.len(), 9);
+    assert_eq!(quarter_hours[0]["time"], 0.5);
+    assert_eq!(quarter_hours[4]["time"], 1.0);
+
+    let (status, body_json) = timeline(start + 7200, &format!("custom&buckets={},{},{}", start, start + 3600, start + 7200));
+    assert_eq!(status, Status::Ok);
+    let buckets = body_json.as_array().unwrap();
+    assert_eq!(buckets.len(), 2);
+    assert_eq!(buckets[0]["time"], 0.5);
+    assert_eq!(buckets[0]["users"], 1);
+    assert_eq!(buckets[1]["time"], 1.0);
+
+    for interval in vec![
+        "fortnight".to_string(),
+        "custom".to_string(),
+        format!("custom&buckets={},{}", start + 3600, start),
+        format!("day&buckets={},{}", start, start + 3600),
+    ] {
+        let (status, _) = timeline(start + 7200, &interval);
+        assert_eq!(status, Status::UnprocessableEntity, "{}", interval);
+    }
+    let (status, _) = timeline(start + 60 * 60 * 24 * 30, "minute");
+    assert_eq!(status, Status::UnprocessableEntity);
+
+    teardown_api_key(&jwt, &api_key);
+    teardown(&jwt);
+}
+
+#[test]
+fn test_timeline_intervals_across_dst() {
+    let jwt = setup();
+    let admin_jwt = get_admin_jwt();
+    let api_key = create_sync_client_api_key(&jwt, 2);
+
+    let client = Client::new(gtm_api::rocket()).unwrap();
+
+    let user = random_string(16);
+    let provider = random_string(10);
+    let repo = random_string(10);
+    post_timeline_entries(&client, &api_key, &user, &provider, &repo, &vec![(1600001000, 60, 0)]);
+
+    let starts = |tz: &Tz, start: i64, end: i64, interval: &str| -> Vec<DateTime<Tz>> {
+        let mut response = client.get(
+            format!("/services/gtm/api/{}-{}-{}/timeline?start={}&end={}&timezone={}&interval={}",
+                    provider, user, repo, start, end, tz.name(), interval))
+            .header(bearer_header(&admin_jwt))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let body_json: Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+        let intervals = body_json.as_array().unwrap();
+        for pair in intervals.windows(2) {
+            let end = DateTime::parse_from_rfc3339(pair[0]["end"].as_str().unwrap()).unwrap();
+            let next = DateTime::parse_from_rfc3339(pair[1]["start"].as_str().unwrap()).unwrap();
+            assert_eq!(end + Duration::seconds(1), next, "intervals must be contiguous");
+        }
+        intervals.iter()
+            .map(|i| DateTime::parse_from_rfc3339(i["start"].as_str().unwrap()).unwrap().with_timezone(tz))
+            .collect()
+    };
+    // Local midnight, or the first instant after it when midnight is skipped by a DST transition.
+    let is_midnight = |date_time: &DateTime<Tz>| {
+        let midnight = date_time.date().naive_local().and_hms(0, 0, 0);
+        match date_time.timezone().from_local_datetime(&midnight).earliest() {
+            Some(expected) => *date_time == expected,
+            None => date_time.time() < NaiveTime::from_hms(2, 0, 0),
+        }
+    };
+
+    let zones: Vec<(Tz, NaiveDate)> = vec![
+        (chrono_tz::Europe::Tallinn, NaiveDate::from_ymd(2021, 3, 28)),
+        (chrono_tz::Europe::Tallinn, NaiveDate::from_ymd(2021, 10, 31)),
+        (chrono_tz::America::New_York, NaiveDate::from_ymd(2021, 3, 14)),
+        (chrono_tz::America::New_York, NaiveDate::from_ymd(2021, 11, 7)),
+        (chrono_tz::Australia::Sydney, NaiveDate::from_ymd(2021, 4, 4)),
+        (chrono_tz::Australia::Lord_Howe, NaiveDate::from_ymd(2021, 10, 3)),
+        (chrono_tz::America::Sao_Paulo, NaiveDate::from_ymd(2018, 11, 4)),
+        (chrono_tz::Asia::Kolkata, NaiveDate::from_ymd(2021, 3, 28)),
+    ];
+    for (tz, switch) in zones {
+        let first = switch - Duration::days(10);
+        let start = tz.from_local_datetime(&first.and_hms(0, 0, 0)).earliest().unwrap().timestamp();
+        let end = start + 60 * 60 * 24 * 20;
+
+        let days = starts(&tz, start, end, "day");
+        assert!(days.len() >= 20, "{}", tz.name());
+        for (n, day) in days.iter().enumerate() {
+            assert_eq!(day.date().naive_local(), first + Duration::days(n as i64), "{} {}", tz.name(), day);
+            assert!(is_midnight(day), "{} {}", tz.name(), day);
+        }
+
+        // A start within the day still gives buckets from local midnight on.
+        for interval in vec!["day", "week"] {
+            let buckets = starts(&tz, start + 49000, end, interval);
+            assert_eq!(buckets[0].date().naive_local(), first, "{} {}", tz.name(), interval);
+            assert!(is_midnight(&buckets[0]), "{} {} {}", tz.name(), interval, buckets[0]);
+            assert!(buckets.iter().all(|b| is_midnight(b)), "{} {}", tz.name(), interval);
+        }
+
+        for interval in vec!["week", "isoweek"] {
+            let weeks = starts(&tz, start - 60 * 60 * 24 * 60, end + 60 * 60 * 24 * 60, interval);
+            for pair in weeks.windows(2) {
+                assert_eq!(pair[1].date().naive_local() - pair[0].date().naive_local(), Duration::days(7), "{} {}", tz.name(), pair[1]);
+                assert!(is_midnight(&pair[1]), "{} {}", tz.name(), pair[1]);
+            }
+        }
+    }
+
+    // Month ends are clamped to shorter months without drifting to earlier days.
+    for tz in vec![chrono_tz::Europe::Tallinn, chrono_tz::America::New_York, chrono_tz::Australia::Sydney] {
+        let start = tz.ymd(2020, 1, 31).and_hms(0, 0, 0).timestamp();
+        let months = starts(&tz, start, start + 60 * 60 * 24 * 360, "month");
+        let days: Vec<u32> = months.iter().map(|m| m.date().naive_local().day()).collect();
+        assert_eq!(days, vec![31, 29, 31, 30, 31, 30, 31, 31, 30, 31, 30, 31], "{}", tz.name());
+        assert!(months.iter().all(|m| is_midnight(m)), "{}", tz.name());
+    }
+
+    teardown_api_key(&jwt, &api_key);
+    teardown(&jwt);
+}
+
+#[test]
+fn test_app_timeline() {
+    let jwt = setup();
+    let admin_jwt = get_admin_jwt();
+    let api_key = create_sync_client_api_key(&jwt, 2);
+
+    let client = Client::new(gtm_api::rocket()).unwrap();
+
+    let user = random_string(16);
+    let provider = random_string(10);
+    let repo = random_string(10);
+
+    let commit = |author: usize, files: Vec<(&str, i64, i64)>| json!({
+        "author": format!("test-author-{} <test{}@test.test>", author, author),
+        "branch": "test-branch",
+        "message": "test-message",
+        "hash": random_string(16),
+        "time": 1000,
+        "files": files.into_iter()
+            .map(|(path, timestamp, time)| json!({
+                "path": path,
+                "status": "m",
+                "time_total": time,
+                "added_lines": 0,
+                "deleted_lines": 0,
+                "timeline": vec![json!({ "timestamp": timestamp, "time": time })],
+            }))
+            .collect::<Vec<Value>>(),
+    });
+    let commits = vec![
+        commit(0, vec![("src/main.rs", 3600, 3600), ("browser.app", 3600, 1800), ("terminal.app", 90000, 900)]),
+        commit(1, vec![("browser.app", 3600, 600)]),
+    ];
+
+    let response = client.post("/services/gtm/api/repositories")
+        .header(api_key_header(&api_key))
+        .header(ContentType::JSON)
+        .body(json!({
+            "repository": {
+                "user": &user,
+                "provider": &provider,
+                "repo": &repo,
+                "commits": &commits,
+            }
+        }).to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let mut response = client.get(
+        format!("/services/gtm/api/{}-{}-{}/apps?start={}&end={}&interval={}&timezone={}",
+                provider, user, repo, 0, 60 * 60 * 24 * 7, "day", "UTC"))
+        .header(bearer_header(&admin_jwt))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.content_type(), Some(ContentType::JSON));
+
+    let body_json: Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+    assert_eq!(body_json["apps"], json!(["browser", "terminal"]));
+
+    let data = body_json["data"].as_array().unwrap();
+    assert_eq!(data.len(), 7);
+    assert_eq!(data[0]["time"], 0.7);
+    assert_eq!(data[0]["apps"]["browser"]["time"], 0.7);
+    assert_eq!(data[0]["apps"]["browser"]["users"]["test1@test.test"], 0.2);
+    assert!(data[0]["apps"]["terminal"].is_null());
+    assert_eq!(data[1]["apps"]["terminal"]["time"], 0.3);
+    assert_eq!(data[2]["time"], 0.0);
+
+    let users = body_json["users"].as_array().unwrap();
+    assert_eq!(users.len(), 2);
+    assert_eq!(users[0]["user"], "test0@test.test");
+    assert_eq!(users[0]["app_time"], 0.8);
+    assert_eq!(users[0]["code_time"], 1.0);
+    assert_eq!(users[0]["app_code_ratio"], 0.75);
+    assert_eq!(users[0]["apps"]["terminal"], 0.3);
+    assert_eq!(users[1]["user"], "test1@test.test");
+    assert_eq!(users[1]["code_time"], 0.0);
+    assert!(users[1]["app_code_ratio"].is_null());
+
+    // Apps are reported even when the group leaves them out of its stats
+    let response = client.put(format!("/services/gtm/api/groups/{}-{}-{}/path-rules/apps", provider, user, repo))
+        .header(bearer_header(&admin_jwt))
+        .header(ContentType::JSON)
+        .body(json!({ "include_apps": false }).to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let mut response = client.get(
+        format!("/services/gtm/api/{}-{}-{}/apps?start={}&end={}&interval={}&timezone={}&cumulative=true",
+                provider, user, repo, 0, 60 * 60 * 24 * 7, "day", "UTC"))
+        .header(bearer_header(&admin_jwt))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let body_json: Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+    let data = body_json["data"].as_array().unwrap();
+    assert_eq!(data[0]["time"], 0.7);
+    assert_eq!(data[6]["time"], 0.9);
+    assert_eq!(data[6]["apps"]["browser"]["time"], 0.7);
+
+    teardown_api_key(&jwt, &api_key);
+    teardown(&jwt);
+}
@@ -85,4 +85,45 @@ fn test_get_user_id() {
     assert!(id.unwrap() > 0);
 
     teardown(&jwt);
-}
\ No newline at end of file
+}
+#[test]
+fn test_my_dashboard() {
+    let jwt = setup();
+    let client = Client::new(gtm_api::rocket()).unwrap();
+
+    for path in vec!["timeline", "activity", "stats"] {
+        let response = client.get(
+            format!("/services/gtm/api/me/{}?start=0&end={}&interval=day&timezone=UTC", path, 60 * 60 * 24 * 7))
+            .dispatch();
+        assert_eq!(response.status(), Status::Unauthorized, "{}", path);
+    }
+
+    // Without linked emails the caller has no commits, but no group access is needed either.
+    let mut response = client.get(
+        format!("/services/gtm/api/me/timeline?start=0&end={}&interval=day&timezone=UTC", 60 * 60 * 24 * 7))
+        .header(bearer_header(&jwt))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let body_json: Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+    let intervals = body_json.as_array().unwrap();
+    assert_eq!(intervals.len(), 8);
+    assert!(intervals.iter().all(|i| i["users"] == 0));
+
+    let mut response = client.get(
+        format!("/services/gtm/api/me/activity?start=0&end={}&interval=week&timezone=UTC", 60 * 60 * 24 * 7))
+        .header(bearer_header(&jwt))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let body_json: Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+    assert_eq!(body_json.as_array().unwrap().len(), 7);
+
+    let mut response = client.get("/services/gtm/api/me/stats?start=0")
+        .header(bearer_header(&jwt))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let body_json: Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+    assert_eq!(body_json["users"], json!([]));
+    assert_eq!(body_json["files"], json!([]));
+
+    teardown(&jwt);
+}